@@ -0,0 +1,54 @@
+use godot::classes::Sprite2D;
+use godot::prelude::*;
+
+use super::AGENT_GROUP;
+use crate::behaviour::agent_behaviour::IAgentBehaviour;
+use crate::behaviour::utility::JobScore;
+
+/// The part every villager-like agent node shares: it joins `AGENT_GROUP`, starts its behaviour on
+/// the first physics tick and from then on ticks it and moves to wherever the behaviour says.
+#[derive(Default)]
+pub struct AgentNode {
+    behaviour: Option<Box<dyn IAgentBehaviour>>,
+}
+
+impl AgentNode {
+    pub fn ready(&self, mut node: Gd<Sprite2D>) {
+        node.add_to_group(AGENT_GROUP);
+    }
+
+    /// `make_behaviour` is called on the first tick, once the node's exported properties are set.
+    pub fn physics_process(
+        &mut self,
+        mut node: Gd<Sprite2D>,
+        delta: f64,
+        make_behaviour: impl FnOnce() -> Box<dyn IAgentBehaviour>,
+    ) {
+        let Some(behaviour) = self.behaviour.as_mut() else {
+            let mut behaviour = make_behaviour();
+            behaviour.start(node.get_name().to_string(), node.get_parent());
+            self.behaviour = Some(behaviour);
+            return;
+        };
+        let result = behaviour.tick(delta, node.get_position());
+        if let Some(next_position) = result.next_position {
+            node.set_position(next_position);
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(behaviour) = self.behaviour.as_mut() {
+            behaviour.cancel();
+        }
+    }
+
+    pub fn job_scores(&self) -> Vec<JobScore> {
+        self.behaviour.as_ref().map_or_else(Vec::new, |behaviour| behaviour.job_scores())
+    }
+
+    /// What the agent is doing, from the outermost behaviour in, for debugging. See
+    /// `StateStack::to_dictionary`, empty before the behaviour started.
+    pub fn state_stack(&self) -> Dictionary {
+        self.behaviour.as_ref().map_or_else(Dictionary::new, |behaviour| behaviour.state_stack().to_dictionary())
+    }
+}
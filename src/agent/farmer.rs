@@ -1,22 +1,27 @@
 use godot::classes::{ISprite2D, Sprite2D};
 use godot::prelude::*;
 
-use crate::behaviour::agent_behaviour::AgentBehaviour;
-use crate::behaviour::behaviour_regestry::make_farmer_agent_behaviour;
-use crate::behaviour::farmer_behaviour::FarmerBehaviour;
-
-#[derive(PartialEq, Eq, Clone, Copy)]
-enum FarmerState {
-    Starting,
-    Acting,
-}
+use super::agent_node::AgentNode;
+use crate::behaviour::agent_behaviour::IAgentBehaviour;
+use crate::behaviour::behaviour_regestry::{
+    load_farmer_tree_behaviour, make_farmer_agent_behaviour, make_farmer_goap_behaviour, make_farmer_tree_behaviour,
+    make_scheduled_behaviour, make_threat_aware_behaviour,
+};
 
 #[derive(GodotClass)]
 #[class(base=Sprite2D)]
 struct Farmer {
     base: Base<Sprite2D>,
-    behaviour: AgentBehaviour<FarmerBehaviour>,
-    state: FarmerState,
+    agent: AgentNode,
+    /// Run the farmer on a behaviour tree instead of the hand written state machine
+    #[export]
+    use_behaviour_tree: bool,
+    /// Tree file to run, the built in farmer tree is used when empty
+    #[export(file = "*.json")]
+    behaviour_tree_path: GString,
+    /// Let the farmer plan its work towards its goals, takes precedence over the behaviour tree
+    #[export]
+    use_planner: bool,
 }
 
 #[godot_api]
@@ -25,24 +30,45 @@ impl ISprite2D for Farmer {
         godot_print!("Hello, world!");
         let agent_name = base.to_gd().get_name().to_string();
         godot_print!("Agent name: {}", agent_name);
-        Self { base, behaviour: make_farmer_agent_behaviour(), state: FarmerState::Starting }
+        Self {
+            base,
+            agent: AgentNode::default(),
+            use_behaviour_tree: true,
+            behaviour_tree_path: "res://trees/farmer.json".into(),
+            use_planner: false,
+        }
+    }
+
+    fn ready(&mut self) {
+        self.agent.ready(self.base.to_gd());
     }
 
     fn physics_process(&mut self, delta: f64) {
-        match self.state {
-            FarmerState::Starting => {
-                self.behaviour.start(self.base().get_name().to_string(), self.base().get_parent());
-                self.state = FarmerState::Acting;
-            }
-            FarmerState::Acting => {
-                let result = self.behaviour.tick(delta, self.base().get_position());
-                match result.next_position {
-                    Some(next_position) => {
-                        self.base_mut().set_position(next_position);
-                    }
-                    None => {}
-                }
-            }
-        }
+        self.agent.physics_process(self.base.to_gd(), delta, || {
+            let behaviour: Box<dyn IAgentBehaviour> = if self.use_planner {
+                Box::new(make_farmer_goap_behaviour())
+            } else if !self.use_behaviour_tree {
+                Box::new(make_farmer_agent_behaviour())
+            } else if self.behaviour_tree_path.is_empty() {
+                Box::new(make_farmer_tree_behaviour())
+            } else {
+                Box::new(load_farmer_tree_behaviour(&self.behaviour_tree_path.to_string()))
+            };
+            Box::new(make_threat_aware_behaviour(Box::new(make_scheduled_behaviour(behaviour))))
+        });
+    }
+}
+
+#[godot_api]
+impl Farmer {
+    /// Player order to drop the current task, the farmer picks a new one on the next tick.
+    #[func]
+    fn cancel_task(&mut self) {
+        self.agent.cancel();
+    }
+
+    #[func]
+    fn get_state_stack(&self) -> Dictionary {
+        self.agent.state_stack()
     }
 }
@@ -0,0 +1,46 @@
+use godot::classes::{ISprite2D, Sprite2D};
+use godot::prelude::*;
+
+use super::agent_node::AgentNode;
+use crate::behaviour::behaviour_regestry::make_guard_agent_behaviour;
+use crate::behaviour::guard_behaviour::GuardPayer;
+
+/// Patrols the village and drives off wolves. Does not flee from them like other agents.
+#[derive(GodotClass)]
+#[class(base=Sprite2D)]
+struct Guard {
+    base: Base<Sprite2D>,
+    agent: AgentNode,
+    /// Let the households on the patrol route pay the guard instead of the treasury
+    #[export]
+    paid_by_households: bool,
+}
+
+#[godot_api]
+impl ISprite2D for Guard {
+    fn init(base: Base<Sprite2D>) -> Self {
+        Self { base, agent: AgentNode::default(), paid_by_households: false }
+    }
+
+    fn ready(&mut self) {
+        self.agent.ready(self.base.to_gd());
+    }
+
+    fn physics_process(&mut self, delta: f64) {
+        let payer = if self.paid_by_households { GuardPayer::Households } else { GuardPayer::Treasury };
+        self.agent.physics_process(self.base.to_gd(), delta, || Box::new(make_guard_agent_behaviour(payer)));
+    }
+}
+
+#[godot_api]
+impl Guard {
+    #[func]
+    fn cancel_task(&mut self) {
+        self.agent.cancel();
+    }
+
+    #[func]
+    fn get_state_stack(&self) -> Dictionary {
+        self.agent.state_stack()
+    }
+}
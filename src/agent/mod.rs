@@ -1,5 +1,21 @@
+// Agent nodes register #[func]s and exported properties through closures returning godot's large CallError
+#[allow(clippy::result_large_err)]
 pub mod farmer;
+#[allow(clippy::result_large_err)]
+pub mod berry_picker;
+#[allow(clippy::result_large_err)]
+pub mod woodcutter;
+#[allow(clippy::result_large_err)]
+pub mod miner;
+#[allow(clippy::result_large_err)]
+pub mod hauler;
+pub mod wolf;
+#[allow(clippy::result_large_err)]
+pub mod guard;
+#[allow(clippy::result_large_err)]
+pub mod villager;
 pub mod agent_regestry;
+pub mod agent_node;
 
-
-
+/// Group every villager-like agent joins, wolves excluded
+pub const AGENT_GROUP: &str = "agents";
@@ -0,0 +1,54 @@
+use godot::classes::{ISprite2D, Sprite2D};
+use godot::prelude::*;
+
+use super::agent_node::AgentNode;
+use crate::behaviour::behaviour_regestry::{
+    make_scheduled_behaviour, make_threat_aware_behaviour, make_villager_agent_behaviour,
+};
+
+/// Agent that is not tied to one profession, it takes whichever job scores highest.
+#[derive(GodotClass)]
+#[class(base=Sprite2D)]
+struct Villager {
+    base: Base<Sprite2D>,
+    agent: AgentNode,
+}
+
+#[godot_api]
+impl ISprite2D for Villager {
+    fn init(base: Base<Sprite2D>) -> Self {
+        Self { base, agent: AgentNode::default() }
+    }
+
+    fn ready(&mut self) {
+        self.agent.ready(self.base.to_gd());
+    }
+
+    fn physics_process(&mut self, delta: f64) {
+        self.agent.physics_process(self.base.to_gd(), delta, || {
+            Box::new(make_threat_aware_behaviour(Box::new(make_scheduled_behaviour(Box::new(
+                make_villager_agent_behaviour(),
+            )))))
+        });
+    }
+}
+
+#[godot_api]
+impl Villager {
+    #[func]
+    fn cancel_task(&mut self) {
+        self.agent.cancel();
+    }
+
+    /// One line per job with its score and the terms it is made of, from the last job choice.
+    #[func]
+    fn describe_job_scores(&self) -> GString {
+        let lines: Vec<String> = self.agent.job_scores().iter().map(|score| score.to_string()).collect();
+        lines.join("\n").into()
+    }
+
+    #[func]
+    fn get_state_stack(&self) -> Dictionary {
+        self.agent.state_stack()
+    }
+}
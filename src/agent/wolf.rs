@@ -0,0 +1,70 @@
+use godot::classes::{ISprite2D, Sprite2D};
+use godot::prelude::*;
+
+use crate::behaviour::behaviour_regestry::make_wolf_behaviour;
+use crate::behaviour::perception::nodes_in_group;
+use crate::behaviour::wolf_behaviour::WolfBehaviour;
+
+pub const WOLF_GROUP: &str = "wolves";
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum WolfState {
+    Starting,
+    Acting,
+}
+
+/// Predator that roams around its den. Agents flee when one gets too close.
+#[derive(GodotClass)]
+#[class(base=Sprite2D)]
+pub struct Wolf {
+    base: Base<Sprite2D>,
+    behaviour: WolfBehaviour,
+    state: WolfState,
+}
+
+#[godot_api]
+impl ISprite2D for Wolf {
+    fn init(base: Base<Sprite2D>) -> Self {
+        Self { base, behaviour: make_wolf_behaviour(), state: WolfState::Starting }
+    }
+
+    fn ready(&mut self) {
+        self.base_mut().add_to_group(WOLF_GROUP);
+    }
+
+    fn physics_process(&mut self, delta: f64) {
+        match self.state {
+            WolfState::Starting => {
+                let den = self.base().get_position();
+                let name = self.base().get_name().to_string();
+                self.behaviour.start(&name, den);
+                self.state = WolfState::Acting;
+            }
+            WolfState::Acting => {
+                let position = self.base().get_position();
+                if let Some(next_position) = self.behaviour.tick(delta, position) {
+                    self.base_mut().set_position(next_position);
+                }
+            }
+        }
+    }
+}
+
+impl Wolf {
+    pub fn positions() -> Vec<Vector2> {
+        nodes_in_group::<Wolf>(WOLF_GROUP).iter().map(|wolf| wolf.get_position()).collect()
+    }
+
+    /// Makes the wolf run off and settle further away from `scared_from`.
+    pub fn scare(&mut self, scared_from: Vector2) {
+        if self.behaviour.is_driven_off() {
+            return;
+        }
+        let position = self.base().get_position();
+        self.behaviour.scare(position, scared_from);
+    }
+
+    pub fn is_driven_off(&self) -> bool {
+        self.behaviour.is_driven_off()
+    }
+}
@@ -0,0 +1,47 @@
+use godot::classes::{ISprite2D, Sprite2D};
+use godot::prelude::*;
+
+use super::agent_node::AgentNode;
+use crate::behaviour::behaviour_regestry::{
+    make_scheduled_behaviour, make_threat_aware_behaviour, make_woodcutter_agent_behaviour,
+};
+
+/// Agent that fells trees for wood and replants them.
+#[derive(GodotClass)]
+#[class(base=Sprite2D)]
+struct Woodcutter {
+    base: Base<Sprite2D>,
+    agent: AgentNode,
+}
+
+#[godot_api]
+impl ISprite2D for Woodcutter {
+    fn init(base: Base<Sprite2D>) -> Self {
+        Self { base, agent: AgentNode::default() }
+    }
+
+    fn ready(&mut self) {
+        self.agent.ready(self.base.to_gd());
+    }
+
+    fn physics_process(&mut self, delta: f64) {
+        self.agent.physics_process(self.base.to_gd(), delta, || {
+            Box::new(make_threat_aware_behaviour(Box::new(make_scheduled_behaviour(Box::new(
+                make_woodcutter_agent_behaviour(),
+            )))))
+        });
+    }
+}
+
+#[godot_api]
+impl Woodcutter {
+    #[func]
+    fn cancel_task(&mut self) {
+        self.agent.cancel();
+    }
+
+    #[func]
+    fn get_state_stack(&self) -> Dictionary {
+        self.agent.state_stack()
+    }
+}
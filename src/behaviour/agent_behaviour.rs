@@ -2,14 +2,24 @@ use godot::prelude::*;
 
 use crate::behaviour::move_and_build_behaviour::MoveAndBuildBehaviour;
 use crate::building::Building;
+use crate::resources::inventory::InventoryResource;
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::transaction_log::TransactionReason;
+use crate::resources::treasury::Treasury;
+use crate::world::job_board::{Job, JobKind, Skill, CLAIM_TIMEOUT};
+use crate::world::job_office::JobOffice;
+use crate::world::world_clock::WorldClock;
 
+use super::introspection::StateStack;
 use super::move_behaviour::Result;
+use super::utility::JobScore;
 use super::work_behaviour::IWorkBehaviour;
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum AgentState {
     Idle,
     HomeBuilding,
+    HelpingBuild,
     Working,
 }
 
@@ -17,11 +27,32 @@ pub struct AgentBehaviourResult {
     pub next_position: Option<Vector2>,
 }
 
+/// Top level decision making of an agent, ticked by its Godot node.
+pub trait IAgentBehaviour {
+    fn start(&mut self, agent_name: String, parent_node: Option<Gd<Node>>);
+    fn tick(&mut self, delta: f64, agent_position: Vector2) -> AgentBehaviourResult;
+    /// Aborts whatever the agent is doing, e.g. on a player order or when danger appears.
+    fn cancel(&mut self);
+    /// Continues the interrupted task after the agent was not ticked for a while and moved elsewhere.
+    /// Takes back whatever `pause` let go of.
+    fn resume(&mut self, agent_position: Vector2);
+    /// Puts the task on hold for a long time, e.g. overnight. Anything shared with other agents is
+    /// let go of so they are not kept waiting, `resume` takes it back.
+    fn pause(&mut self) {}
+    fn home_position(&self) -> Option<Vector2>;
+    /// How the agent rated its jobs the last time it chose one.
+    fn job_scores(&self) -> Vec<JobScore>;
+    /// What the agent is doing right now, from this behaviour down to the innermost one.
+    fn state_stack(&self) -> StateStack;
+}
+
 pub struct AgentBehaviour<T: IWorkBehaviour> {
     state: AgentState,
     home_build_behaviour: MoveAndBuildBehaviour<Building>,
     home: Option<Gd<Building>>,
     work_behaviour: T,
+    /// Job board job the agent is working on
+    job: Option<Job>,
     agent_name: String,
     parent_node: Option<Gd<Node>>,
 }
@@ -30,20 +61,149 @@ impl<T: IWorkBehaviour> AgentBehaviour<T> {
     pub fn new(home_build_behaviour: MoveAndBuildBehaviour<Building>, work_behaviour: T) -> Self {
         Self {
             state: AgentState::Idle,
-            home_build_behaviour: home_build_behaviour,
+            home_build_behaviour,
             home: None,
-            work_behaviour: work_behaviour,
+            work_behaviour,
+            job: None,
             agent_name: "".to_string(),
             parent_node: None,
         }
     }
 
-    pub fn tick(&mut self, delta: f64, agent_position: Vector2) -> AgentBehaviourResult {
+    fn start_home_building(&mut self, agent_position: Vector2) {
+        godot_print!("Agent {} Starting home build", self.agent_name);
+        self.home = Some(self.home_build_behaviour.start_construction(
+            agent_position,
+            self.agent_name.clone(),
+            self.parent_node.clone(),
+        ));
+        self.state = AgentState::HomeBuilding;
+    }
+
+    /// Every agent can help build, the work behaviour may know more.
+    fn skills(&self) -> Vec<Skill> {
+        let mut skills = vec![Skill::Building];
+        skills.extend(self.work_behaviour.skills());
+        skills
+    }
+
+    /// Claims the best job on the board the agent is skilled for and starts on it.
+    fn start_board_job(&mut self, agent_position: Vector2) -> bool {
+        let now = WorldClock::seconds();
+        let skills = self.skills();
+        let reach = self.home_build_behaviour.help_radius();
+        let mut office = JobOffice::singleton();
+        let job = {
+            let mut office = office.bind_mut();
+            office.board.expire_claims(now);
+            office.board.claim(&self.agent_name, &skills, agent_position, reach, now, CLAIM_TIMEOUT)
+        };
+        let Some(job) = job else {
+            return false;
+        };
+        godot_print!("Agent {} Claimed {:?} job {}", self.agent_name, job.kind, job.id);
+        let started = match job.kind {
+            JobKind::Construct => self.start_helping(&job, agent_position),
+            _ => self.start_job_work(&job, agent_position),
+        };
+        if !started {
+            office.bind_mut().board.release(job.id, &self.agent_name);
+            return false;
+        }
+        self.job = Some(job);
+        true
+    }
+
+    /// Helps finish a home someone else started.
+    fn start_helping(&mut self, job: &Job, agent_position: Vector2) -> bool {
+        let Ok(site) = Gd::<Building>::try_from_instance_id(InstanceId::from_i64(job.poster)) else {
+            JobOffice::singleton().bind_mut().board.complete(job.id);
+            return false;
+        };
+        if !self.home_build_behaviour.start_joining_construction(site, agent_position, self.agent_name.clone()) {
+            return false;
+        }
+        self.state = AgentState::HelpingBuild;
+        true
+    }
+
+    /// Hands the job to the work behaviour, which does it as part of its work.
+    fn start_job_work(&mut self, job: &Job, agent_position: Vector2) -> bool {
+        self.work_behaviour.start_work(
+            self.home.as_ref().unwrap().clone(),
+            self.agent_name.clone(),
+            self.parent_node.clone(),
+        );
+        if !self.work_behaviour.take_job(job, agent_position) {
+            return false;
+        }
+        self.state = AgentState::Working;
+        true
+    }
+
+    /// Keeps the claim on the job from lapsing, or claims it again after a pause. False once
+    /// someone else has the job.
+    fn renew_job(&self) -> bool {
+        let Some(job) = self.job.as_ref() else {
+            return true;
+        };
+        let now = WorldClock::seconds();
+        JobOffice::singleton().bind_mut().board.claim_posted(job.poster, job.kind, &self.agent_name, now, CLAIM_TIMEOUT)
+    }
+
+    fn release_job(&mut self) {
+        if let Some(job) = self.job.take() {
+            JobOffice::singleton().bind_mut().board.release(job.id, &self.agent_name);
+        }
+    }
+
+    /// Takes the finished job off the board, the treasury pays its wage into the home.
+    fn finish_job(&mut self) {
+        let Some(job) = self.job.take() else {
+            return;
+        };
+        JobOffice::singleton().bind_mut().board.complete(job.id);
+        let Some(mut home) = self.home.clone().filter(|home| home.is_instance_valid()) else {
+            return;
+        };
+        if job.wage <= 0 {
+            return;
+        }
+        let context = InventoryLedger::context(&self.agent_name, TransactionReason::Wage);
+        let mut treasury = Treasury::singleton();
+        InventoryLedger::singleton().bind_mut().log.transfer(
+            &mut treasury.bind_mut().inventory,
+            &mut home.bind_mut().inventory,
+            InventoryResource::Coins,
+            job.wage,
+            &context,
+        );
+    }
+
+    fn start_working(&mut self) {
+        godot_print!("Agent {} Starting work", self.agent_name);
+        self.work_behaviour.start_work(
+            self.home.as_ref().unwrap().clone(),
+            self.agent_name.clone(),
+            self.parent_node.clone(),
+        );
+        self.state = AgentState::Working;
+    }
+}
+
+impl<T: IWorkBehaviour> IAgentBehaviour for AgentBehaviour<T> {
+    fn tick(&mut self, delta: f64, agent_position: Vector2) -> AgentBehaviourResult {
         loop {
             match self.state {
                 AgentState::Idle => {
+                    if self.home.as_ref().is_some_and(|home| !home.is_instance_valid()) {
+                        godot_print!("Agent {} Home was lost", self.agent_name);
+                        self.home = None;
+                    }
                     if self.home.is_none() {
                         self.start_home_building(agent_position);
+                    } else if self.start_board_job(agent_position) {
+                        continue;
                     } else if self.work_behaviour.is_work_available() {
                         self.start_working();
                     }
@@ -53,52 +213,132 @@ impl<T: IWorkBehaviour> AgentBehaviour<T> {
                     let (result, next_position) = self.home_build_behaviour.build(delta);
                     match result {
                         Result::Running => {
-                            return AgentBehaviourResult { next_position: next_position };
+                            return AgentBehaviourResult { next_position };
                         }
                         Result::Success => {
                             godot_print!("Agent {} Home build complete", self.agent_name);
                             self.state = AgentState::Idle;
                         }
+                        Result::Failure(reason) => {
+                            // The home build behaviour already removed the unfinished home
+                            godot_print!("Agent {} Home build failed {:?}", self.agent_name, reason);
+                            self.home = None;
+                            self.state = AgentState::Idle;
+                            return AgentBehaviourResult { next_position: None };
+                        }
+                    }
+                }
+                AgentState::HelpingBuild => {
+                    if !self.renew_job() {
+                        godot_print!("Agent {} Lost the job to someone else", self.agent_name);
+                        self.cancel();
+                        return AgentBehaviourResult { next_position: None };
+                    }
+                    let (result, next_position) = self.home_build_behaviour.build(delta);
+                    match result {
+                        Result::Running => {
+                            return AgentBehaviourResult { next_position };
+                        }
+                        Result::Success => {
+                            godot_print!("Agent {} Helped finish a home", self.agent_name);
+                            self.finish_job();
+                            self.state = AgentState::Idle;
+                        }
+                        Result::Failure(reason) => {
+                            godot_print!("Agent {} Helping failed {:?}", self.agent_name, reason);
+                            self.release_job();
+                            self.state = AgentState::Idle;
+                            return AgentBehaviourResult { next_position: None };
+                        }
                     }
                 }
                 AgentState::Working => {
+                    if !self.renew_job() {
+                        godot_print!("Agent {} Lost the job to someone else", self.agent_name);
+                        self.cancel();
+                        return AgentBehaviourResult { next_position: None };
+                    }
                     let work_result = self.work_behaviour.work(delta, agent_position);
                     match work_result.result {
                         Result::Running => {
                             return AgentBehaviourResult { next_position: work_result.next_position };
                         }
                         Result::Success => {
+                            self.finish_job();
                             self.state = AgentState::Idle;
                         }
+                        Result::Failure(reason) => {
+                            // The work behaviour cleaned up after itself, try again next tick
+                            godot_print!("Agent {} Work failed {:?}", self.agent_name, reason);
+                            self.release_job();
+                            self.state = AgentState::Idle;
+                            return AgentBehaviourResult { next_position: None };
+                        }
                     }
                 }
             }
         }
     }
 
-    pub fn start(&mut self, agent_name: String, parent_node: Option<Gd<Node>>) {
-        self.agent_name = agent_name;
+    fn cancel(&mut self) {
+        godot_print!("Agent {} Cancelling task", self.agent_name);
+        match self.state {
+            AgentState::Idle => {}
+            AgentState::HomeBuilding => {
+                self.home_build_behaviour.cancel();
+                self.home = None;
+            }
+            AgentState::HelpingBuild => {
+                self.home_build_behaviour.cancel();
+            }
+            AgentState::Working => {
+                self.work_behaviour.cancel();
+            }
+        }
+        self.release_job();
         self.state = AgentState::Idle;
-        self.parent_node = parent_node;
     }
 
-    fn start_home_building(&mut self, agent_position: Vector2) {
-        godot_print!("Agent {} Starting home build", self.agent_name);
-        self.home = Some(self.home_build_behaviour.start_construction(
-            agent_position,
-            self.agent_name.clone(),
-            self.parent_node.clone(),
-        ));
-        self.state = AgentState::HomeBuilding;
+    fn resume(&mut self, agent_position: Vector2) {
+        match self.state {
+            AgentState::Idle => {}
+            AgentState::HomeBuilding | AgentState::HelpingBuild => self.home_build_behaviour.resume(agent_position),
+            AgentState::Working => self.work_behaviour.resume(agent_position),
+        }
     }
 
-    fn start_working(&mut self) {
-        godot_print!("Agent {} Starting work", self.agent_name);
-        self.work_behaviour.start_work(
-            self.home.as_ref().unwrap().clone(),
-            self.agent_name.clone(),
-            self.parent_node.clone(),
-        );
-        self.state = AgentState::Working;
+    fn pause(&mut self) {
+        // The job is open to others while the agent is away, `renew_job` claims it back
+        if let Some(job) = self.job.as_ref() {
+            JobOffice::singleton().bind_mut().board.release(job.id, &self.agent_name);
+        }
+        match self.state {
+            AgentState::Idle => {}
+            AgentState::HomeBuilding | AgentState::HelpingBuild => self.home_build_behaviour.pause(),
+            AgentState::Working => self.work_behaviour.pause(),
+        }
+    }
+
+    fn home_position(&self) -> Option<Vector2> {
+        self.home.as_ref().filter(|home| home.is_instance_valid()).map(|home| home.bind().base().get_position())
+    }
+
+    fn job_scores(&self) -> Vec<JobScore> {
+        self.work_behaviour.job_scores()
+    }
+
+    fn state_stack(&self) -> StateStack {
+        let stack = StateStack::of("AgentBehaviour", self.state);
+        match self.state {
+            AgentState::Idle => stack,
+            AgentState::HomeBuilding | AgentState::HelpingBuild => stack.then(self.home_build_behaviour.state_stack()),
+            AgentState::Working => stack.then(self.work_behaviour.state_stack()),
+        }
+    }
+
+    fn start(&mut self, agent_name: String, parent_node: Option<Gd<Node>>) {
+        self.agent_name = agent_name;
+        self.state = AgentState::Idle;
+        self.parent_node = parent_node;
     }
 }
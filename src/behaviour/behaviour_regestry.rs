@@ -1,27 +1,149 @@
+use bonsai_bt::{Action, Behavior, Select, Sequence, Wait};
 use godot::builtin::Vector2;
+use godot::classes::FileAccess;
+use godot::global::godot_error;
 
-use crate::building::{Building, Field};
+use crate::building::{Building, Field, Quarry};
+use crate::resources::prices::Prices;
 
 use super::{
-    agent_behaviour::AgentBehaviour,
-    farmer_behaviour::{FarmerBehaviour, FarmerBehaviourConfig},
+    agent_behaviour::{AgentBehaviour, IAgentBehaviour},
+    berry_picker_behaviour::{BerryPickerBehaviour, BerryPickerBehaviourConfig},
+    farmer_behaviour::{FarmerBehaviour, FarmerBehaviourConfig, FIELD_HARVEST_YIELD},
+    goap::{Condition, Effect, Goal, PlannerAction, WorldFact},
+    goap_behaviour::{GoapAgentBehaviour, GoapBehaviourConfig},
+    guard_behaviour::{GuardBehaviour, GuardBehaviourConfig, GuardPayer},
+    hauler_behaviour::{HaulerBehaviour, HaulerBehaviourConfig},
+    job_selector::{CareerConfig, Job, JobSelector, JobSelectorConfig},
     move_and_build_behaviour::{MoveAndBuildBehaviour, MoveAndBuildBehaviourConfig},
     move_behaviour::{MoveBehaviour, MoveBehaviourConfig},
+    schedule_behaviour::ScheduledBehaviour,
+    steering::SteeringConfig,
+    threat_awareness::{ThreatAwareBehaviour, ThreatAwarenessConfig},
+    tree_behaviour::{ActionExecutor, AgentAction, AgentBlackboard, BuildingKind, MoveTarget, TreeAgentBehaviour},
+    tree_loader::{load_behaviour_tree, TreeNodeRegistry},
+    utility::UtilityWeights,
+    wolf_behaviour::{WolfBehaviour, WolfBehaviourConfig},
+    miner_behaviour::{MinerBehaviour, MinerBehaviourConfig},
+    woodcutter_behaviour::{WoodcutterBehaviour, WoodcutterBehaviourConfig},
 };
 
+fn make_steering_config() -> SteeringConfig {
+    SteeringConfig {
+        separation_radius: 60.0,
+        avoidance_horizon: 1.0,
+        max_steering_share: 0.6,
+        queue_radius: 250.0,
+        queue_spacing: 80.0,
+    }
+}
+
 fn make_move_behaviour_config() -> MoveBehaviourConfig {
-    MoveBehaviourConfig { speed: 100.0, max_step_height: 20.0, step_period: 0.1 }
+    MoveBehaviourConfig {
+        speed: 100.0,
+        max_step_height: 20.0,
+        step_period: 0.1,
+        pathfinding: true,
+        steering: Some(make_steering_config()),
+        stuck_timeout: 10.0,
+    }
 }
 
 fn make_farmer_behaviour_config() -> FarmerBehaviourConfig {
-    FarmerBehaviourConfig { max_field_count: 3, field_building_radius: 100.0 }
+    FarmerBehaviourConfig {
+        max_field_count: 3,
+        field_building_radius: 100.0,
+        carry_capacity: 3,
+        fallow_duration: 5.0,
+    }
+}
+
+fn make_woodcutter_behaviour_config() -> WoodcutterBehaviourConfig {
+    WoodcutterBehaviourConfig {
+        carry_capacity: 3,
+        fell_duration: 3.0,
+        replant_duration: 1.0,
+        replant: true,
+        work_offset: Vector2::new(0.0, 100.0),
+    }
+}
+
+fn make_miner_behaviour_config() -> MinerBehaviourConfig {
+    MinerBehaviourConfig { carry_capacity: 4, extraction_rate: 1.0, work_offset: Vector2::new(0.0, 100.0) }
+}
+
+fn make_hauler_behaviour_config() -> HaulerBehaviourConfig {
+    HaulerBehaviourConfig { carry_capacity: 6, fee_per_unit: 1 }
+}
+
+fn make_berry_picker_behaviour_config() -> BerryPickerBehaviourConfig {
+    BerryPickerBehaviourConfig { carry_capacity: 4, gather_duration: 1.5, gather_offset: Vector2::new(0.0, 100.0) }
+}
+
+fn make_guard_behaviour_config(payer: GuardPayer) -> GuardBehaviourConfig {
+    GuardBehaviourConfig {
+        sight_radius: 900.0,
+        scare_distance: 150.0,
+        give_up_distance: 1400.0,
+        max_patrol_stops: 6,
+        patrol_offset: Vector2::new(0.0, 150.0),
+        rest_duration: 5.0,
+        wage: 2,
+        payer,
+    }
+}
+
+fn make_guard_move_behaviour_config() -> MoveBehaviourConfig {
+    MoveBehaviourConfig {
+        speed: 150.0,
+        max_step_height: 20.0,
+        step_period: 0.08,
+        pathfinding: true,
+        steering: Some(make_steering_config()),
+        stuck_timeout: 10.0,
+    }
+}
+
+fn make_job_selector_config() -> JobSelectorConfig {
+    JobSelectorConfig {
+        weights: UtilityWeights { household_need: 3.0, price: 0.5, distance: 1.0, skill: 1.0, distance_scale: 500.0 },
+        skill_gain: 0.05,
+        max_skill: 1.0,
+        career: CareerConfig {
+            wage_window: 120.0,
+            switch_threshold: 0.3,
+            switch_rate: 0.2,
+            minimum_stay: 60.0,
+            retraining_duration: 10.0,
+        },
+    }
+}
+
+fn make_threat_awareness_config() -> ThreatAwarenessConfig {
+    ThreatAwarenessConfig { flee_distance: 400.0, safe_distance: 700.0 }
+}
+
+fn make_flee_behaviour_config() -> MoveBehaviourConfig {
+    MoveBehaviourConfig {
+        speed: 180.0,
+        max_step_height: 25.0,
+        step_period: 0.07,
+        pathfinding: true,
+        steering: Some(make_steering_config()),
+        stuck_timeout: 10.0,
+    }
+}
+
+fn make_wolf_behaviour_config() -> WolfBehaviourConfig {
+    WolfBehaviourConfig { roam_radius: 800.0, rest_duration: 3.0, driven_off_distance: 2000.0 }
 }
 
 fn make_field_build_behaviour_config() -> MoveAndBuildBehaviourConfig {
     MoveAndBuildBehaviourConfig {
         building_radius: 100.0,
         build_offset: Vector2::new(0.0, 100.0),
-        building_duration: 1.0,
+        build_rate: 1.0,
+        help_radius: 0.0,
     }
 }
 
@@ -29,7 +151,17 @@ fn make_home_build_behaviour_config() -> MoveAndBuildBehaviourConfig {
     MoveAndBuildBehaviourConfig {
         building_radius: 600.0,
         build_offset: Vector2::new(0.0, 100.0),
-        building_duration: 2.0,
+        build_rate: 2.0,
+        help_radius: 800.0,
+    }
+}
+
+fn make_quarry_build_behaviour_config() -> MoveAndBuildBehaviourConfig {
+    MoveAndBuildBehaviourConfig {
+        building_radius: 0.0,
+        build_offset: Vector2::new(0.0, 100.0),
+        build_rate: 1.0,
+        help_radius: 0.0,
     }
 }
 
@@ -52,3 +184,250 @@ fn make_farmer_behaviour() -> FarmerBehaviour {
 pub fn make_farmer_agent_behaviour() -> AgentBehaviour<FarmerBehaviour> {
     AgentBehaviour::new(make_home_build_behaviour(), make_farmer_behaviour())
 }
+
+fn make_berry_picker_behaviour() -> BerryPickerBehaviour {
+    BerryPickerBehaviour::new(make_move_behaviour(), make_berry_picker_behaviour_config())
+}
+
+pub fn make_berry_picker_agent_behaviour() -> AgentBehaviour<BerryPickerBehaviour> {
+    AgentBehaviour::new(make_home_build_behaviour(), make_berry_picker_behaviour())
+}
+
+fn make_woodcutter_behaviour() -> WoodcutterBehaviour {
+    WoodcutterBehaviour::new(make_move_behaviour(), make_woodcutter_behaviour_config())
+}
+
+pub fn make_woodcutter_agent_behaviour() -> AgentBehaviour<WoodcutterBehaviour> {
+    AgentBehaviour::new(make_home_build_behaviour(), make_woodcutter_behaviour())
+}
+
+fn make_quarry_build_behaviour() -> MoveAndBuildBehaviour<Quarry> {
+    MoveAndBuildBehaviour::new(make_move_behaviour(), make_quarry_build_behaviour_config())
+}
+
+fn make_miner_behaviour() -> MinerBehaviour {
+    MinerBehaviour::new(make_quarry_build_behaviour(), make_move_behaviour(), make_miner_behaviour_config())
+}
+
+pub fn make_miner_agent_behaviour() -> AgentBehaviour<MinerBehaviour> {
+    AgentBehaviour::new(make_home_build_behaviour(), make_miner_behaviour())
+}
+
+fn make_hauler_behaviour() -> HaulerBehaviour {
+    HaulerBehaviour::new(make_move_behaviour(), make_hauler_behaviour_config())
+}
+
+pub fn make_hauler_agent_behaviour() -> AgentBehaviour<HaulerBehaviour> {
+    AgentBehaviour::new(make_home_build_behaviour(), make_hauler_behaviour())
+}
+
+fn make_guard_behaviour(payer: GuardPayer) -> GuardBehaviour {
+    GuardBehaviour::new(MoveBehaviour::new(make_guard_move_behaviour_config()), make_guard_behaviour_config(payer))
+}
+
+pub fn make_guard_agent_behaviour(payer: GuardPayer) -> AgentBehaviour<GuardBehaviour> {
+    AgentBehaviour::new(make_home_build_behaviour(), make_guard_behaviour(payer))
+}
+
+/// Villager that can farm, pick berries or cut wood and changes profession when another one pays better.
+pub fn make_villager_agent_behaviour() -> AgentBehaviour<JobSelector> {
+    let jobs = vec![
+        Job::new(Box::new(make_farmer_behaviour())),
+        Job::new(Box::new(make_berry_picker_behaviour())),
+        Job::new(Box::new(make_woodcutter_behaviour())),
+    ];
+    let job_selector = JobSelector::new(jobs, make_move_behaviour(), make_job_selector_config(), Prices::default());
+    AgentBehaviour::new(make_home_build_behaviour(), job_selector)
+}
+
+fn make_farmer_behaviour_tree() -> Behavior<AgentAction> {
+    let ensure_home = Select(vec![Action(AgentAction::HasHome), Action(AgentAction::Construct(BuildingKind::Home))]);
+    let harvest = Sequence(vec![
+        Action(AgentAction::CanCarryHarvest),
+        Action(AgentAction::SelectGrownField),
+        Action(AgentAction::Deconstruct),
+        Action(AgentAction::Harvest),
+    ]);
+    let deliver = Sequence(vec![
+        Action(AgentAction::IsCarrying),
+        Action(AgentAction::MoveTo(MoveTarget::Home)),
+        Action(AgentAction::Deposit),
+    ]);
+    let replant_field = Sequence(vec![Action(AgentAction::SelectEmptyField), Action(AgentAction::Plant)]);
+    let build_field = Sequence(vec![Action(AgentAction::CanBuildField), Action(AgentAction::Construct(BuildingKind::Field))]);
+    Sequence(vec![ensure_home, Select(vec![harvest, deliver, replant_field, build_field, Wait(0.5)])])
+}
+
+fn make_action_executor() -> ActionExecutor {
+    ActionExecutor::new(make_move_behaviour(), make_home_build_behaviour(), make_field_build_behaviour())
+}
+
+fn make_farmer_tree_agent_behaviour(tree: Behavior<AgentAction>) -> TreeAgentBehaviour {
+    let farmer_config = make_farmer_behaviour_config();
+    let blackboard =
+        AgentBlackboard::new(farmer_config.carry_capacity, farmer_config.max_field_count, farmer_config.fallow_duration);
+    TreeAgentBehaviour::new(tree, blackboard, make_action_executor())
+}
+
+pub fn make_farmer_tree_behaviour() -> TreeAgentBehaviour {
+    make_farmer_tree_agent_behaviour(make_farmer_behaviour_tree())
+}
+
+/// Farmer running the tree stored at `tree_path`, falls back to the built in tree if the file is invalid.
+pub fn load_farmer_tree_behaviour(tree_path: &str) -> TreeAgentBehaviour {
+    let source = FileAccess::get_file_as_string(tree_path).to_string();
+    match load_behaviour_tree(&source, &TreeNodeRegistry::agent_nodes()) {
+        Ok(tree) => make_farmer_tree_agent_behaviour(tree),
+        Err(errors) => {
+            for error in errors {
+                godot_error!("{}: {}", tree_path, error);
+            }
+            make_farmer_tree_behaviour()
+        }
+    }
+}
+
+/// Build, harvest, move and deposit steps of a farmer described for the planner.
+pub fn make_farmer_planner_actions(
+    carry_capacity: i32,
+    max_field_count: usize,
+    fallow_duration: f32,
+) -> Vec<PlannerAction<AgentAction>> {
+    let harvested_field = if fallow_duration > 0.0 { WorldFact::FallowFieldCount } else { WorldFact::EmptyFieldCount };
+    vec![
+        PlannerAction {
+            name: "Build home",
+            steps: vec![AgentAction::Construct(BuildingKind::Home)],
+            cost: 10,
+            preconditions: vec![Condition::AtMost(WorldFact::HasHome, 0)],
+            effects: vec![Effect::Set(WorldFact::HasHome, 1)],
+        },
+        PlannerAction {
+            name: "Build field",
+            steps: vec![AgentAction::Construct(BuildingKind::Field)],
+            cost: 3,
+            preconditions: vec![
+                Condition::AtLeast(WorldFact::HasHome, 1),
+                Condition::AtMost(WorldFact::FieldCount, max_field_count as i32 - 1),
+            ],
+            effects: vec![Effect::Add(WorldFact::FieldCount, 1), Effect::Add(WorldFact::PlantedFieldCount, 1)],
+        },
+        PlannerAction {
+            name: "Replant field",
+            steps: vec![AgentAction::SelectEmptyField, AgentAction::Plant],
+            cost: 2,
+            preconditions: vec![Condition::AtLeast(WorldFact::EmptyFieldCount, 1)],
+            effects: vec![Effect::Add(WorldFact::EmptyFieldCount, -1), Effect::Add(WorldFact::PlantedFieldCount, 1)],
+        },
+        PlannerAction {
+            name: "Wait for fallow",
+            steps: vec![AgentAction::WaitForFallowField],
+            cost: 5,
+            preconditions: vec![
+                Condition::AtLeast(WorldFact::FallowFieldCount, 1),
+                Condition::AtMost(WorldFact::EmptyFieldCount, 0),
+            ],
+            effects: vec![
+                Effect::AddFrom(WorldFact::FallowFieldCount, WorldFact::EmptyFieldCount),
+                Effect::Set(WorldFact::FallowFieldCount, 0),
+            ],
+        },
+        PlannerAction {
+            name: "Wait for harvest",
+            steps: vec![AgentAction::WaitForGrownField],
+            cost: 5,
+            preconditions: vec![
+                Condition::AtLeast(WorldFact::PlantedFieldCount, 1),
+                Condition::AtMost(WorldFact::GrownFieldCount, 0),
+            ],
+            effects: vec![Effect::AddFrom(WorldFact::PlantedFieldCount, WorldFact::GrownFieldCount)],
+        },
+        PlannerAction {
+            name: "Harvest field",
+            steps: vec![AgentAction::SelectGrownField, AgentAction::Deconstruct, AgentAction::Harvest],
+            cost: 2,
+            preconditions: vec![
+                Condition::AtLeast(WorldFact::GrownFieldCount, 1),
+                Condition::AtMost(WorldFact::CarriedWheat, carry_capacity - FIELD_HARVEST_YIELD),
+            ],
+            effects: vec![
+                Effect::Add(WorldFact::GrownFieldCount, -1),
+                Effect::Add(WorldFact::PlantedFieldCount, -1),
+                Effect::Add(harvested_field, 1),
+                Effect::Add(WorldFact::CarriedWheat, FIELD_HARVEST_YIELD),
+            ],
+        },
+        PlannerAction {
+            name: "Deliver wheat",
+            steps: vec![AgentAction::MoveTo(MoveTarget::Home), AgentAction::Deposit],
+            cost: 2,
+            preconditions: vec![Condition::AtLeast(WorldFact::HasHome, 1), Condition::AtLeast(WorldFact::CarriedWheat, 1)],
+            effects: vec![
+                Effect::AddFrom(WorldFact::CarriedWheat, WorldFact::HouseholdFood),
+                Effect::Set(WorldFact::CarriedWheat, 0),
+            ],
+        },
+        PlannerAction {
+            name: "Supply buyer",
+            steps: vec![AgentAction::SelectBuyer, AgentAction::MoveTo(MoveTarget::TargetBuyer), AgentAction::Supply],
+            cost: 3,
+            preconditions: vec![
+                Condition::AtLeast(WorldFact::MarketHasBuyers, 1),
+                Condition::AtLeast(WorldFact::CarriedWheat, 1),
+            ],
+            // Sensed again after the delivery, a buyer still short of food gets another one
+            effects: vec![Effect::Set(WorldFact::CarriedWheat, 0), Effect::Set(WorldFact::MarketHasBuyers, 0)],
+        },
+    ]
+}
+
+/// Goals in order of priority, the farmer works on the first one that is not met.
+pub fn make_farmer_goals(max_field_count: usize) -> Vec<Goal> {
+    vec![
+        Goal { name: "Stock food", conditions: vec![Condition::AtLeast(WorldFact::HouseholdFood, 10)] },
+        Goal {
+            name: "Plant fields",
+            conditions: vec![
+                Condition::AtLeast(WorldFact::FieldCount, max_field_count as i32),
+                Condition::AtMost(WorldFact::EmptyFieldCount, 0),
+            ],
+        },
+        Goal { name: "Supply buyers", conditions: vec![Condition::AtMost(WorldFact::MarketHasBuyers, 0)] },
+    ]
+}
+
+fn make_goap_behaviour_config() -> GoapBehaviourConfig {
+    GoapBehaviourConfig { max_expanded_states: 20_000, replan_interval: 1.0 }
+}
+
+/// Farmer that plans its work instead of following a fixed tree or state machine.
+pub fn make_farmer_goap_behaviour() -> GoapAgentBehaviour {
+    let farmer_config = make_farmer_behaviour_config();
+    let blackboard =
+        AgentBlackboard::new(farmer_config.carry_capacity, farmer_config.max_field_count, farmer_config.fallow_duration);
+    GoapAgentBehaviour::new(
+        blackboard,
+        make_action_executor(),
+        make_farmer_planner_actions(
+            farmer_config.carry_capacity,
+            farmer_config.max_field_count,
+            farmer_config.fallow_duration,
+        ),
+        make_farmer_goals(farmer_config.max_field_count),
+        make_goap_behaviour_config(),
+    )
+}
+
+/// Wraps an agent behaviour so the agent works by day and rests at home by night.
+pub fn make_scheduled_behaviour(behaviour: Box<dyn IAgentBehaviour>) -> ScheduledBehaviour {
+    ScheduledBehaviour::new(behaviour, make_move_behaviour())
+}
+
+/// Wraps an agent behaviour so the agent flees from nearby wolves.
+pub fn make_threat_aware_behaviour(behaviour: Box<dyn IAgentBehaviour>) -> ThreatAwareBehaviour {
+    ThreatAwareBehaviour::new(behaviour, MoveBehaviour::new(make_flee_behaviour_config()), make_threat_awareness_config())
+}
+
+pub fn make_wolf_behaviour() -> WolfBehaviour {
+    WolfBehaviour::new(make_move_behaviour(), make_wolf_behaviour_config())
+}
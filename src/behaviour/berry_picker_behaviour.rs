@@ -0,0 +1,229 @@
+use super::introspection::StateStack;
+use super::move_behaviour::{FailureReason, MoveBehaviour, Result};
+use super::utility::{household_need, UtilityInputs};
+use super::work_behaviour::{leave_for_hauler, return_home, start_returning_home, IWorkBehaviour, WorkResult};
+use crate::building::{Building, Bush};
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::prices::Prices;
+use crate::resources::transaction_log::InventoryOwner;
+use godot::prelude::*;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum BerryPickerState {
+    Idle,
+    MovingToBush,
+    Gathering,
+    ReturningToHome,
+}
+
+pub struct BerryPickerBehaviourConfig {
+    pub carry_capacity: i32,
+    pub gather_duration: f32,
+    pub gather_offset: Vector2,
+}
+
+pub struct BerryPickerBehaviour {
+    state: BerryPickerState,
+    move_behaviour: MoveBehaviour,
+    config: BerryPickerBehaviourConfig,
+    target_bush: Option<Gd<Bush>>,
+    gather_progress: f32,
+    inventory: Inventory,
+    agent_name: String,
+    home: Option<Gd<Building>>,
+}
+
+impl IWorkBehaviour for BerryPickerBehaviour {
+    fn work(&mut self, delta: f64, agent_position: Vector2) -> WorkResult {
+        loop {
+            match self.state {
+                BerryPickerState::Idle => {
+                    let bush =
+                        if self.inventory.is_full() { None } else { find_nearest_bush_with_berries(agent_position) };
+                    if let Some(bush) = bush {
+                        self.start_moving_to_bush(bush, agent_position);
+                    } else if !self.inventory.is_empty() {
+                        if let Err(reason) = self.start_returning_to_home(agent_position) {
+                            return WorkResult { result: Result::Failure(reason), next_position: None };
+                        }
+                    } else {
+                        return WorkResult { result: Result::Success, next_position: None };
+                    }
+                }
+                BerryPickerState::MovingToBush => {
+                    if !self.is_target_bush_valid() {
+                        return self.fail(FailureReason::TargetLost);
+                    }
+                    let (result, next_position) = self.move_behaviour.move_agent(delta);
+                    match result {
+                        Result::Running => {
+                            return WorkResult { result, next_position: Some(next_position) };
+                        }
+                        Result::Success => {
+                            self.gather_progress = 0.0;
+                            self.state = BerryPickerState::Gathering;
+                            return WorkResult { result: Result::Running, next_position: Some(next_position) };
+                        }
+                        Result::Failure(reason) => return self.fail(reason),
+                    }
+                }
+                BerryPickerState::Gathering => {
+                    if !self.is_target_bush_valid() {
+                        return self.fail(FailureReason::TargetLost);
+                    }
+                    self.gather_progress += delta as f32;
+                    if self.gather_progress < self.config.gather_duration {
+                        return WorkResult { result: Result::Running, next_position: None };
+                    }
+                    self.finish_gathering();
+                }
+                BerryPickerState::ReturningToHome => {
+                    let work_result = return_home(
+                        &mut self.move_behaviour,
+                        self.home.as_mut(),
+                        &mut self.inventory,
+                        delta,
+                        &self.agent_name,
+                    );
+                    if work_result.result != Result::Running {
+                        // Whatever is carried stays in the inventory for the next trip
+                        self.state = BerryPickerState::Idle;
+                    }
+                    return work_result;
+                }
+            }
+        }
+    }
+
+    fn start_work(&mut self, home: Gd<Building>, agent_name: String, _parent_node: Option<Gd<Node>>) {
+        self.home = Some(home);
+        self.state = BerryPickerState::Idle;
+        self.inventory.set_owner(InventoryOwner::Agent(agent_name.clone()));
+        self.agent_name = agent_name;
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        match self.state {
+            BerryPickerState::Idle => {}
+            // Gathering restarts once the picker is back at the bush
+            BerryPickerState::MovingToBush | BerryPickerState::Gathering => {
+                self.move_behaviour.resume(agent_position);
+                self.state = BerryPickerState::MovingToBush;
+            }
+            BerryPickerState::ReturningToHome => self.move_behaviour.resume(agent_position),
+        }
+    }
+
+    fn is_work_available(&self) -> bool {
+        !self.inventory.is_empty() || Bush::all().iter().any(|bush| bush.bind().has_berries())
+    }
+
+    fn is_idle(&self) -> bool {
+        self.state == BerryPickerState::Idle
+    }
+
+    fn carried_goods(&mut self) -> Option<&mut Inventory> {
+        Some(&mut self.inventory)
+    }
+
+    fn job_name(&self) -> &'static str {
+        "Berry picker"
+    }
+
+    fn state_stack(&self) -> StateStack {
+        let stack = StateStack::of("BerryPickerBehaviour", self.state);
+        match self.state {
+            BerryPickerState::MovingToBush | BerryPickerState::ReturningToHome => {
+                stack.then(self.move_behaviour.state_stack())
+            }
+            _ => stack,
+        }
+    }
+
+    fn utility_inputs(&self, agent_position: Vector2, home: &Gd<Building>, prices: &Prices) -> UtilityInputs {
+        let distance = find_nearest_bush_with_berries(agent_position)
+            .map_or(0.0, |bush| agent_position.distance_to(bush.bind().base().get_position()));
+        UtilityInputs {
+            household_need: household_need(&home.bind().inventory, InventoryResource::Berries),
+            price: prices.price(InventoryResource::Berries) * self.config.carry_capacity as f32,
+            distance,
+        }
+    }
+
+    fn cancel(&mut self) {
+        godot_print!("Agent {}: Berry picker cancelled in state {:?}", self.agent_name, self.state);
+        self.move_behaviour.stop();
+        self.target_bush = None;
+        self.state = BerryPickerState::Idle;
+    }
+}
+
+/// Closest bush that still has berries to gather.
+pub fn find_nearest_bush_with_berries(position: Vector2) -> Option<Gd<Bush>> {
+    let bushes = Bush::all();
+    let stocks: Vec<(Vector2, i32)> =
+        bushes.iter().map(|bush| (bush.bind().base().get_position(), bush.bind().berry_count())).collect();
+    nearest_bush_with_berries(position, &stocks).map(|index| bushes[index].clone())
+}
+
+/// Index of the closest of `bushes`, given by position and berry count, with berries left.
+pub fn nearest_bush_with_berries(position: Vector2, bushes: &[(Vector2, i32)]) -> Option<usize> {
+    bushes
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, berries))| *berries > 0)
+        .min_by(|(_, (a, _)), (_, (b, _))| a.distance_to(position).total_cmp(&b.distance_to(position)))
+        .map(|(index, _)| index)
+}
+
+impl BerryPickerBehaviour {
+    pub fn new(move_behaviour: MoveBehaviour, config: BerryPickerBehaviourConfig) -> Self {
+        Self {
+            state: BerryPickerState::Idle,
+            move_behaviour,
+            target_bush: None,
+            gather_progress: 0.0,
+            inventory: Inventory::with_capacity(InventoryOwner::default(), config.carry_capacity),
+            config,
+            agent_name: String::new(),
+            home: None,
+        }
+    }
+
+    fn is_target_bush_valid(&self) -> bool {
+        self.target_bush.as_ref().is_some_and(|bush| bush.is_instance_valid())
+    }
+
+    fn start_moving_to_bush(&mut self, bush: Gd<Bush>, agent_position: Vector2) {
+        godot_print!("Agent {}: Starting move to bush", self.agent_name);
+        let gather_position = bush.bind().base().get_position() + self.config.gather_offset;
+        self.move_behaviour.start_moving(agent_position, gather_position);
+        self.target_bush = Some(bush);
+        self.state = BerryPickerState::MovingToBush;
+    }
+
+    fn finish_gathering(&mut self) {
+        let amount = self.inventory.free_space();
+        let mut bush = self.target_bush.take().unwrap();
+        let gathered = bush.bind_mut().gather(&mut self.inventory, amount, &self.agent_name);
+        godot_print!("Agent {}: Gathered {} berries", self.agent_name, gathered);
+        if self.inventory.is_full() {
+            leave_for_hauler(&mut self.inventory, &bush, self.home.as_ref(), &self.agent_name);
+        }
+        self.state = BerryPickerState::Idle;
+    }
+
+    fn start_returning_to_home(&mut self, agent_position: Vector2) -> std::result::Result<(), FailureReason> {
+        start_returning_home(&mut self.move_behaviour, self.home.as_ref(), agent_position, &self.agent_name)?;
+        self.state = BerryPickerState::ReturningToHome;
+        Ok(())
+    }
+
+    fn fail(&mut self, reason: FailureReason) -> WorkResult {
+        // Gathered berries are kept for the next trip home
+        self.move_behaviour.stop();
+        self.target_bush = None;
+        self.state = BerryPickerState::Idle;
+        WorkResult { result: Result::Failure(reason), next_position: None }
+    }
+}
@@ -1,16 +1,29 @@
-use super::free_space_manager::FreeSpaceManager;
+use super::introspection::StateStack;
 use super::move_and_build_behaviour::MoveAndBuildBehaviour;
-use super::move_behaviour::{MoveBehaviour, Result};
-use super::work_behaviour::{IWorkBehaviour, WorkResult};
+use super::move_behaviour::{FailureReason, MoveBehaviour, Result};
+use super::perception::nearest_within;
+use super::tour_planner::plan_shortest_tour;
+use super::utility::{household_need, UtilityInputs};
+use super::work_behaviour::{return_home, start_returning_home, IWorkBehaviour, WorkResult};
 use crate::building::Building;
 use crate::building::{Field, FieldState};
 use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::prices::Prices;
+use crate::resources::transaction_log::{InventoryOwner, TransactionReason};
+use crate::world::job_board::{Job, JobKind, Skill, CLAIM_TIMEOUT};
+use crate::world::job_office::JobOffice;
+use crate::world::world_clock::WorldClock;
 use godot::prelude::*;
 
+// Every harvested field yields this much wheat
+pub const FIELD_HARVEST_YIELD: i32 = 1;
+
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum FarmerState {
     Idle,
     FieldBuilding,
+    FieldPlanting,
     FieldRemoving,
     ReturningToHome,
 }
@@ -18,6 +31,9 @@ enum FarmerState {
 pub struct FarmerBehaviourConfig {
     pub max_field_count: usize,
     pub field_building_radius: f32,
+    pub carry_capacity: i32,
+    /// Seconds a harvested field lies fallow before it can be planted again, 0 to replant right away
+    pub fallow_duration: f32,
 }
 
 pub struct FarmerBehaviour {
@@ -26,7 +42,11 @@ pub struct FarmerBehaviour {
     move_behaviour: MoveBehaviour,
     fields: Vec<Gd<Field>>,
     config: FarmerBehaviourConfig,
+    /// Instance id of the field being built
+    building_field: Option<i64>,
     removing_field: Option<Gd<Field>>,
+    /// Home of the owner of a field harvested for the job board, the carried crop goes there
+    crop_home: Option<Gd<Building>>,
     inventory: Inventory,
     agent_name: String,
     home: Option<Gd<Building>>,
@@ -35,12 +55,22 @@ pub struct FarmerBehaviour {
 
 impl IWorkBehaviour for FarmerBehaviour {
     fn work(&mut self, delta: f64, agent_position: Vector2) -> WorkResult {
+        self.fields.retain(|field| field.is_instance_valid());
         loop {
-            godot_print!("Agent {}: Farmer state {}", self.agent_name, format!("{:?}", self.state));
             match self.state {
                 FarmerState::Idle => {
-                    if self.is_any_field_completed() {
-                        self.start_field_removing(agent_position);
+                    if self.crop_home.is_none() && can_carry_harvest(&self.inventory) && self.is_any_field_completed() {
+                        if !self.start_own_field_removing(agent_position) {
+                            return WorkResult { result: Result::Failure(FailureReason::TargetLost), next_position: None };
+                        }
+                    } else if !self.inventory.is_empty() {
+                        if let Err(reason) = self.start_returning_to_home(agent_position) {
+                            return WorkResult { result: Result::Failure(reason), next_position: None };
+                        }
+                    } else if let Some(field) = next_field_to_plant(&self.fields, agent_position) {
+                        if !self.start_field_planting(field, agent_position) {
+                            return WorkResult { result: Result::Failure(FailureReason::TargetLost), next_position: None };
+                        }
                     } else if self.fields.len() < self.config.max_field_count {
                         self.start_field_building(agent_position);
                     } else {
@@ -55,35 +85,80 @@ impl IWorkBehaviour for FarmerBehaviour {
                         }
                         Result::Success => {
                             godot_print!("Agent {} Field build complete", self.agent_name);
+                            self.building_field = None;
                             self.state = FarmerState::Idle;
-                            return WorkResult { result: Result::Success, next_position: next_position };
+                            return WorkResult { result: Result::Success, next_position };
+                        }
+                        Result::Failure(reason) => {
+                            // The field build behaviour already removed the unfinished field
+                            self.stop_field_task();
+                            self.state = FarmerState::Idle;
+                            return WorkResult { result: Result::Failure(reason), next_position };
                         }
                     }
                 }
-                FarmerState::FieldRemoving => {
+                FarmerState::FieldPlanting => {
                     let (result, next_position) = self.field_build_behaviour.build(delta);
                     match result {
                         Result::Running => {
                             return WorkResult { result, next_position };
                         }
                         Result::Success => {
-                            godot_print!("Agent {} Field removing complete", self.agent_name);
-                            self.finish_field_removing();
-                            self.start_returning_to_home(agent_position);
+                            godot_print!("Agent {} Field planting complete", self.agent_name);
+                            self.state = FarmerState::Idle;
+                            return WorkResult { result: Result::Success, next_position };
+                        }
+                        Result::Failure(reason) => {
+                            // The field stays, half planted, for the next try
+                            self.state = FarmerState::Idle;
+                            return WorkResult { result: Result::Failure(reason), next_position };
                         }
                     }
                 }
-                FarmerState::ReturningToHome => {
-                    let (result, next_position) = self.move_behaviour.move_agent(delta);
+                FarmerState::FieldRemoving => {
+                    let is_grown =
+                        |field: &Gd<Field>| field.is_instance_valid() && field.bind().state == FieldState::Grown;
+                    let still_ours =
+                        self.removing_field.as_ref().is_some_and(|field| is_grown(field) && self.claim_harvest(field));
+                    if !still_ours {
+                        // Somebody else got to the crop first
+                        self.field_build_behaviour.cancel();
+                        if let Some(field) = self.removing_field.take() {
+                            self.release_harvest_claim(&field);
+                        }
+                        self.state = FarmerState::Idle;
+                        return WorkResult { result: Result::Failure(FailureReason::TargetLost), next_position: None };
+                    }
+                    let (result, next_position) = self.field_build_behaviour.build(delta);
                     match result {
                         Result::Running => {
-                            return WorkResult { result, next_position: Some(next_position) };
+                            return WorkResult { result, next_position };
                         }
                         Result::Success => {
-                            self.finish_returning_to_home();
-                            return WorkResult { result: Result::Success, next_position: Some(next_position) };
+                            godot_print!("Agent {} Field removing complete", self.agent_name);
+                            self.finish_field_removing();
+                            self.state = FarmerState::Idle;
                         }
+                        Result::Failure(reason) => {
+                            self.stop_field_task();
+                            self.state = FarmerState::Idle;
+                            return WorkResult { result: Result::Failure(reason), next_position };
+                        }
+                    }
+                }
+                FarmerState::ReturningToHome => {
+                    // Fails once the field owner's home is gone, the crop then goes to the own home
+                    let mut home = self.crop_home.clone().or_else(|| self.home.clone());
+                    let work_result =
+                        return_home(&mut self.move_behaviour, home.as_mut(), &mut self.inventory, delta, &self.agent_name);
+                    if work_result.result == Result::Success {
+                        self.crop_home = None;
+                    }
+                    if work_result.result != Result::Running {
+                        // Whatever is carried stays in the inventory for the next trip
+                        self.state = FarmerState::Idle;
                     }
+                    return work_result;
                 }
             }
         }
@@ -92,15 +167,183 @@ impl IWorkBehaviour for FarmerBehaviour {
     fn start_work(&mut self, home: Gd<Building>, agent_name: String, parent_node: Option<Gd<Node>>) {
         self.home = Some(home);
         self.state = FarmerState::Idle;
+        self.inventory.set_owner(InventoryOwner::Agent(agent_name.clone()));
         self.agent_name = agent_name;
         self.parent_node = parent_node;
     }
 
+    fn cancel(&mut self) {
+        godot_print!("Agent {}: Farmer cancelled in state {:?}", self.agent_name, self.state);
+        match self.state {
+            FarmerState::Idle => {}
+            FarmerState::FieldBuilding | FarmerState::FieldRemoving => {
+                self.field_build_behaviour.cancel();
+                self.stop_field_task();
+            }
+            FarmerState::FieldPlanting => {
+                self.field_build_behaviour.cancel();
+            }
+            FarmerState::ReturningToHome => {
+                self.move_behaviour.stop();
+            }
+        }
+        self.state = FarmerState::Idle;
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        match self.state {
+            FarmerState::Idle => {}
+            FarmerState::FieldBuilding | FarmerState::FieldPlanting | FarmerState::FieldRemoving => {
+                self.field_build_behaviour.resume(agent_position)
+            }
+            FarmerState::ReturningToHome => self.move_behaviour.resume(agent_position),
+        }
+    }
+
+    fn pause(&mut self) {
+        match self.state {
+            // Planting joins the field as a helper, the slot is given up overnight
+            FarmerState::FieldPlanting => self.field_build_behaviour.pause(),
+            // Others may take the crop meanwhile, the harvest is claimed again on the next tick
+            FarmerState::FieldRemoving => {
+                if let Some(field) = self.removing_field.as_ref().filter(|field| field.is_instance_valid()) {
+                    self.release_harvest_claim(field);
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn is_work_available(&self) -> bool {
-        self.fields.len() < self.config.max_field_count || self.is_any_field_completed()
+        self.fields.len() < self.config.max_field_count
+            || self.is_any_field_completed()
+            || self.fields.iter().any(|field| field.is_instance_valid() && field.bind().is_replantable())
+            || !self.inventory.is_empty()
+    }
+
+    fn is_idle(&self) -> bool {
+        self.state == FarmerState::Idle
+    }
+
+    fn carried_goods(&mut self) -> Option<&mut Inventory> {
+        Some(&mut self.inventory)
+    }
+
+    fn abandon(&mut self) {
+        self.cancel();
+        godot_print!("Agent {}: Abandoning {} fields", self.agent_name, self.fields.len());
+        for mut field in self.fields.drain(..).filter(|field| field.is_instance_valid()) {
+            field.bind_mut().demolish();
+        }
+    }
+
+    fn job_name(&self) -> &'static str {
+        "Farmer"
+    }
+
+    fn state_stack(&self) -> StateStack {
+        let stack = StateStack::of("FarmerBehaviour", self.state);
+        match self.state {
+            FarmerState::FieldBuilding | FarmerState::FieldPlanting | FarmerState::FieldRemoving => {
+                stack.then(self.field_build_behaviour.state_stack())
+            }
+            FarmerState::ReturningToHome => stack.then(self.move_behaviour.state_stack()),
+            _ => stack,
+        }
+    }
+
+    fn skills(&self) -> Vec<Skill> {
+        vec![Skill::Farming]
+    }
+
+    /// Harvests someone else's grown field off the board, the wheat goes to the home of the field's
+    /// owner. Own fields are harvested as part of the usual work.
+    fn take_job(&mut self, job: &Job, agent_position: Vector2) -> bool {
+        if job.kind != JobKind::Harvest || !self.inventory.is_empty() {
+            return false;
+        }
+        let Ok(field) = Gd::<Field>::try_from_instance_id(InstanceId::from_i64(job.poster)) else {
+            return false;
+        };
+        if field.bind().state != FieldState::Grown || self.fields.contains(&field) {
+            return false;
+        }
+        self.start_field_removing(field, agent_position);
+        true
+    }
+
+    fn utility_inputs(&self, agent_position: Vector2, home: &Gd<Building>, prices: &Prices) -> UtilityInputs {
+        let home_position = home.bind().base().get_position();
+        // New fields are built next to home, grown ones are harvested where they are
+        let distance = self
+            .fields
+            .iter()
+            .filter(|field| field.is_instance_valid() && field.bind().state == FieldState::Grown)
+            .map(|field| agent_position.distance_to(field.bind().base().get_position()))
+            .fold(agent_position.distance_to(home_position), f32::min);
+        UtilityInputs {
+            household_need: household_need(&home.bind().inventory, InventoryResource::Wheat),
+            price: prices.price(InventoryResource::Wheat) * FIELD_HARVEST_YIELD as f32,
+            distance,
+        }
     }
 }
 
+/// Field a farmer is working on, by instance id
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FieldTask {
+    Building(i64),
+    Removing(i64),
+}
+
+/// Updates the farmer's fields after `task` stopped early, cancelled or failed. A field that was
+/// being built is gone and is taken off the list wherever it sits in it, a field that was being
+/// harvested is restored and stays.
+pub fn forget_stopped_field<F>(fields: &mut Vec<F>, task: FieldTask, instance_id: impl Fn(&F) -> i64) {
+    if let FieldTask::Building(building) = task {
+        fields.retain(|field| instance_id(field) != building);
+    }
+}
+
+pub fn field_instance_id(field: &Gd<Field>) -> i64 {
+    field.instance_id_unchecked().to_i64()
+}
+
+/// First field of the shortest tour that collects as many grown fields as the inventory can carry
+/// and then ends at home.
+pub fn next_field_to_harvest(
+    fields: &[Gd<Field>],
+    agent_position: Vector2,
+    home_position: Option<Vector2>,
+    inventory: &Inventory,
+) -> Option<Gd<Field>> {
+    let grown_fields: Vec<&Gd<Field>> =
+        fields.iter().filter(|field| field.is_instance_valid() && field.bind().state == FieldState::Grown).collect();
+    let field_positions: Vec<Vector2> = grown_fields.iter().map(|field| field.bind().base().get_position()).collect();
+    let max_stops = (inventory.free_space() / FIELD_HARVEST_YIELD) as usize;
+    let tour = plan_shortest_tour(agent_position, &field_positions, home_position, max_stops);
+    tour.first().map(|index| grown_fields[*index].clone())
+}
+
+/// Closest of the fields that is empty and can be planted again.
+pub fn next_field_to_plant(fields: &[Gd<Field>], agent_position: Vector2) -> Option<Gd<Field>> {
+    let empty_fields: Vec<&Gd<Field>> =
+        fields.iter().filter(|field| field.is_instance_valid() && field.bind().is_replantable()).collect();
+    let positions: Vec<Vector2> = empty_fields.iter().map(|field| field.bind().base().get_position()).collect();
+    nearest_within(agent_position, f32::INFINITY, &positions).map(|index| empty_fields[index].clone())
+}
+
+/// Leaves a deconstructed field empty for replanting and puts its yield into the inventory.
+pub fn harvest_field(field: &mut Gd<Field>, inventory: &mut Inventory, agent_name: &str, fallow_duration: f32) {
+    field.bind_mut().harvest(fallow_duration);
+    let context = InventoryLedger::context(agent_name, TransactionReason::Harvest);
+    InventoryLedger::singleton().bind_mut().log.produce(inventory, InventoryResource::Wheat, FIELD_HARVEST_YIELD, &context);
+}
+
+pub fn can_carry_harvest(inventory: &Inventory) -> bool {
+    inventory.free_space() >= FIELD_HARVEST_YIELD
+}
+
 impl FarmerBehaviour {
     pub fn new(
         field_build_behaviour: MoveAndBuildBehaviour<Field>,
@@ -109,12 +352,14 @@ impl FarmerBehaviour {
     ) -> Self {
         Self {
             state: FarmerState::Idle,
-            field_build_behaviour: field_build_behaviour,
-            move_behaviour: move_behaviour,
+            field_build_behaviour,
+            move_behaviour,
             fields: Vec::new(),
-            config: farmer_config,
+            building_field: None,
             removing_field: None,
-            inventory: Inventory::new(),
+            crop_home: None,
+            inventory: Inventory::with_capacity(InventoryOwner::default(), farmer_config.carry_capacity),
+            config: farmer_config,
             agent_name: "".to_string(),
             home: None,
             parent_node: None,
@@ -123,49 +368,120 @@ impl FarmerBehaviour {
 
     fn start_field_building(&mut self, agent_position: Vector2) {
         godot_print!("Agent {}: Starting field build", self.agent_name);
-        let field = self.field_build_behaviour.start_construction(agent_position, self.agent_name.clone(), self.parent_node.clone());
+        let mut field = self.field_build_behaviour.start_construction(agent_position, self.agent_name.clone(), self.parent_node.clone());
+        field.bind_mut().set_owner_home(self.home.clone());
+        self.building_field = Some(field_instance_id(&field));
         self.fields.push(field);
         self.state = FarmerState::FieldBuilding;
     }
 
+    /// Cleans up after building or harvesting a field stopped early, see `forget_stopped_field`.
+    fn stop_field_task(&mut self) {
+        let task = match self.state {
+            FarmerState::FieldBuilding => self.building_field.take().map(FieldTask::Building),
+            FarmerState::FieldRemoving => {
+                let field = self.removing_field.take();
+                if let Some(field) = field.as_ref() {
+                    self.release_harvest_claim(field);
+                }
+                field.map(|field| FieldTask::Removing(field_instance_id(&field)))
+            }
+            FarmerState::Idle | FarmerState::FieldPlanting | FarmerState::ReturningToHome => None,
+        };
+        if let Some(task) = task {
+            forget_stopped_field(&mut self.fields, task, field_instance_id);
+        }
+    }
+
+    fn start_field_planting(&mut self, field: Gd<Field>, agent_position: Vector2) -> bool {
+        godot_print!("Agent {}: Starting field planting", self.agent_name);
+        if !self.field_build_behaviour.start_joining_construction(field, agent_position, self.agent_name.clone()) {
+            return false;
+        }
+        self.state = FarmerState::FieldPlanting;
+        true
+    }
+
     fn is_any_field_completed(&self) -> bool {
-        self.fields.iter().any(|field| field.bind().state == FieldState::Grown)
+        !self.harvestable_fields().is_empty()
     }
 
-    fn start_field_removing(&mut self, agent_position: Vector2) {
+    /// Own grown fields whose harvest nobody else claimed on the job board.
+    fn harvestable_fields(&self) -> Vec<Gd<Field>> {
+        let office = JobOffice::singleton();
+        let office = office.bind();
+        self.fields
+            .iter()
+            .filter(|field| field.is_instance_valid() && field.bind().state == FieldState::Grown)
+            .filter(|field| {
+                !office.board.is_taken_by_others(field.instance_id().to_i64(), JobKind::Harvest, &self.agent_name)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Claims the harvest of the next own field and starts on it, false if someone claimed it first.
+    fn start_own_field_removing(&mut self, agent_position: Vector2) -> bool {
+        let home_position = self.home.as_ref().map(|home| home.bind().base().get_position());
+        let fields = self.harvestable_fields();
+        let field = next_field_to_harvest(&fields, agent_position, home_position, &self.inventory).unwrap();
+        if !self.claim_harvest(&field) {
+            return false;
+        }
+        self.start_field_removing(field, agent_position);
+        true
+    }
+
+    /// Claims the harvest of `field`, or renews the claim so it does not lapse while the farmer is
+    /// still at it. False once someone else has it.
+    fn claim_harvest(&self, field: &Gd<Field>) -> bool {
+        JobOffice::singleton().bind_mut().board.claim_posted(
+            field_instance_id(field),
+            JobKind::Harvest,
+            &self.agent_name,
+            WorldClock::seconds(),
+            CLAIM_TIMEOUT,
+        )
+    }
+
+    fn release_harvest_claim(&self, field: &Gd<Field>) {
+        let mut office = JobOffice::singleton();
+        let mut office = office.bind_mut();
+        if let Some(id) = office.board.find(field_instance_id(field), JobKind::Harvest).map(|job| job.id) {
+            office.board.release(id, &self.agent_name);
+        }
+    }
+
+    fn start_field_removing(&mut self, field: Gd<Field>, agent_position: Vector2) {
         godot_print!("Agent {}: Starting field removing", self.agent_name);
-        let field = self.fields.iter().find(|field| field.bind().state == FieldState::Grown).unwrap();
         self.removing_field = Some(field.clone());
-        self.field_build_behaviour.start_deconstruction(field.clone(), agent_position);
+        self.field_build_behaviour.start_deconstruction(field, agent_position);
         self.state = FarmerState::FieldRemoving;
     }
 
     fn finish_field_removing(&mut self) {
         godot_print!("Agent {} Field removing complete", self.agent_name);
-        let mut free_space_manager = FreeSpaceManager::singleton();
-        let field_instance_id = self.removing_field.as_ref().unwrap().instance_id();
-        self.fields.retain(|field| field.instance_id() != field_instance_id);
-        free_space_manager
-            .bind_mut()
-            .remove_occupied_position(self.removing_field.as_ref().unwrap().bind().base().get_position());
-        self.removing_field.as_mut().unwrap().bind_mut().base_mut().queue_free();
-        self.removing_field = None;
-        self.inventory.add(InventoryResource::Wheat, 1);
-    }
-
-    fn start_returning_to_home(&mut self, agent_position: Vector2) {
-        if self.home.is_none() {
-            self.state = FarmerState::Idle;
-            return;
-        }
-        godot_print!("Agent {} Starting returning to home", self.agent_name);
-        self.move_behaviour.start_moving(agent_position, self.home.as_ref().unwrap().bind().base().get_position());
-        self.state = FarmerState::ReturningToHome;
+        let mut field = self.removing_field.take().unwrap();
+        self.release_harvest_claim(&field);
+        harvest_field(&mut field, &mut self.inventory, &self.agent_name, self.config.fallow_duration);
+        let owner_home = field.bind().owner_home();
+        if owner_home.is_some() && owner_home != self.home {
+            self.crop_home = owner_home;
+        }
     }
 
-    fn finish_returning_to_home(&mut self) {
-        godot_print!("Agent {} Returning to home complete", self.agent_name);
-        self.home.as_mut().unwrap().bind_mut().inventory.move_full_inventory_from(&mut self.inventory);
-        self.state = FarmerState::Idle;
+    /// Where the carried crop goes, the field owner's home for a job board harvest.
+    fn crop_destination(&self) -> Option<Gd<Building>> {
+        self.crop_home.clone().or_else(|| self.home.clone()).filter(|home| home.is_instance_valid())
+    }
+
+    fn start_returning_to_home(&mut self, agent_position: Vector2) -> std::result::Result<(), FailureReason> {
+        if self.crop_home.as_ref().is_some_and(|home| !home.is_instance_valid()) {
+            self.crop_home = None;
+        }
+        let home = self.crop_destination();
+        start_returning_home(&mut self.move_behaviour, home.as_ref(), agent_position, &self.agent_name)?;
+        self.state = FarmerState::ReturningToHome;
+        Ok(())
     }
 }
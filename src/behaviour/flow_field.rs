@@ -0,0 +1,173 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use super::pathfinding::{Cell, CostGrid, OpenCell, NEIGHBOURS};
+
+/// Cells a flow field reaches in each direction from its goal. Kept well below the map radius so
+/// a building going up only drops the fields of goals near it; agents starting further out than
+/// this search their own path instead.
+pub const FLOW_FIELD_RADIUS: i32 = 12;
+/// Requests for a goal within `POPULAR_WINDOW` after which agents heading there share a flow field
+pub const POPULAR_AFTER: u32 = 3;
+/// Seconds over which the requests for a goal are counted, older ones no longer make it popular
+pub const POPULAR_WINDOW: f64 = 10.0;
+/// Flow fields kept at once, the least recently used one goes first
+pub const FLOW_FIELD_CAPACITY: usize = 32;
+
+struct FlowCell {
+    cost_to_goal: f32,
+    next: Cell,
+}
+
+/// Cheapest next step towards one goal from every cell around it, so any number of agents can
+/// head there without searching a path each. Costs and corners follow `pathfinding::find_path`,
+/// the goal can always be entered.
+pub struct FlowField {
+    goal: Cell,
+    radius: i32,
+    cells: HashMap<Cell, FlowCell>,
+}
+
+impl FlowField {
+    pub fn compute(grid: &impl CostGrid, goal: Cell, radius: i32) -> Self {
+        let mut field = Self { goal, radius, cells: HashMap::new() };
+        let passable = |cell: Cell| cell == goal || (field.covers(cell) && grid.move_cost(cell).is_some());
+        let mut cells = HashMap::from([(goal, FlowCell { cost_to_goal: 0.0, next: goal })]);
+        let mut open = BinaryHeap::from([OpenCell { estimate: 0.0, cell: goal }]);
+
+        while let Some(OpenCell { estimate, cell }) = open.pop() {
+            if estimate > cells[&cell].cost_to_goal {
+                continue;
+            }
+            // Walking from a neighbour into `cell` costs what entering `cell` costs
+            let enter_cost = grid.move_cost(cell).unwrap_or(1.0);
+            for (dx, dy) in NEIGHBOURS {
+                let from = (cell.0 + dx, cell.1 + dy);
+                if !passable(from) {
+                    continue;
+                }
+                let diagonal = dx != 0 && dy != 0;
+                if diagonal && (!passable((cell.0 + dx, cell.1)) || !passable((cell.0, cell.1 + dy))) {
+                    continue;
+                }
+                let step_cost = if diagonal { enter_cost * std::f32::consts::SQRT_2 } else { enter_cost };
+                let cost_to_goal = estimate + step_cost;
+                if cells.get(&from).is_some_and(|known: &FlowCell| known.cost_to_goal <= cost_to_goal) {
+                    continue;
+                }
+                cells.insert(from, FlowCell { cost_to_goal, next: cell });
+                open.push(OpenCell { estimate: cost_to_goal, cell: from });
+            }
+        }
+        field.cells = cells;
+        field
+    }
+
+    pub fn goal(&self) -> Cell {
+        self.goal
+    }
+
+    /// Whether `cell` lies in the area the field was computed over.
+    pub fn covers(&self, cell: Cell) -> bool {
+        (cell.0 - self.goal.0).abs() <= self.radius && (cell.1 - self.goal.1).abs() <= self.radius
+    }
+
+    pub fn cost_to_goal(&self, cell: Cell) -> Option<f32> {
+        self.cells.get(&cell).map(|flow_cell| flow_cell.cost_to_goal)
+    }
+
+    /// Cell to walk to from `cell`, the goal itself once there. Agents on a cell the field can't
+    /// enter, like their own home, step to the neighbour closest to the goal. None when there is
+    /// no way to the goal from here.
+    pub fn next_cell(&self, cell: Cell) -> Option<Cell> {
+        if let Some(flow_cell) = self.cells.get(&cell) {
+            return Some(flow_cell.next);
+        }
+        NEIGHBOURS
+            .iter()
+            .map(|(dx, dy)| (cell.0 + dx, cell.1 + dy))
+            .filter_map(|next| self.cost_to_goal(next).map(|cost| (next, cost)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(next, _)| next)
+    }
+
+    /// Whether an agent on `cell` can follow the field to the goal.
+    pub fn reaches(&self, cell: Cell) -> bool {
+        self.next_cell(cell).is_some()
+    }
+}
+
+/// Flow fields of the destinations many agents head to. A goal gets one after it was asked for
+/// `popular_after` times within `window` seconds. Fields are dropped when a cell they cover
+/// changes and computed again the next time they are needed.
+pub struct FlowFieldCache {
+    fields: HashMap<Cell, (FlowField, u64)>,
+    /// Requests per goal and when their window started
+    requests: HashMap<Cell, (u32, f64)>,
+    capacity: usize,
+    popular_after: u32,
+    window: f64,
+    radius: i32,
+    /// Counts field lookups, orders the fields by when they were last used
+    uses: u64,
+}
+
+impl Default for FlowFieldCache {
+    fn default() -> Self {
+        Self::new(FLOW_FIELD_CAPACITY, POPULAR_AFTER, POPULAR_WINDOW, FLOW_FIELD_RADIUS)
+    }
+}
+
+impl FlowFieldCache {
+    pub fn new(capacity: usize, popular_after: u32, window: f64, radius: i32) -> Self {
+        Self { fields: HashMap::new(), requests: HashMap::new(), capacity, popular_after, window, radius, uses: 0 }
+    }
+
+    /// Counts a move towards `goal` at `now` seconds and tells whether the goal is popular enough
+    /// for a field. The count starts over once its window has passed, goals nobody asked for in a
+    /// while are forgotten.
+    pub fn note_request(&mut self, goal: Cell, now: f64) -> bool {
+        let window = self.window;
+        if !self.requests.contains_key(&goal) {
+            self.requests.retain(|_, (_, started)| now - *started < window);
+        }
+        let (requests, started) = self.requests.entry(goal).or_insert((0, now));
+        if now - *started >= window {
+            (*requests, *started) = (0, now);
+        }
+        *requests += 1;
+        *requests >= self.popular_after
+    }
+
+    /// Field towards `goal`, computed over `grid` unless a valid one is kept already.
+    pub fn field(&mut self, goal: Cell, grid: &impl CostGrid) -> &FlowField {
+        self.uses += 1;
+        let uses = self.uses;
+        if !self.fields.contains_key(&goal) && self.fields.len() >= self.capacity {
+            let least_recent = self.fields.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(goal, _)| *goal);
+            if let Some(least_recent) = least_recent {
+                self.fields.remove(&least_recent);
+            }
+        }
+        let radius = self.radius;
+        let (field, last_used) =
+            self.fields.entry(goal).or_insert_with(|| (FlowField::compute(grid, goal, radius), uses));
+        *last_used = uses;
+        field
+    }
+
+    pub fn is_cached(&self, goal: Cell) -> bool {
+        self.fields.contains_key(&goal)
+    }
+
+    /// Drops the fields covering `cell` after its occupancy changed and returns how many.
+    pub fn invalidate(&mut self, cell: Cell) -> usize {
+        let before = self.fields.len();
+        self.fields.retain(|_, (field, _)| !field.covers(cell));
+        before - self.fields.len()
+    }
+
+    /// Drops every field, e.g. after the terrain changed.
+    pub fn clear(&mut self) {
+        self.fields.clear();
+    }
+}
@@ -4,6 +4,13 @@ use godot::prelude::*;
 use rand::seq::SliceRandom;
 use godot::classes::Engine;
 
+use super::flow_field::FlowFieldCache;
+use super::pathfinding::{self, Cell, CostGrid};
+use super::random::seeded_rng;
+use super::steering::{self, Crowd};
+use crate::world::terrain::{Structure, TerrainMap};
+use crate::world::world_clock::WorldClock;
+
 #[derive(GodotClass)]
 #[class(base=Object)]
 pub struct FreeSpaceManager {
@@ -13,6 +20,25 @@ pub struct FreeSpaceManager {
     cell_y_size: f32,
     distance_step: i32,
     occupied_positions: HashSet<(i32, i32)>,
+    terrain: TerrainMap,
+    flow_fields: FlowFieldCache,
+    crowd: Crowd,
+}
+
+/// Cells that can't be entered because something stands on them or the terrain forbids it
+struct OccupancyGrid<'a> {
+    occupied_positions: &'a HashSet<(i32, i32)>,
+    terrain: &'a TerrainMap,
+}
+
+impl CostGrid for OccupancyGrid<'_> {
+    fn move_cost(&self, cell: Cell) -> Option<f32> {
+        if self.occupied_positions.contains(&cell) {
+            None
+        } else {
+            self.terrain.move_cost(cell)
+        }
+    }
 }
 
 #[godot_api]
@@ -24,6 +50,9 @@ impl IObject for FreeSpaceManager {
             cell_y_size: 200.0,
             distance_step: 3,
             occupied_positions: HashSet::new(),
+            terrain: TerrainMap::default(),
+            flow_fields: FlowFieldCache::default(),
+            crowd: Crowd::new(200.0),
         }
     }
 }
@@ -36,36 +65,116 @@ impl FreeSpaceManager {
             .expect("FreeSpaceManager singleton not found").try_cast::<FreeSpaceManager>().unwrap()
     }
 
+    pub fn cell_size(&self) -> Vector2 {
+        Vector2::new(self.cell_x_size, self.cell_y_size)
+    }
+
+    pub fn terrain(&self) -> &TerrainMap {
+        &self.terrain
+    }
+
+    pub fn terrain_mut(&mut self) -> &mut TerrainMap {
+        self.flow_fields.clear();
+        &mut self.terrain
+    }
+
+    /// Agents on the move, for steering clear of each other
+    pub fn crowd_mut(&mut self) -> &mut Crowd {
+        &mut self.crowd
+    }
+
+    pub fn cell_of(&self, position: Vector2) -> Cell {
+        self.cell_id_from_position(position)
+    }
+
+    pub fn cell_position(&self, cell: Cell) -> Vector2 {
+        self.position_from_cell_id(cell.0, cell.1)
+    }
+
     pub fn add_occupied_position(&mut self, position: Vector2) {
         let (cell_x, cell_y) = self.cell_id_from_position(position);
         self.occupied_positions.insert((cell_x, cell_y));
+        self.flow_fields.invalidate((cell_x, cell_y));
     }
 
     pub fn remove_occupied_position(&mut self, position: Vector2) {
         let (cell_x, cell_y) = self.cell_id_from_position(position);
         self.occupied_positions.remove(&(cell_x, cell_y));
+        self.flow_fields.invalidate((cell_x, cell_y));
     }
 
-    pub fn find_random_free_position_near(&self, target: Vector2, radius: f32) -> Vector2 {
+    /// Free cell near `target` whose terrain allows `structure`, cells closer than `radius` are
+    /// more likely.
+    pub fn find_random_free_position_near(&self, target: Vector2, radius: f32, structure: Structure) -> Vector2 {
         let (target_cell_x, target_cell_y) = self.cell_id_from_position(target);
-        let reference_distance = (radius / self.cell_x_size).ceil() as i32;
+        // At least one cell, a zero radius would divide the weights by zero
+        let reference_distance = ((radius / self.cell_x_size).ceil() as i32).max(1);
 
         // Create a list of positions to check in expanding square pattern
         let mut available_cells = Vec::new();
         let mut distance_min = 0;
         let mut distance_max = reference_distance;
-        while available_cells.len() == 0 {
-            available_cells = self.expand_available_cells(target_cell_x, target_cell_y, distance_min, distance_max, reference_distance);
+        while available_cells.is_empty() {
+            available_cells = self.expand_available_cells(target_cell_x, target_cell_y, distance_min, distance_max, reference_distance, structure);
             distance_min = distance_max + 1;
-            distance_max = distance_max + self.distance_step;
+            distance_max += self.distance_step;
         }
 
-        // Shuffle the positions
-        let mut rng = rand::thread_rng();
+        // The same world seed picks the same spot
+        let mut rng = seeded_rng(&format!("free position near {target_cell_x} {target_cell_y}"));
         let cell = available_cells.choose_weighted(&mut rng, |p| p.2).unwrap();
         self.position_from_cell_id(cell.0, cell.1)
     }
 
+    /// Waypoints from `from` to `to` around occupied cells and water over the cheapest terrain,
+    /// ending exactly at `to`. None when there is no way through.
+    pub fn find_path(&self, from: Vector2, to: Vector2) -> Option<Vec<Vector2>> {
+        let start = self.cell_id_from_position(from);
+        let goal = self.cell_id_from_position(to);
+        if start == goal {
+            return Some(vec![to]);
+        }
+        let mut cells = pathfinding::find_path(self, start, goal)?;
+        cells.pop();
+        let mut waypoints: Vec<Vector2> =
+            cells.into_iter().map(|(cell_x, cell_y)| self.position_from_cell_id(cell_x, cell_y)).collect();
+        waypoints.push(to);
+        Some(waypoints)
+    }
+
+    /// Counts a move from `from` to `to` and tells whether the mover should follow the shared flow
+    /// field of `to` instead of searching its own path. Only popular destinations get one.
+    pub fn prefers_flow_field(&mut self, from: Vector2, to: Vector2) -> bool {
+        let (start, goal) = (self.cell_id_from_position(from), self.cell_id_from_position(to));
+        if !self.flow_fields.note_request(goal, WorldClock::seconds()) {
+            return false;
+        }
+        let grid = OccupancyGrid { occupied_positions: &self.occupied_positions, terrain: &self.terrain };
+        self.flow_fields.field(goal, &grid).reaches(start)
+    }
+
+    /// Next point on the flow field from `from` towards `to`, `to` itself from the cell next to it.
+    /// None when the way to `to` got blocked.
+    pub fn flow_step(&mut self, from: Vector2, to: Vector2) -> Option<Vector2> {
+        let (cell, goal) = (self.cell_id_from_position(from), self.cell_id_from_position(to));
+        if cell == goal {
+            return Some(to);
+        }
+        let grid = OccupancyGrid { occupied_positions: &self.occupied_positions, terrain: &self.terrain };
+        let next = self.flow_fields.field(goal, &grid).next_cell(cell)?;
+        if next == goal {
+            Some(to)
+        } else {
+            Some(self.position_from_cell_id(next.0, next.1))
+        }
+    }
+
+    /// Where `offset` takes an agent at `position` without entering a cell it can't walk on, see
+    /// `steering::walkable_offset`.
+    pub fn steer(&self, position: Vector2, offset: Vector2) -> Vector2 {
+        steering::walkable_offset(self, |position| self.cell_id_from_position(position), position, offset)
+    }
+
     fn cell_id_from_position(&self, position: Vector2) -> (i32, i32) {
         let cell_x = ((position.x - self.cell_x_size / 2.) / self.cell_x_size).floor() as i32;
         let cell_y = ((position.y - self.cell_y_size / 2.) / self.cell_y_size).floor() as i32;
@@ -79,7 +188,7 @@ impl FreeSpaceManager {
         )
     }
 
-    fn expand_available_cells(&self, target_cell_x: i32, target_cell_y: i32, distance_min: i32, distance_max: i32, reference_distance: i32) -> Vec<(i32, i32, f32)> {
+    fn expand_available_cells(&self, target_cell_x: i32, target_cell_y: i32, distance_min: i32, distance_max: i32, reference_distance: i32, structure: Structure) -> Vec<(i32, i32, f32)> {
         let mut check_cells = Vec::new();
 
         for cell_x in distance_min..=distance_max {
@@ -109,8 +218,18 @@ impl FreeSpaceManager {
         check_cells
             .iter()
             .filter(|(cell_x, cell_y)| !self.occupied_positions.contains(&(target_cell_x + cell_x, target_cell_y + cell_y)))
-            .map(|(cell_x, cell_y)| (target_cell_x + cell_x, target_cell_y + cell_y, ((-(cell_x.pow(2) + cell_y.pow(2)) as f32) / (reference_distance as f32).powf(2.0)).exp()))
+            .filter(|(cell_x, cell_y)| self.terrain.allows((target_cell_x + cell_x, target_cell_y + cell_y), structure))
+            .map(|(cell_x, cell_y)| {
+                let weight = ((-(cell_x.pow(2) + cell_y.pow(2)) as f32) / (reference_distance as f32).powf(2.0)).exp();
+                // Far out the weight underflows to zero, which choose_weighted refuses when all are
+                (target_cell_x + cell_x, target_cell_y + cell_y, weight.max(f32::MIN_POSITIVE))
+            })
             .collect()
     }
 }
 
+impl CostGrid for FreeSpaceManager {
+    fn move_cost(&self, cell: Cell) -> Option<f32> {
+        OccupancyGrid { occupied_positions: &self.occupied_positions, terrain: &self.terrain }.move_cost(cell)
+    }
+}
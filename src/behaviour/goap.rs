@@ -0,0 +1,165 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+
+/// Facts the planner reasons about. Yes/no facts are stored as 0 and 1.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum WorldFact {
+    HasHome,
+    /// Fields the agent owns, whatever state they are in
+    FieldCount,
+    /// Fields growing or grown
+    PlantedFieldCount,
+    GrownFieldCount,
+    FallowFieldCount,
+    /// Fields ready to be planted again
+    EmptyFieldCount,
+    CarriedWheat,
+    HouseholdFood,
+    /// Another household is short of food
+    MarketHasBuyers,
+}
+
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Debug, Default)]
+pub struct WorldState {
+    values: BTreeMap<WorldFact, i32>,
+}
+
+impl WorldState {
+    pub fn new() -> Self {
+        Self { values: BTreeMap::new() }
+    }
+
+    /// Facts that were never set are 0.
+    pub fn get(&self, fact: WorldFact) -> i32 {
+        self.values.get(&fact).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, fact: WorldFact, value: i32) {
+        self.values.insert(fact, value);
+    }
+
+    pub fn with(mut self, fact: WorldFact, value: i32) -> Self {
+        self.set(fact, value);
+        self
+    }
+
+    pub fn satisfies(&self, conditions: &[Condition]) -> bool {
+        conditions.iter().all(|condition| condition.is_met(self))
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Condition {
+    AtLeast(WorldFact, i32),
+    AtMost(WorldFact, i32),
+}
+
+impl Condition {
+    pub fn is_met(&self, state: &WorldState) -> bool {
+        match *self {
+            Condition::AtLeast(fact, value) => state.get(fact) >= value,
+            Condition::AtMost(fact, value) => state.get(fact) <= value,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Effect {
+    Set(WorldFact, i32),
+    Add(WorldFact, i32),
+    /// Adds the value of the first fact to the second one
+    AddFrom(WorldFact, WorldFact),
+}
+
+impl Effect {
+    fn apply(&self, state: &mut WorldState) {
+        match *self {
+            Effect::Set(fact, value) => state.set(fact, value),
+            Effect::Add(fact, value) => state.set(fact, state.get(fact) + value),
+            Effect::AddFrom(from, to) => state.set(to, state.get(to) + state.get(from)),
+        }
+    }
+}
+
+/// Something an agent can do, described by when it can be done and what it changes.
+/// `steps` are what actually runs when the action is part of a plan.
+#[derive(Clone, Debug)]
+pub struct PlannerAction<A> {
+    pub name: &'static str,
+    pub steps: Vec<A>,
+    pub cost: u32,
+    pub preconditions: Vec<Condition>,
+    pub effects: Vec<Effect>,
+}
+
+impl<A> PlannerAction<A> {
+    pub fn is_possible(&self, state: &WorldState) -> bool {
+        state.satisfies(&self.preconditions)
+    }
+
+    pub fn apply(&self, state: &WorldState) -> WorldState {
+        let mut next_state = state.clone();
+        for effect in &self.effects {
+            effect.apply(&mut next_state);
+        }
+        next_state
+    }
+}
+
+/// A state the agent wants to reach.
+#[derive(Clone, Debug)]
+pub struct Goal {
+    pub name: &'static str,
+    pub conditions: Vec<Condition>,
+}
+
+/// Cheapest sequence of actions, as indices into `actions`, that leads from `start` to a
+/// state meeting `goal`. Gives up after expanding `max_expanded_states` states.
+pub fn plan<A>(
+    start: &WorldState,
+    goal: &[Condition],
+    actions: &[PlannerAction<A>],
+    max_expanded_states: usize,
+) -> Option<Vec<usize>> {
+    let mut best_costs: HashMap<WorldState, u32> = HashMap::from([(start.clone(), 0)]);
+    let mut came_from: HashMap<WorldState, (WorldState, usize)> = HashMap::new();
+    // Ties are broken by insertion order so equal plans come out the same every time
+    let mut open = BinaryHeap::from([Reverse((0, 0_usize, start.clone()))]);
+    let mut pushed = 1;
+    let mut expanded = 0;
+
+    while let Some(Reverse((cost, _, state))) = open.pop() {
+        if best_costs.get(&state).is_some_and(|best_cost| *best_cost < cost) {
+            continue;
+        }
+        if state.satisfies(goal) {
+            return Some(reconstruct_plan(&came_from, state));
+        }
+        expanded += 1;
+        if expanded > max_expanded_states {
+            return None;
+        }
+        for (index, action) in actions.iter().enumerate().filter(|(_, action)| action.is_possible(&state)) {
+            let next_state = action.apply(&state);
+            let next_cost = cost + action.cost;
+            if best_costs.get(&next_state).is_some_and(|best_cost| *best_cost <= next_cost) {
+                continue;
+            }
+            best_costs.insert(next_state.clone(), next_cost);
+            came_from.insert(next_state.clone(), (state.clone(), index));
+            open.push(Reverse((next_cost, pushed, next_state)));
+            pushed += 1;
+        }
+    }
+    None
+}
+
+fn reconstruct_plan(came_from: &HashMap<WorldState, (WorldState, usize)>, mut state: WorldState) -> Vec<usize> {
+    let mut plan = Vec::new();
+    while let Some((previous_state, action)) = came_from.get(&state) {
+        plan.push(*action);
+        state = previous_state.clone();
+    }
+    plan.reverse();
+    plan
+}
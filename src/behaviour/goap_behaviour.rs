@@ -0,0 +1,185 @@
+use bonsai_bt::Status;
+use godot::prelude::*;
+
+use super::agent_behaviour::{AgentBehaviourResult, IAgentBehaviour};
+use super::introspection::StateStack;
+use super::goap::{plan, Goal, PlannerAction, WorldFact, WorldState};
+use super::tree_behaviour::{ActionExecutor, AgentAction, AgentBlackboard};
+use super::utility::JobScore;
+use crate::building::{Building, FieldState};
+use crate::resources::inventory::InventoryResource;
+use crate::resources::transaction_log::InventoryOwner;
+
+pub struct GoapBehaviourConfig {
+    /// Search budget of a single planning attempt
+    pub max_expanded_states: usize,
+    /// How long to wait before planning again when no goal can be reached
+    pub replan_interval: f32,
+}
+
+struct ActivePlan {
+    goal: &'static str,
+    actions: Vec<usize>,
+    action: usize,
+    step: usize,
+}
+
+/// Agent that plans its way to the first unmet goal with the goal oriented action planner
+/// and runs the plan with the same executor as the behaviour trees. Any failed step drops
+/// the plan and a new one is made from what the world looks like then.
+pub struct GoapAgentBehaviour {
+    blackboard: AgentBlackboard,
+    executor: ActionExecutor,
+    actions: Vec<PlannerAction<AgentAction>>,
+    goals: Vec<Goal>,
+    config: GoapBehaviourConfig,
+    plan: Option<ActivePlan>,
+    replan_time: f32,
+}
+
+impl GoapAgentBehaviour {
+    pub fn new(
+        blackboard: AgentBlackboard,
+        executor: ActionExecutor,
+        actions: Vec<PlannerAction<AgentAction>>,
+        goals: Vec<Goal>,
+        config: GoapBehaviourConfig,
+    ) -> Self {
+        Self { blackboard, executor, actions, goals, config, plan: None, replan_time: 0.0 }
+    }
+
+    fn sense_world(&self) -> WorldState {
+        let blackboard = &self.blackboard;
+        let count_fields = |states: &[FieldState]| {
+            blackboard.fields.iter().filter(|field| states.contains(&field.bind().state)).count() as i32
+        };
+        let empty_fields = blackboard.fields.iter().filter(|field| field.bind().is_replantable()).count() as i32;
+        let household_food = blackboard.home.as_ref().map_or(0, |home| home.bind().food());
+        let market_has_buyers = Building::all()
+            .iter()
+            .any(|building| blackboard.home.as_ref() != Some(building) && building.bind().is_short_of_food());
+        WorldState::new()
+            .with(WorldFact::HasHome, blackboard.home.is_some() as i32)
+            .with(WorldFact::FieldCount, blackboard.fields.len() as i32)
+            .with(WorldFact::PlantedFieldCount, count_fields(&[FieldState::Growing, FieldState::Grown]))
+            .with(WorldFact::GrownFieldCount, count_fields(&[FieldState::Grown]))
+            .with(WorldFact::FallowFieldCount, count_fields(&[FieldState::Fallow]))
+            .with(WorldFact::EmptyFieldCount, empty_fields)
+            .with(WorldFact::CarriedWheat, blackboard.inventory.amount(InventoryResource::Wheat))
+            .with(WorldFact::HouseholdFood, household_food)
+            .with(WorldFact::MarketHasBuyers, market_has_buyers as i32)
+    }
+
+    fn make_plan(&mut self) -> Option<ActivePlan> {
+        let world = self.sense_world();
+        for goal in self.goals.iter().filter(|goal| !world.satisfies(&goal.conditions)) {
+            if let Some(actions) = plan(&world, &goal.conditions, &self.actions, self.config.max_expanded_states) {
+                let names: Vec<&str> = actions.iter().map(|action| self.actions[*action].name).collect();
+                godot_print!("Agent {}: Plan for {}: {}", self.blackboard.agent_name, goal.name, names.join(", "));
+                return Some(ActivePlan { goal: goal.name, actions, action: 0, step: 0 });
+            }
+            godot_print!("Agent {}: No plan for {}", self.blackboard.agent_name, goal.name);
+        }
+        None
+    }
+
+    fn drop_plan(&mut self) {
+        self.plan = None;
+        self.executor.reset();
+    }
+}
+
+impl IAgentBehaviour for GoapAgentBehaviour {
+    fn start(&mut self, agent_name: String, parent_node: Option<Gd<Node>>) {
+        self.blackboard.inventory.set_owner(InventoryOwner::Agent(agent_name.clone()));
+        self.blackboard.agent_name = agent_name;
+        self.blackboard.parent_node = parent_node;
+        self.drop_plan();
+    }
+
+    fn tick(&mut self, delta: f64, agent_position: Vector2) -> AgentBehaviourResult {
+        self.blackboard.agent_position = agent_position;
+        self.blackboard.next_position = None;
+        self.blackboard.fields.retain(|field| field.is_instance_valid());
+        if self.blackboard.home.as_ref().is_some_and(|home| !home.is_instance_valid()) {
+            self.blackboard.home = None;
+        }
+
+        if self.plan.is_none() {
+            self.replan_time -= delta as f32;
+            if self.replan_time > 0.0 {
+                return AgentBehaviourResult { next_position: None };
+            }
+            self.plan = self.make_plan();
+            if self.plan.is_none() {
+                self.replan_time = self.config.replan_interval;
+                return AgentBehaviourResult { next_position: None };
+            }
+        }
+
+        // Instant steps finish in the same tick, so keep going until something has to wait
+        let mut dt = delta;
+        while let Some(active_plan) = &self.plan {
+            let Some(&action_index) = active_plan.actions.get(active_plan.action) else {
+                godot_print!("Agent {}: Reached goal {}", self.blackboard.agent_name, active_plan.goal);
+                self.drop_plan();
+                break;
+            };
+            let action = &self.actions[action_index];
+            let (status, remaining_dt) =
+                self.executor.execute(action.steps[active_plan.step], dt, &mut self.blackboard);
+            dt = remaining_dt;
+            match status {
+                Status::Running => break,
+                Status::Success => {
+                    let step_count = action.steps.len();
+                    let active_plan = self.plan.as_mut().unwrap();
+                    active_plan.step += 1;
+                    if active_plan.step >= step_count {
+                        active_plan.step = 0;
+                        active_plan.action += 1;
+                    }
+                }
+                Status::Failure => {
+                    let action_name = action.name;
+                    godot_print!("Agent {}: {} failed, replanning", self.blackboard.agent_name, action_name);
+                    self.drop_plan();
+                    break;
+                }
+            }
+        }
+        AgentBehaviourResult { next_position: self.blackboard.next_position }
+    }
+
+    fn cancel(&mut self) {
+        godot_print!("Agent {}: Cancelling plan", self.blackboard.agent_name);
+        self.executor.cancel(&mut self.blackboard);
+        self.drop_plan();
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        self.blackboard.agent_position = agent_position;
+        self.executor.resume(&self.blackboard);
+    }
+
+    fn home_position(&self) -> Option<Vector2> {
+        self.blackboard
+            .home
+            .as_ref()
+            .filter(|home| home.is_instance_valid())
+            .map(|home| home.bind().base().get_position())
+    }
+
+    fn job_scores(&self) -> Vec<JobScore> {
+        Vec::new()
+    }
+
+    fn state_stack(&self) -> StateStack {
+        let Some(active_plan) = &self.plan else {
+            return StateStack::of("GoapAgentBehaviour", format_args!("Planning"));
+        };
+        let action = active_plan.actions.get(active_plan.action).map_or("", |action| self.actions[*action].name);
+        StateStack::of("GoapAgentBehaviour", format_args!("{}({})", active_plan.goal, action))
+            .then(self.executor.state_stack())
+    }
+}
@@ -0,0 +1,376 @@
+use super::introspection::StateStack;
+use super::move_behaviour::{FailureReason, MoveBehaviour, Result};
+use super::perception::nearest_threat;
+use super::tour_planner::plan_shortest_tour;
+use super::utility::UtilityInputs;
+use super::work_behaviour::{IWorkBehaviour, WorkResult};
+use crate::agent::wolf::Wolf;
+use crate::building::{Building, Field};
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::prices::Prices;
+use crate::resources::transaction_log::{TransactionContext, TransactionLog, TransactionReason};
+use crate::resources::treasury::Treasury;
+use godot::prelude::*;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum GuardState {
+    Idle,
+    Patrolling,
+    Chasing,
+    Resting,
+}
+
+/// Who pays the guard at the end of each patrol round.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum GuardPayer {
+    /// Coins from the town treasury
+    Treasury,
+    /// The households on the patrol route share the wage, paid with whatever goods they have
+    Households,
+}
+
+pub struct GuardBehaviourConfig {
+    /// Wolves closer than this are chased
+    pub sight_radius: f32,
+    /// A wolf this close is scared off
+    pub scare_distance: f32,
+    /// The chase is given up when the wolf gets further away than this
+    pub give_up_distance: f32,
+    pub max_patrol_stops: usize,
+    /// Guards walk past buildings at this offset instead of into them
+    pub patrol_offset: Vector2,
+    /// Break at home between patrol rounds
+    pub rest_duration: f32,
+    /// Paid once per patrol round
+    pub wage: i32,
+    pub payer: GuardPayer,
+}
+
+/// Walks a loop past homes and fields, chases wolves it sees and drives them off.
+pub struct GuardBehaviour {
+    state: GuardState,
+    move_behaviour: MoveBehaviour,
+    config: GuardBehaviourConfig,
+    route: Vec<Vector2>,
+    /// Homes on the current route, they share the wage when households pay
+    patrolled_households: Vec<Gd<Building>>,
+    next_waypoint: usize,
+    target_wolf: Option<Gd<Wolf>>,
+    /// Wolf the last chase failed to reach, not chased again while it stays in sight
+    unreachable_wolf: Option<Gd<Wolf>>,
+    rest_time: f32,
+    agent_name: String,
+    home: Option<Gd<Building>>,
+}
+
+impl IWorkBehaviour for GuardBehaviour {
+    fn work(&mut self, delta: f64, agent_position: Vector2) -> WorkResult {
+        loop {
+            match self.state {
+                GuardState::Idle => {
+                    if let Err(reason) = self.start_patrol(agent_position) {
+                        return WorkResult { result: Result::Failure(reason), next_position: None };
+                    }
+                }
+                GuardState::Patrolling => {
+                    if self.spot_threat(agent_position) {
+                        continue;
+                    }
+                    let (result, next_position) = self.move_behaviour.move_agent(delta);
+                    match result {
+                        Result::Running => {
+                            return WorkResult { result, next_position: Some(next_position) };
+                        }
+                        Result::Success => {
+                            self.next_waypoint += 1;
+                            if self.next_waypoint >= self.route.len() {
+                                self.finish_patrol();
+                            } else {
+                                self.continue_patrol(next_position);
+                            }
+                            return WorkResult { result: Result::Running, next_position: Some(next_position) };
+                        }
+                        Result::Failure(reason) => return self.fail(reason),
+                    }
+                }
+                GuardState::Chasing => {
+                    let Some(wolf_position) = self.target_wolf_position(agent_position) else {
+                        godot_print!("Agent {}: Lost sight of the wolf", self.agent_name);
+                        self.target_wolf = None;
+                        self.continue_patrol(agent_position);
+                        continue;
+                    };
+                    if agent_position.distance_to(wolf_position) <= self.config.scare_distance {
+                        godot_print!("Agent {}: Drove off a wolf", self.agent_name);
+                        self.target_wolf.take().unwrap().bind_mut().scare(agent_position);
+                        self.continue_patrol(agent_position);
+                        continue;
+                    }
+                    self.move_behaviour.retarget(wolf_position);
+                    let (result, next_position) = self.move_behaviour.move_agent(delta);
+                    if let Result::Failure(reason) = result {
+                        godot_print!("Agent {}: Gave up the chase: {:?}", self.agent_name, reason);
+                        self.unreachable_wolf = self.target_wolf.take();
+                        self.continue_patrol(next_position);
+                        return WorkResult { result: Result::Running, next_position: Some(next_position) };
+                    }
+                    return WorkResult { result: Result::Running, next_position: Some(next_position) };
+                }
+                GuardState::Resting => {
+                    if self.spot_threat(agent_position) {
+                        continue;
+                    }
+                    self.rest_time += delta as f32;
+                    if self.rest_time < self.config.rest_duration {
+                        return WorkResult { result: Result::Running, next_position: None };
+                    }
+                    self.state = GuardState::Idle;
+                    return WorkResult { result: Result::Success, next_position: None };
+                }
+            }
+        }
+    }
+
+    fn start_work(&mut self, home: Gd<Building>, agent_name: String, _parent_node: Option<Gd<Node>>) {
+        self.home = Some(home);
+        self.state = GuardState::Idle;
+        self.agent_name = agent_name;
+    }
+
+    fn is_work_available(&self) -> bool {
+        true
+    }
+
+    fn is_idle(&self) -> bool {
+        self.state == GuardState::Idle
+    }
+
+    fn job_name(&self) -> &'static str {
+        "Guard"
+    }
+
+    fn state_stack(&self) -> StateStack {
+        let stack = StateStack::of("GuardBehaviour", self.state);
+        match self.state {
+            GuardState::Patrolling | GuardState::Chasing => stack.then(self.move_behaviour.state_stack()),
+            _ => stack,
+        }
+    }
+
+    fn utility_inputs(&self, _agent_position: Vector2, home: &Gd<Building>, prices: &Prices) -> UtilityInputs {
+        // Guarding is urgent while a wolf is near home, otherwise it is just a paid job
+        let home_position = home.bind().base().get_position();
+        let threatened = nearest_threat(home_position, self.config.sight_radius).is_some();
+        UtilityInputs {
+            household_need: if threatened { 1.0 } else { 0.0 },
+            price: prices.price(InventoryResource::Coins) * self.config.wage as f32,
+            distance: 0.0,
+        }
+    }
+
+    fn cancel(&mut self) {
+        godot_print!("Agent {}: Guard cancelled in state {:?}", self.agent_name, self.state);
+        self.move_behaviour.stop();
+        self.target_wolf = None;
+        self.route.clear();
+        self.patrolled_households.clear();
+        self.state = GuardState::Idle;
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        match self.state {
+            GuardState::Idle | GuardState::Resting => {}
+            GuardState::Patrolling => self.move_behaviour.resume(agent_position),
+            GuardState::Chasing => {
+                self.target_wolf = None;
+                self.continue_patrol(agent_position);
+            }
+        }
+    }
+}
+
+/// Splits `wage` between payers that have `available` goods, one unit at a time so
+/// everybody pays about the same. Returns what each payer owes, which may add up to less
+/// than the wage if they run out.
+pub fn split_wage(wage: i32, available: &[i32]) -> Vec<i32> {
+    let mut shares = vec![0; available.len()];
+    let mut remaining = wage;
+    while remaining > 0 {
+        let mut paid_any = false;
+        for (share, available) in shares.iter_mut().zip(available) {
+            if remaining > 0 && *share < *available {
+                *share += 1;
+                remaining -= 1;
+                paid_any = true;
+            }
+        }
+        if !paid_any {
+            break;
+        }
+    }
+    shares
+}
+
+impl GuardBehaviour {
+    pub fn new(move_behaviour: MoveBehaviour, config: GuardBehaviourConfig) -> Self {
+        Self {
+            state: GuardState::Idle,
+            move_behaviour,
+            config,
+            route: Vec::new(),
+            patrolled_households: Vec::new(),
+            next_waypoint: 0,
+            target_wolf: None,
+            unreachable_wolf: None,
+            rest_time: 0.0,
+            agent_name: String::new(),
+            home: None,
+        }
+    }
+
+    fn home(&self) -> Option<&Gd<Building>> {
+        self.home.as_ref().filter(|home| home.is_instance_valid())
+    }
+
+    fn start_patrol(&mut self, agent_position: Vector2) -> std::result::Result<(), FailureReason> {
+        let Some(home) = self.home().cloned() else {
+            return Err(FailureReason::NoHome);
+        };
+        let home_position = home.bind().base().get_position();
+        let households: Vec<Gd<Building>> = Building::all().into_iter().filter(|building| *building != home).collect();
+        let waypoints: Vec<Vector2> = households
+            .iter()
+            .map(|household| household.bind().base().get_position())
+            .chain(Field::all().iter().map(|field| field.bind().base().get_position()))
+            .map(|position| position + self.config.patrol_offset)
+            .collect();
+        let order = plan_shortest_tour(home_position, &waypoints, Some(home_position), self.config.max_patrol_stops);
+        self.patrolled_households = order.iter().filter_map(|index| households.get(*index)).cloned().collect();
+        self.route = order.iter().map(|index| waypoints[*index]).chain([home_position]).collect();
+        godot_print!("Agent {}: Starting patrol past {} waypoints", self.agent_name, order.len());
+        self.next_waypoint = 0;
+        self.continue_patrol(agent_position);
+        Ok(())
+    }
+
+    /// Heads to the next waypoint, or back to resting if the round was already finished.
+    fn continue_patrol(&mut self, agent_position: Vector2) {
+        match self.route.get(self.next_waypoint) {
+            Some(waypoint) => {
+                self.move_behaviour.start_moving(agent_position, *waypoint);
+                self.state = GuardState::Patrolling;
+            }
+            None => {
+                self.move_behaviour.stop();
+                self.state = GuardState::Resting;
+            }
+        }
+    }
+
+    fn spot_threat(&mut self, agent_position: Vector2) -> bool {
+        let Some(wolf) = nearest_threat(agent_position, self.config.sight_radius) else {
+            self.unreachable_wolf = None;
+            return false;
+        };
+        if self.unreachable_wolf.as_ref() == Some(&wolf) {
+            return false;
+        }
+        godot_print!("Agent {}: Wolf spotted, chasing", self.agent_name);
+        self.move_behaviour.start_moving(agent_position, wolf.get_position());
+        self.target_wolf = Some(wolf);
+        self.state = GuardState::Chasing;
+        true
+    }
+
+    fn target_wolf_position(&self, agent_position: Vector2) -> Option<Vector2> {
+        let wolf = self.target_wolf.as_ref().filter(|wolf| wolf.is_instance_valid())?;
+        if wolf.bind().is_driven_off() {
+            return None;
+        }
+        let wolf_position = wolf.get_position();
+        (agent_position.distance_to(wolf_position) <= self.config.give_up_distance).then_some(wolf_position)
+    }
+
+    fn finish_patrol(&mut self) {
+        godot_print!("Agent {}: Patrol round complete", self.agent_name);
+        let paid = self.collect_wage();
+        if paid < self.config.wage {
+            godot_print!("Agent {}: Only got {} of {} wage", self.agent_name, paid, self.config.wage);
+        }
+        self.move_behaviour.stop();
+        self.rest_time = 0.0;
+        self.state = GuardState::Resting;
+    }
+
+    /// Transfers the wage into the guard's home and returns how much was paid.
+    fn collect_wage(&mut self) -> i32 {
+        let Some(mut home) = self.home().cloned() else {
+            return 0;
+        };
+        let context = InventoryLedger::context(&self.agent_name, TransactionReason::Wage);
+        let mut ledger = InventoryLedger::singleton();
+        let mut ledger = ledger.bind_mut();
+        let mut home = home.bind_mut();
+        match self.config.payer {
+            GuardPayer::Treasury => {
+                let mut treasury = Treasury::singleton();
+                let mut treasury = treasury.bind_mut();
+                ledger.log.transfer(
+                    &mut treasury.inventory,
+                    &mut home.inventory,
+                    InventoryResource::Coins,
+                    self.config.wage,
+                    &context,
+                )
+            }
+            GuardPayer::Households => {
+                let mut households: Vec<Gd<Building>> = self
+                    .patrolled_households
+                    .iter()
+                    .filter(|household| household.is_instance_valid())
+                    .cloned()
+                    .collect();
+                let available: Vec<i32> =
+                    households.iter().map(|household| household.bind().inventory.total()).collect();
+                let shares = split_wage(self.config.wage, &available);
+                let mut paid = 0;
+                for (household, share) in households.iter_mut().zip(shares) {
+                    let mut household = household.bind_mut();
+                    paid +=
+                        pay_in_kind(&mut ledger.log, &mut household.inventory, &mut home.inventory, share, &context);
+                }
+                paid
+            }
+        }
+    }
+
+    fn fail(&mut self, reason: FailureReason) -> WorkResult {
+        self.move_behaviour.stop();
+        self.target_wolf = None;
+        self.route.clear();
+        self.patrolled_households.clear();
+        self.state = GuardState::Idle;
+        WorkResult { result: Result::Failure(reason), next_position: None }
+    }
+}
+
+/// Pays `amount` units out of `source`, coins first and then any other goods.
+fn pay_in_kind(
+    log: &mut TransactionLog,
+    source: &mut Inventory,
+    destination: &mut Inventory,
+    amount: i32,
+    context: &TransactionContext,
+) -> i32 {
+    let mut resources = source.resources();
+    resources.sort_by_key(|(resource, _)| *resource != InventoryResource::Coins);
+    let mut paid = 0;
+    for (resource, available) in resources {
+        if paid >= amount {
+            break;
+        }
+        paid += log.transfer(source, destination, resource, available.min(amount - paid), context);
+    }
+    paid
+}
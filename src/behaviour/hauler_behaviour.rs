@@ -0,0 +1,399 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::introspection::StateStack;
+use super::move_behaviour::{FailureReason, MoveBehaviour, Result};
+use super::utility::{household_need, UtilityInputs};
+use super::work_behaviour::{return_home, start_returning_home, IWorkBehaviour, WorkResult};
+use crate::building::Building;
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::logistics::Logistics;
+use crate::resources::prices::Prices;
+use crate::resources::transaction_log::{InventoryOwner, TransactionReason};
+use crate::resources::transport::{
+    plan_transport_route, TransportRequest, TransportRequestId, TransportStop, TransportStopKind,
+};
+use crate::resources::treasury::Treasury;
+use godot::prelude::*;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum HaulerState {
+    Idle,
+    Travelling,
+    ReturningGoods,
+    ReturningToHome,
+}
+
+pub struct HaulerBehaviourConfig {
+    pub carry_capacity: i32,
+    /// Coins the treasury pays for every unit delivered
+    pub fee_per_unit: i32,
+}
+
+/// Serves transport requests from the `Logistics` board. A batch of requests is claimed at once,
+/// most urgent first, and driven as one route: all pickups, then all dropoffs. Goods that can't be
+/// delivered are carried back to where they were picked up, only when that is gone as well are
+/// they taken home.
+pub struct HaulerBehaviour {
+    state: HaulerState,
+    move_behaviour: MoveBehaviour,
+    config: HaulerBehaviourConfig,
+    requests: HashMap<TransportRequestId, TransportRequest>,
+    /// How much was actually picked up for each request
+    loaded: HashMap<TransportRequestId, i32>,
+    route: VecDeque<TransportStop>,
+    /// Loaded goods to carry back to their source, with the amount still to return
+    returns: VecDeque<TransportRequest>,
+    inventory: Inventory,
+    agent_name: String,
+    home: Option<Gd<Building>>,
+}
+
+impl IWorkBehaviour for HaulerBehaviour {
+    fn work(&mut self, delta: f64, agent_position: Vector2) -> WorkResult {
+        loop {
+            match self.state {
+                HaulerState::Idle => {
+                    self.forget_returns_no_longer_carried();
+                    if let Some(request) = self.returns.front() {
+                        godot_print!("Agent {}: Taking {} {} back", self.agent_name, request.amount, request.resource);
+                        self.move_behaviour.start_moving(agent_position, request.source_position);
+                        self.state = HaulerState::ReturningGoods;
+                    } else if self.inventory.is_empty() && self.claim_route(agent_position) {
+                        self.start_next_stop(agent_position);
+                    } else if !self.inventory.is_empty() {
+                        if let Err(reason) = self.start_returning_to_home(agent_position) {
+                            return WorkResult { result: Result::Failure(reason), next_position: None };
+                        }
+                    } else {
+                        return WorkResult { result: Result::Success, next_position: None };
+                    }
+                }
+                HaulerState::Travelling => {
+                    let (result, next_position) = self.move_behaviour.move_agent(delta);
+                    match result {
+                        Result::Running => {
+                            return WorkResult { result, next_position: Some(next_position) };
+                        }
+                        Result::Success => {
+                            self.serve_stop();
+                            if self.route.is_empty() {
+                                self.state = HaulerState::Idle;
+                                return WorkResult { result: Result::Success, next_position: Some(next_position) };
+                            }
+                            self.start_next_stop(next_position);
+                            return WorkResult { result: Result::Running, next_position: Some(next_position) };
+                        }
+                        Result::Failure(reason) => return self.fail(reason),
+                    }
+                }
+                HaulerState::ReturningGoods => {
+                    let (result, next_position) = self.move_behaviour.move_agent(delta);
+                    match result {
+                        Result::Running => {
+                            return WorkResult { result, next_position: Some(next_position) };
+                        }
+                        Result::Success => {
+                            let request = self.returns.pop_front().unwrap();
+                            self.return_to_source(&request);
+                            self.state = HaulerState::Idle;
+                            return WorkResult { result: Result::Running, next_position: Some(next_position) };
+                        }
+                        Result::Failure(reason) => {
+                            // Goods that can't be taken back are taken home instead
+                            self.release_returns();
+                            return self.fail(reason);
+                        }
+                    }
+                }
+                HaulerState::ReturningToHome => {
+                    let work_result = return_home(
+                        &mut self.move_behaviour,
+                        self.home.as_mut(),
+                        &mut self.inventory,
+                        delta,
+                        &self.agent_name,
+                    );
+                    if work_result.result != Result::Running {
+                        // Whatever is carried stays in the inventory for the next trip
+                        self.state = HaulerState::Idle;
+                    }
+                    return work_result;
+                }
+            }
+        }
+    }
+
+    fn start_work(&mut self, home: Gd<Building>, agent_name: String, _parent_node: Option<Gd<Node>>) {
+        self.home = Some(home);
+        self.state = HaulerState::Idle;
+        self.inventory.set_owner(InventoryOwner::Agent(agent_name.clone()));
+        self.agent_name = agent_name;
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        // Stops may have been dropped while on hold, the hauler heads for whatever comes next
+        let next_position = match self.state {
+            HaulerState::Idle => return,
+            HaulerState::Travelling => self.route.front().map(|stop| stop.position),
+            HaulerState::ReturningGoods => self.returns.front().map(|request| request.source_position),
+            HaulerState::ReturningToHome => {
+                self.move_behaviour.resume(agent_position);
+                return;
+            }
+        };
+        match next_position {
+            Some(position) => self.move_behaviour.start_moving(agent_position, position),
+            None => {
+                self.move_behaviour.stop();
+                self.state = HaulerState::Idle;
+            }
+        }
+    }
+
+    fn pause(&mut self) {
+        // Requests not picked up yet go back on the board, loaded goods stay with the hauler
+        let waiting: Vec<TransportRequestId> =
+            self.requests.keys().filter(|id| !self.loaded.contains_key(id)).copied().collect();
+        let mut logistics = Logistics::singleton();
+        for id in waiting {
+            logistics.bind_mut().board.release(id, &self.agent_name);
+            self.requests.remove(&id);
+            self.route.retain(|stop| stop.request != id);
+        }
+    }
+
+    fn is_work_available(&self) -> bool {
+        !self.inventory.is_empty() || Logistics::singleton().bind().board.open_requests().next().is_some()
+    }
+
+    fn is_idle(&self) -> bool {
+        self.state == HaulerState::Idle
+    }
+
+    fn carried_goods(&mut self) -> Option<&mut Inventory> {
+        Some(&mut self.inventory)
+    }
+
+    fn job_name(&self) -> &'static str {
+        "Hauler"
+    }
+
+    fn state_stack(&self) -> StateStack {
+        let stack = StateStack::of("HaulerBehaviour", self.state);
+        match self.state {
+            HaulerState::Travelling | HaulerState::ReturningGoods | HaulerState::ReturningToHome => {
+                stack.then(self.move_behaviour.state_stack())
+            }
+            _ => stack,
+        }
+    }
+
+    fn utility_inputs(&self, agent_position: Vector2, home: &Gd<Building>, prices: &Prices) -> UtilityInputs {
+        let logistics = Logistics::singleton();
+        let distance = logistics
+            .bind()
+            .board
+            .open_requests()
+            .max_by(|a, b| a.urgency.total_cmp(&b.urgency))
+            .map_or(0.0, |request| agent_position.distance_to(request.source_position));
+        UtilityInputs {
+            household_need: household_need(&home.bind().inventory, InventoryResource::Coins),
+            price: prices.price(InventoryResource::Coins)
+                * (self.config.fee_per_unit * self.config.carry_capacity) as f32,
+            distance,
+        }
+    }
+
+    fn cancel(&mut self) {
+        godot_print!("Agent {}: Hauler cancelled in state {:?}", self.agent_name, self.state);
+        self.move_behaviour.stop();
+        self.drop_route();
+        self.state = HaulerState::Idle;
+    }
+}
+
+impl HaulerBehaviour {
+    pub fn new(move_behaviour: MoveBehaviour, config: HaulerBehaviourConfig) -> Self {
+        Self {
+            state: HaulerState::Idle,
+            move_behaviour,
+            requests: HashMap::new(),
+            loaded: HashMap::new(),
+            route: VecDeque::new(),
+            returns: VecDeque::new(),
+            inventory: Inventory::with_capacity(InventoryOwner::default(), config.carry_capacity),
+            config,
+            agent_name: String::new(),
+            home: None,
+        }
+    }
+
+    fn claim_route(&mut self, agent_position: Vector2) -> bool {
+        let batch = Logistics::singleton().bind_mut().board.claim_batch(&self.agent_name, self.config.carry_capacity);
+        if batch.is_empty() {
+            return false;
+        }
+        godot_print!("Agent {}: Claimed {} transport requests", self.agent_name, batch.len());
+        self.route = plan_transport_route(agent_position, &batch).into();
+        self.requests = batch.into_iter().map(|request| (request.id, request)).collect();
+        self.loaded.clear();
+        true
+    }
+
+    /// Hands the requests not picked up yet back to the board. Goods already loaded stay with the
+    /// hauler, their requests stay claimed until the goods are back at their source.
+    fn drop_route(&mut self) {
+        for (id, amount) in std::mem::take(&mut self.loaded) {
+            if let Some(request) = self.requests.remove(&id) {
+                self.returns.push_back(TransportRequest { amount, ..request });
+            }
+        }
+        let mut logistics = Logistics::singleton();
+        for id in self.requests.keys() {
+            logistics.bind_mut().board.release(*id, &self.agent_name);
+        }
+        self.requests.clear();
+        self.route.clear();
+    }
+
+    /// Gives up on carrying goods back, they are taken home with the rest of the load.
+    fn release_returns(&mut self) {
+        let mut logistics = Logistics::singleton();
+        for request in self.returns.drain(..) {
+            logistics.bind_mut().board.release(request.id, &self.agent_name);
+        }
+    }
+
+    /// Goods taken home in the meantime, e.g. on a change of job, have nothing left to return.
+    fn forget_returns_no_longer_carried(&mut self) {
+        let (carried, gone): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut self.returns)
+            .into_iter()
+            .partition(|request| self.inventory.amount(request.resource) > 0);
+        self.returns = carried;
+        let mut logistics = Logistics::singleton();
+        for request in gone {
+            logistics.bind_mut().board.release(request.id, &self.agent_name);
+        }
+    }
+
+    /// Puts goods back at the source of `request` and reopens the request for the next hauler. When
+    /// the source is gone the request is closed and the goods stay in the inventory.
+    fn return_to_source(&mut self, request: &TransportRequest) {
+        let amount = request.amount.min(self.inventory.amount(request.resource));
+        let context = InventoryLedger::context(&self.agent_name, TransactionReason::Transport);
+        let mut ledger = InventoryLedger::singleton();
+        let returned = Building::with_owner_inventory(&request.source, |source| {
+            ledger.bind_mut().log.transfer(&mut self.inventory, source, request.resource, amount, &context)
+        });
+        let mut logistics = Logistics::singleton();
+        match returned {
+            Some(returned) => {
+                godot_print!("Agent {}: Returned {} {} to its source", self.agent_name, returned, request.resource);
+                logistics.bind_mut().board.release(request.id, &self.agent_name);
+            }
+            None => {
+                godot_print!("Agent {}: Source of {} is gone", self.agent_name, request.resource);
+                logistics.bind_mut().board.complete(request.id);
+            }
+        }
+    }
+
+    fn start_next_stop(&mut self, agent_position: Vector2) {
+        let stop = self.route.front().unwrap();
+        self.move_behaviour.start_moving(agent_position, stop.position);
+        self.state = HaulerState::Travelling;
+    }
+
+    fn serve_stop(&mut self) {
+        let stop = self.route.pop_front().unwrap();
+        let Some(request) = self.requests.get(&stop.request).cloned() else {
+            return;
+        };
+        match stop.kind {
+            TransportStopKind::Pickup => self.pick_up(&request),
+            TransportStopKind::Dropoff => self.drop_off(&request),
+        }
+    }
+
+    fn pick_up(&mut self, request: &TransportRequest) {
+        let amount = request.amount.min(self.inventory.free_space());
+        let context = InventoryLedger::context(&self.agent_name, TransactionReason::Transport);
+        let mut ledger = InventoryLedger::singleton();
+        let loaded = Building::with_owner_inventory(&request.source, |source| {
+            ledger.bind_mut().log.transfer(source, &mut self.inventory, request.resource, amount, &context)
+        })
+        .unwrap_or(0);
+        godot_print!("Agent {}: Picked up {} {}", self.agent_name, loaded, request.resource);
+        if loaded == 0 {
+            // Nothing to carry, the dropoff is skipped and the request closed
+            self.close_request(request.id);
+            return;
+        }
+        if loaded < request.amount {
+            // The rest stays on the board for another trip
+            Logistics::singleton().bind_mut().board.split_off(request.id, request.amount - loaded);
+        }
+        self.loaded.insert(request.id, loaded);
+    }
+
+    fn drop_off(&mut self, request: &TransportRequest) {
+        let amount = self.loaded.remove(&request.id).unwrap_or(0);
+        self.close_request(request.id);
+        let delivered = self.deliver(request, amount);
+        if delivered < amount {
+            // The destination is gone or took less, the rest is carried back after the route
+            godot_print!("Agent {}: Could not deliver {} {}", self.agent_name, amount - delivered, request.resource);
+            self.returns.push_back(TransportRequest { amount: amount - delivered, ..request.clone() });
+        }
+        if delivered == 0 {
+            return;
+        }
+        godot_print!("Agent {}: Delivered {} {}", self.agent_name, delivered, request.resource);
+        let Some(mut home) = self.home.clone().filter(|home| home.is_instance_valid()) else {
+            return;
+        };
+        let context = InventoryLedger::context(&self.agent_name, TransactionReason::Wage);
+        let mut treasury = Treasury::singleton();
+        InventoryLedger::singleton().bind_mut().log.transfer(
+            &mut treasury.bind_mut().inventory,
+            &mut home.bind_mut().inventory,
+            InventoryResource::Coins,
+            delivered * self.config.fee_per_unit,
+            &context,
+        );
+    }
+
+    /// Hands `amount` of the request's goods over at its destination and returns how much was taken.
+    fn deliver(&mut self, request: &TransportRequest, amount: i32) -> i32 {
+        if let InventoryOwner::Construction(site) = request.destination {
+            return Building::deliver_materials(site, &mut self.inventory, request.resource, amount, &self.agent_name)
+                .unwrap_or(0);
+        }
+        let context = InventoryLedger::context(&self.agent_name, TransactionReason::Transport);
+        let mut ledger = InventoryLedger::singleton();
+        Building::with_owner_inventory(&request.destination, |destination| {
+            ledger.bind_mut().log.transfer(&mut self.inventory, destination, request.resource, amount, &context)
+        })
+        .unwrap_or(0)
+    }
+
+    fn close_request(&mut self, id: TransportRequestId) {
+        Logistics::singleton().bind_mut().board.complete(id);
+        self.requests.remove(&id);
+        self.route.retain(|stop| stop.request != id);
+    }
+
+    fn start_returning_to_home(&mut self, agent_position: Vector2) -> std::result::Result<(), FailureReason> {
+        start_returning_home(&mut self.move_behaviour, self.home.as_ref(), agent_position, &self.agent_name)?;
+        self.state = HaulerState::ReturningToHome;
+        Ok(())
+    }
+
+    fn fail(&mut self, reason: FailureReason) -> WorkResult {
+        self.move_behaviour.stop();
+        self.drop_route();
+        self.state = HaulerState::Idle;
+        WorkResult { result: Result::Failure(reason), next_position: None }
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt;
+
+use godot::prelude::*;
+
+/// One level of an agent's decision making, e.g. `FarmerBehaviour::FieldRemoving`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateFrame {
+    pub behaviour: &'static str,
+    pub state: String,
+}
+
+/// Active states of an agent from the outermost behaviour inwards, along with where it is
+/// walking to and which cell it holds for a building.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StateStack {
+    pub frames: Vec<StateFrame>,
+    pub move_target: Option<Vector2>,
+    pub reserved_cell: Option<Vector2>,
+}
+
+impl StateStack {
+    /// Stack with a single frame, `state` is shown as its `Debug` output.
+    pub fn of(behaviour: &'static str, state: impl fmt::Debug) -> Self {
+        Self { frames: vec![StateFrame { behaviour, state: format!("{:?}", state) }], ..Self::default() }
+    }
+
+    /// Puts the stack of an inner behaviour below this one. Its move target and reserved cell
+    /// win, the innermost behaviour knows best where the agent is going.
+    pub fn then(mut self, inner: StateStack) -> Self {
+        self.frames.extend(inner.frames);
+        self.move_target = inner.move_target.or(self.move_target);
+        self.reserved_cell = inner.reserved_cell.or(self.reserved_cell);
+        self
+    }
+
+    pub fn with_move_target(mut self, move_target: Option<Vector2>) -> Self {
+        self.move_target = move_target;
+        self
+    }
+
+    pub fn with_reserved_cell(mut self, reserved_cell: Option<Vector2>) -> Self {
+        self.reserved_cell = reserved_cell;
+        self
+    }
+
+    /// Structured form handed to scripts: `frames` as a list of `behaviour`/`state` pairs,
+    /// `move_target` and `reserved_cell` null when unset.
+    pub fn to_dictionary(&self) -> Dictionary {
+        let mut frames = VariantArray::new();
+        for frame in &self.frames {
+            frames.push(&dict! { "behaviour": frame.behaviour, "state": frame.state.clone() }.to_variant());
+        }
+        let optional = |position: Option<Vector2>| position.map_or(Variant::nil(), |position| position.to_variant());
+        dict! {
+            "frames": frames,
+            "text": self.to_string(),
+            "move_target": optional(self.move_target),
+            "reserved_cell": optional(self.reserved_cell),
+        }
+    }
+}
+
+impl fmt::Display for StateStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, frame) in self.frames.iter().enumerate() {
+            if index > 0 {
+                write!(f, " > ")?;
+            }
+            write!(f, "{}::{}", frame.behaviour, frame.state)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,423 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::introspection::StateStack;
+use super::move_behaviour::{MoveBehaviour, Result};
+use super::random::seeded_rng;
+use super::utility::{pick_best_job, score_job, unavailable_job, JobScore, UtilityInputs, UtilityWeights};
+use super::work_behaviour::{IWorkBehaviour, WorkResult};
+use crate::building::Building;
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::labour_exchange::LabourExchange;
+use crate::resources::labour_market::{pick_better_profession, WageSample};
+use crate::resources::prices::Prices;
+use crate::world::job_board::{Job as BoardJob, Skill};
+use crate::world::world_clock::WorldClock;
+use godot::prelude::*;
+
+/// When an agent considers changing profession.
+pub struct CareerConfig {
+    /// Seconds of wage history compared
+    pub wage_window: f64,
+    /// Another profession has to pay this much more, relative, before the agent switches
+    pub switch_threshold: f32,
+    /// Chance to reconsider the profession after each finished round
+    pub switch_rate: f32,
+    /// Seconds an agent stays in a profession before it may switch again
+    pub minimum_stay: f32,
+    /// Seconds without work while learning the new profession
+    pub retraining_duration: f32,
+}
+
+pub struct JobSelectorConfig {
+    pub weights: UtilityWeights,
+    /// Skill gained in a job for every finished round of it
+    pub skill_gain: f32,
+    pub max_skill: f32,
+    pub career: CareerConfig,
+}
+
+/// A profession an agent can take up, with how good the agent is at it.
+pub struct Job {
+    pub behaviour: Box<dyn IWorkBehaviour>,
+    pub skill: f32,
+}
+
+impl Job {
+    pub fn new(behaviour: Box<dyn IWorkBehaviour>) -> Self {
+        Self { behaviour, skill: 0.0 }
+    }
+}
+
+/// Holds several work behaviours. The agent takes up the profession scoring highest and sticks
+/// with it, doing whichever other job scores highest when its profession has no work. Jobs are
+/// scored again whenever the current one is between tasks, and goods still carried for a job the
+/// agent leaves are walked home first. After every round the income is reported to the labour
+/// market, and once in a while the agent switches to a profession that pays clearly better.
+pub struct JobSelector {
+    jobs: Vec<Job>,
+    move_behaviour: MoveBehaviour,
+    config: JobSelectorConfig,
+    prices: Prices,
+    profession: Option<usize>,
+    time_in_profession: f32,
+    retraining_left: f32,
+    current_job: Option<usize>,
+    round_time: f32,
+    /// Ledger entries from this index on belong to the current round
+    round_first_entry: usize,
+    /// Goods of jobs the agent left are on their way home
+    taking_goods_home: bool,
+    walking_home: bool,
+    scores: Vec<JobScore>,
+    agent_name: String,
+    home: Option<Gd<Building>>,
+    parent_node: Option<Gd<Node>>,
+    /// Seeded from the agent name on the first career decision
+    rng: Option<StdRng>,
+}
+
+impl IWorkBehaviour for JobSelector {
+    fn work(&mut self, delta: f64, agent_position: Vector2) -> WorkResult {
+        if self.taking_goods_home {
+            return self.take_goods_home(delta, agent_position);
+        }
+        if self.retraining_left > 0.0 {
+            self.retraining_left -= delta as f32;
+            return WorkResult { result: Result::Running, next_position: None };
+        }
+        self.time_in_profession += delta as f32;
+        let current_job = match self.current_job {
+            Some(current_job) if self.jobs[current_job].behaviour.is_idle() => {
+                match self.rescore_jobs(current_job, agent_position) {
+                    Some(current_job) => current_job,
+                    None => return WorkResult { result: Result::Running, next_position: None },
+                }
+            }
+            Some(current_job) => current_job,
+            None => match self.select_job(agent_position) {
+                Some(current_job) => current_job,
+                None => return WorkResult { result: Result::Success, next_position: None },
+            },
+        };
+        self.round_time += delta as f32;
+        let job = &mut self.jobs[current_job];
+        let work_result = job.behaviour.work(delta, agent_position);
+        match work_result.result {
+            Result::Running => {}
+            Result::Success => {
+                job.skill = (job.skill + self.config.skill_gain).min(self.config.max_skill);
+                self.finish_round(current_job);
+            }
+            Result::Failure(_) => {
+                self.finish_round(current_job);
+            }
+        }
+        work_result
+    }
+
+    fn start_work(&mut self, home: Gd<Building>, agent_name: String, parent_node: Option<Gd<Node>>) {
+        self.home = Some(home);
+        self.agent_name = agent_name;
+        self.parent_node = parent_node;
+        self.current_job = None;
+    }
+
+    fn is_work_available(&self) -> bool {
+        self.taking_goods_home
+            || self.retraining_left > 0.0
+            || self.jobs.iter().any(|job| job.behaviour.is_work_available())
+    }
+
+    fn cancel(&mut self) {
+        if let Some(current_job) = self.current_job.take() {
+            self.jobs[current_job].behaviour.cancel();
+        }
+        // The goods are still taken home, the walk starts over on the next tick
+        self.move_behaviour.stop();
+        self.walking_home = false;
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        if self.walking_home {
+            self.move_behaviour.resume(agent_position);
+        } else if let Some(current_job) = self.current_job {
+            self.jobs[current_job].behaviour.resume(agent_position);
+        }
+    }
+
+    fn pause(&mut self) {
+        if let Some(current_job) = self.current_job {
+            self.jobs[current_job].behaviour.pause();
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.current_job.is_none() && !self.taking_goods_home
+    }
+
+    fn job_name(&self) -> &'static str {
+        match self.current_job.or(self.profession) {
+            Some(job) => self.jobs[job].behaviour.job_name(),
+            None => "Unemployed",
+        }
+    }
+
+    fn utility_inputs(&self, agent_position: Vector2, home: &Gd<Building>, prices: &Prices) -> UtilityInputs {
+        let best_job = self.current_job.or_else(|| pick_best_job(&self.scores)).unwrap_or(0);
+        self.jobs[best_job].behaviour.utility_inputs(agent_position, home, prices)
+    }
+
+    fn job_scores(&self) -> Vec<JobScore> {
+        self.scores.clone()
+    }
+
+    fn state_stack(&self) -> StateStack {
+        if self.retraining_left > 0.0 {
+            return StateStack::of("JobSelector", format_args!("Retraining({:.1})", self.retraining_left));
+        }
+        match self.current_job {
+            Some(job) => {
+                let behaviour = &self.jobs[job].behaviour;
+                StateStack::of("JobSelector", format_args!("{}", behaviour.job_name())).then(behaviour.state_stack())
+            }
+            None => StateStack::of("JobSelector", format_args!("Choosing")),
+        }
+    }
+
+    fn skills(&self) -> Vec<Skill> {
+        match self.profession.filter(|_| self.retraining_left <= 0.0) {
+            Some(profession) => self.jobs[profession].behaviour.skills(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Board jobs are done in the agent's profession and count as one of its rounds.
+    fn take_job(&mut self, job: &BoardJob, agent_position: Vector2) -> bool {
+        let Some(profession) = self.profession.filter(|_| self.retraining_left <= 0.0) else {
+            return false;
+        };
+        let Some(home) = self.home.clone().filter(|home| home.is_instance_valid()) else {
+            return false;
+        };
+        let behaviour = &mut self.jobs[profession].behaviour;
+        behaviour.start_work(home, self.agent_name.clone(), self.parent_node.clone());
+        if !behaviour.take_job(job, agent_position) {
+            return false;
+        }
+        self.start_round(profession);
+        true
+    }
+}
+
+impl JobSelector {
+    pub fn new(jobs: Vec<Job>, move_behaviour: MoveBehaviour, config: JobSelectorConfig, prices: Prices) -> Self {
+        Self {
+            jobs,
+            move_behaviour,
+            config,
+            prices,
+            profession: None,
+            time_in_profession: 0.0,
+            retraining_left: 0.0,
+            current_job: None,
+            round_time: 0.0,
+            round_first_entry: 0,
+            taking_goods_home: false,
+            walking_home: false,
+            scores: Vec::new(),
+            agent_name: String::new(),
+            home: None,
+            parent_node: None,
+            rng: None,
+        }
+    }
+
+    fn score_jobs(&self, agent_position: Vector2, home: &Gd<Building>) -> Vec<JobScore> {
+        self.jobs
+            .iter()
+            .map(|job| {
+                let name = job.behaviour.job_name();
+                if !job.behaviour.is_work_available() {
+                    return unavailable_job(name);
+                }
+                let inputs = job.behaviour.utility_inputs(agent_position, home, &self.prices);
+                score_job(name, inputs, job.skill, &self.config.weights)
+            })
+            .collect()
+    }
+
+    fn select_job(&mut self, agent_position: Vector2) -> Option<usize> {
+        let home = self.home.clone().filter(|home| home.is_instance_valid())?;
+        self.scores = self.score_jobs(agent_position, &home);
+        let job = self.preferred_job()?;
+        godot_print!("Agent {}: Picked job {}", self.agent_name, self.scores[job]);
+        self.jobs[job].behaviour.start_work(home, self.agent_name.clone(), self.parent_node.clone());
+        self.start_round(job);
+        Some(job)
+    }
+
+    /// The profession if it has work, otherwise the best scoring job.
+    fn preferred_job(&mut self) -> Option<usize> {
+        let best_job = pick_best_job(&self.scores)?;
+        let profession = *self.profession.get_or_insert_with(|| {
+            godot_print!("Agent {}: Takes up profession {}", self.agent_name, self.scores[best_job].job);
+            best_job
+        });
+        Some(if self.scores[profession].available { profession } else { best_job })
+    }
+
+    /// Scores the jobs again while `current_job` is between tasks and changes over when another
+    /// job should be done now, ending the round of the current one. Returns `None` while goods of
+    /// the old job are taken home first or the agent retrains.
+    fn rescore_jobs(&mut self, current_job: usize, agent_position: Vector2) -> Option<usize> {
+        let Some(home) = self.home.clone().filter(|home| home.is_instance_valid()) else {
+            return Some(current_job);
+        };
+        self.scores = self.score_jobs(agent_position, &home);
+        match self.preferred_job() {
+            Some(job) if job != current_job => {
+                godot_print!(
+                    "Agent {}: Changes from {} to {}",
+                    self.agent_name,
+                    self.scores[current_job],
+                    self.scores[job]
+                );
+                self.finish_round(current_job);
+                self.taking_goods_home |= self.carries_goods();
+                if self.taking_goods_home || self.retraining_left > 0.0 {
+                    return None;
+                }
+                self.select_job(agent_position)
+            }
+            _ => Some(current_job),
+        }
+    }
+
+    /// Whether any job other than the current one still holds carried goods.
+    fn carries_goods(&mut self) -> bool {
+        let current_job = self.current_job;
+        self.jobs
+            .iter_mut()
+            .enumerate()
+            .filter(|(job, _)| Some(*job) != current_job)
+            .any(|(_, job)| job.behaviour.carried_goods().is_some_and(|goods| !goods.is_empty()))
+    }
+
+    /// Walks what the agent carries for jobs it left home, so nothing is left with a job the
+    /// agent stopped doing.
+    fn take_goods_home(&mut self, delta: f64, agent_position: Vector2) -> WorkResult {
+        let Some(mut home) = self.home.clone().filter(|home| home.is_instance_valid()) else {
+            // Nowhere to take them, the jobs keep the goods
+            self.taking_goods_home = false;
+            self.walking_home = false;
+            return WorkResult { result: Result::Running, next_position: None };
+        };
+        if !self.walking_home {
+            godot_print!("Agent {}: Taking goods home", self.agent_name);
+            self.move_behaviour.start_moving(agent_position, home.bind().base().get_position());
+            self.walking_home = true;
+        }
+        let (result, next_position) = self.move_behaviour.move_agent(delta);
+        match result {
+            Result::Running => WorkResult { result, next_position: Some(next_position) },
+            Result::Success => {
+                for job in self.jobs.iter_mut() {
+                    if let Some(goods) = job.behaviour.carried_goods() {
+                        home.bind_mut().deposit_from(goods, &self.agent_name);
+                    }
+                }
+                self.taking_goods_home = false;
+                self.walking_home = false;
+                WorkResult { result: Result::Success, next_position: Some(next_position) }
+            }
+            Result::Failure(reason) => {
+                // Tried again from wherever the agent is on the next tick
+                self.move_behaviour.stop();
+                self.walking_home = false;
+                WorkResult { result: Result::Failure(reason), next_position: None }
+            }
+        }
+    }
+
+    fn start_round(&mut self, job: usize) {
+        self.current_job = Some(job);
+        self.round_time = 0.0;
+        self.round_first_entry = InventoryLedger::singleton().bind().log.entries().len();
+    }
+
+    /// Value of what this agent brought home since the round started.
+    fn round_income(&self) -> f32 {
+        let Some(home) = self.home.as_ref().filter(|home| home.is_instance_valid()) else {
+            return 0.0;
+        };
+        let home_owner = home.bind().inventory.owner().clone();
+        let ledger = InventoryLedger::singleton();
+        let ledger = ledger.bind();
+        ledger.log.entries()[self.round_first_entry.min(ledger.log.entries().len())..]
+            .iter()
+            .filter(|entry| entry.agent.as_deref() == Some(self.agent_name.as_str()) && entry.destination == home_owner)
+            .map(|entry| self.prices.price(entry.resource) * entry.amount as f32)
+            .sum()
+    }
+
+    fn finish_round(&mut self, job: usize) {
+        self.current_job = None;
+        let now = WorldClock::seconds();
+        let sample = WageSample {
+            job: self.jobs[job].behaviour.job_name(),
+            agent: self.agent_name.clone(),
+            income: self.round_income(),
+            duration: self.round_time,
+            time: now,
+        };
+        let mut labour_exchange = LabourExchange::singleton();
+        let mut labour_exchange = labour_exchange.bind_mut();
+        labour_exchange.market.record(sample);
+        labour_exchange.market.forget_before(now - self.config.career.wage_window);
+        drop(labour_exchange);
+        self.consider_switching(now);
+    }
+
+    fn consider_switching(&mut self, now: f64) {
+        let Some(profession) = self.profession else {
+            return;
+        };
+        let career = &self.config.career;
+        let agent_name = &self.agent_name;
+        let rng = self.rng.get_or_insert_with(|| seeded_rng(agent_name));
+        if self.time_in_profession < career.minimum_stay || rng.gen::<f32>() >= career.switch_rate {
+            return;
+        }
+        let profession_name = self.jobs[profession].behaviour.job_name();
+        let labour_exchange = LabourExchange::singleton();
+        let market = &labour_exchange.bind().market;
+        let own_wage = market.agent_wage(&self.agent_name, profession_name, now, career.wage_window);
+        let market_wages: Vec<(&'static str, Option<f32>)> = self
+            .jobs
+            .iter()
+            .map(|job| job.behaviour.job_name())
+            .filter(|job| *job != profession_name)
+            .map(|job| (job, market.market_wage(job, now, career.wage_window)))
+            .collect();
+        // The income lost while retraining, spread over the shortest stay in the new profession
+        let retraining_cost = own_wage.unwrap_or_default() * career.retraining_duration / career.minimum_stay.max(1.0);
+        let Some(better_profession) =
+            pick_better_profession(own_wage, &market_wages, career.switch_threshold, retraining_cost)
+        else {
+            return;
+        };
+        godot_print!(
+            "Agent {}: Switching profession from {} to {}, earned {:.2}/s",
+            self.agent_name,
+            profession_name,
+            better_profession,
+            own_wage.unwrap_or_default()
+        );
+        self.jobs[profession].behaviour.abandon();
+        self.profession = self.jobs.iter().position(|job| job.behaviour.job_name() == better_profession);
+        self.time_in_profession = 0.0;
+        self.retraining_left = career.retraining_duration;
+        self.taking_goods_home |= self.carries_goods();
+    }
+}
@@ -0,0 +1,311 @@
+use super::introspection::StateStack;
+use super::move_and_build_behaviour::{allows_structure, MoveAndBuildBehaviour};
+use super::move_behaviour::{FailureReason, MoveBehaviour, Result};
+use super::perception::nearest_within;
+use super::utility::{household_need, UtilityInputs};
+use super::work_behaviour::{leave_for_hauler, return_home, start_returning_home, IWorkBehaviour, WorkResult};
+use crate::building::{Building, Deposit, Quarry};
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::prices::Prices;
+use crate::resources::transaction_log::InventoryOwner;
+use godot::prelude::*;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum MinerState {
+    Idle,
+    QuarryBuilding,
+    MovingToQuarry,
+    Mining,
+    ReturningToHome,
+}
+
+pub struct MinerBehaviourConfig {
+    pub carry_capacity: i32,
+    /// Units per second mined from a full deposit, see `deposit::extraction_rate`
+    pub extraction_rate: f32,
+    pub work_offset: Vector2,
+}
+
+/// Builds a quarry on the closest free deposit, mines it until it is exhausted and then moves
+/// on to the next deposit, however far away.
+pub struct MinerBehaviour {
+    state: MinerState,
+    quarry_build_behaviour: MoveAndBuildBehaviour<Quarry>,
+    move_behaviour: MoveBehaviour,
+    config: MinerBehaviourConfig,
+    quarry: Option<Gd<Quarry>>,
+    deposit: Option<Gd<Deposit>>,
+    mining_progress: f32,
+    inventory: Inventory,
+    agent_name: String,
+    home: Option<Gd<Building>>,
+    parent_node: Option<Gd<Node>>,
+}
+
+impl IWorkBehaviour for MinerBehaviour {
+    fn work(&mut self, delta: f64, agent_position: Vector2) -> WorkResult {
+        loop {
+            match self.state {
+                MinerState::Idle => {
+                    self.forget_lost_quarry();
+                    let has_quarry = self.quarry.as_ref().is_some_and(|quarry| quarry.bind().is_completed());
+                    if !self.inventory.is_full() && has_quarry {
+                        self.start_moving_to_quarry(agent_position);
+                    } else if self.inventory.is_full() || (!self.inventory.is_empty() && self.quarry.is_none()) {
+                        if let Err(reason) = self.start_returning_to_home(agent_position) {
+                            return WorkResult { result: Result::Failure(reason), next_position: None };
+                        }
+                    } else if let Some(deposit) =
+                        find_nearest_free_deposit(agent_position).filter(|_| self.quarry.is_none())
+                    {
+                        if !self.start_quarry_building(deposit, agent_position) {
+                            return WorkResult { result: Result::Failure(FailureReason::TargetLost), next_position: None };
+                        }
+                    } else {
+                        return WorkResult { result: Result::Success, next_position: None };
+                    }
+                }
+                MinerState::QuarryBuilding => {
+                    let (result, next_position) = self.quarry_build_behaviour.build(delta);
+                    match result {
+                        Result::Running => {
+                            return WorkResult { result, next_position };
+                        }
+                        Result::Success => {
+                            godot_print!("Agent {}: Quarry build complete", self.agent_name);
+                            self.state = MinerState::Idle;
+                            return WorkResult { result: Result::Running, next_position };
+                        }
+                        Result::Failure(reason) => {
+                            // The quarry build behaviour already removed the unfinished quarry
+                            self.quarry = None;
+                            self.deposit = None;
+                            self.state = MinerState::Idle;
+                            return WorkResult { result: Result::Failure(reason), next_position };
+                        }
+                    }
+                }
+                MinerState::MovingToQuarry => {
+                    if !self.is_quarry_valid() {
+                        return self.fail(FailureReason::TargetLost);
+                    }
+                    let (result, next_position) = self.move_behaviour.move_agent(delta);
+                    match result {
+                        Result::Running => {
+                            return WorkResult { result, next_position: Some(next_position) };
+                        }
+                        Result::Success => {
+                            self.mining_progress = 0.0;
+                            self.state = MinerState::Mining;
+                            return WorkResult { result: Result::Running, next_position: Some(next_position) };
+                        }
+                        Result::Failure(reason) => return self.fail(reason),
+                    }
+                }
+                MinerState::Mining => {
+                    if !self.is_quarry_valid() {
+                        return self.fail(FailureReason::TargetLost);
+                    }
+                    if self.mine(delta) {
+                        return WorkResult { result: Result::Running, next_position: None };
+                    }
+                    if self.inventory.is_full() {
+                        let quarry = self.quarry.clone().unwrap();
+                        leave_for_hauler(&mut self.inventory, &quarry, self.home.as_ref(), &self.agent_name);
+                    }
+                    self.state = MinerState::Idle;
+                }
+                MinerState::ReturningToHome => {
+                    let work_result = return_home(
+                        &mut self.move_behaviour,
+                        self.home.as_mut(),
+                        &mut self.inventory,
+                        delta,
+                        &self.agent_name,
+                    );
+                    if work_result.result != Result::Running {
+                        // Whatever is carried stays in the inventory for the next trip
+                        self.state = MinerState::Idle;
+                    }
+                    return work_result;
+                }
+            }
+        }
+    }
+
+    fn start_work(&mut self, home: Gd<Building>, agent_name: String, parent_node: Option<Gd<Node>>) {
+        self.home = Some(home);
+        self.state = MinerState::Idle;
+        self.inventory.set_owner(InventoryOwner::Agent(agent_name.clone()));
+        self.agent_name = agent_name;
+        self.parent_node = parent_node;
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        match self.state {
+            MinerState::Idle => {}
+            MinerState::QuarryBuilding => self.quarry_build_behaviour.resume(agent_position),
+            // Mining restarts once the miner is back at the quarry
+            MinerState::MovingToQuarry | MinerState::Mining => {
+                self.move_behaviour.resume(agent_position);
+                self.state = MinerState::MovingToQuarry;
+            }
+            MinerState::ReturningToHome => self.move_behaviour.resume(agent_position),
+        }
+    }
+
+    fn is_work_available(&self) -> bool {
+        !self.inventory.is_empty()
+            || self.is_quarry_valid()
+            || Deposit::all().iter().any(|deposit| deposit.bind().is_free())
+    }
+
+    fn is_idle(&self) -> bool {
+        self.state == MinerState::Idle
+    }
+
+    fn carried_goods(&mut self) -> Option<&mut Inventory> {
+        Some(&mut self.inventory)
+    }
+
+    fn job_name(&self) -> &'static str {
+        "Miner"
+    }
+
+    fn state_stack(&self) -> StateStack {
+        let stack = StateStack::of("MinerBehaviour", self.state);
+        match self.state {
+            MinerState::QuarryBuilding => stack.then(self.quarry_build_behaviour.state_stack()),
+            MinerState::MovingToQuarry | MinerState::ReturningToHome => stack.then(self.move_behaviour.state_stack()),
+            _ => stack,
+        }
+    }
+
+    fn utility_inputs(&self, agent_position: Vector2, home: &Gd<Building>, prices: &Prices) -> UtilityInputs {
+        let work_position = match self.quarry.as_ref().filter(|quarry| quarry.is_instance_valid()) {
+            Some(quarry) => Some(quarry.bind().base().get_position()),
+            None => find_nearest_free_deposit(agent_position).map(|deposit| deposit.bind().base().get_position()),
+        };
+        UtilityInputs {
+            household_need: household_need(&home.bind().inventory, InventoryResource::Stone),
+            price: prices.price(InventoryResource::Stone) * self.config.carry_capacity as f32,
+            distance: work_position.map_or(0.0, |position| agent_position.distance_to(position)),
+        }
+    }
+
+    fn cancel(&mut self) {
+        godot_print!("Agent {}: Miner cancelled in state {:?}", self.agent_name, self.state);
+        if self.state == MinerState::QuarryBuilding {
+            self.quarry_build_behaviour.cancel();
+            self.quarry = None;
+            self.deposit = None;
+        }
+        self.move_behaviour.stop();
+        self.state = MinerState::Idle;
+    }
+}
+
+/// Closest deposit that still holds something, has no quarry on it yet and lies on terrain a
+/// quarry can go on.
+pub fn find_nearest_free_deposit(position: Vector2) -> Option<Gd<Deposit>> {
+    let deposits: Vec<Gd<Deposit>> = Deposit::all()
+        .into_iter()
+        .filter(|deposit| deposit.bind().is_free())
+        .filter(|deposit| allows_structure::<Quarry>(deposit.bind().base().get_position()))
+        .collect();
+    let positions: Vec<Vector2> = deposits.iter().map(|deposit| deposit.bind().base().get_position()).collect();
+    nearest_within(position, f32::INFINITY, &positions).map(|index| deposits[index].clone())
+}
+
+impl MinerBehaviour {
+    pub fn new(
+        quarry_build_behaviour: MoveAndBuildBehaviour<Quarry>,
+        move_behaviour: MoveBehaviour,
+        config: MinerBehaviourConfig,
+    ) -> Self {
+        Self {
+            state: MinerState::Idle,
+            quarry_build_behaviour,
+            move_behaviour,
+            quarry: None,
+            deposit: None,
+            mining_progress: 0.0,
+            inventory: Inventory::with_capacity(InventoryOwner::default(), config.carry_capacity),
+            config,
+            agent_name: String::new(),
+            home: None,
+            parent_node: None,
+        }
+    }
+
+    fn is_quarry_valid(&self) -> bool {
+        self.quarry.as_ref().is_some_and(|quarry| quarry.is_instance_valid())
+            && self
+                .deposit
+                .as_ref()
+                .is_some_and(|deposit| deposit.is_instance_valid() && !deposit.bind().is_exhausted())
+    }
+
+    /// An exhausted deposit takes its quarry down with it.
+    fn forget_lost_quarry(&mut self) {
+        if self.quarry.is_some() && !self.is_quarry_valid() {
+            godot_print!("Agent {}: Quarry is gone", self.agent_name);
+            self.quarry = None;
+            self.deposit = None;
+        }
+    }
+
+    /// Starts a quarry on the deposit, false if the terrain there doesn't allow one.
+    fn start_quarry_building(&mut self, mut deposit: Gd<Deposit>, agent_position: Vector2) -> bool {
+        godot_print!("Agent {}: Starting quarry build", self.agent_name);
+        let deposit_position = deposit.bind().base().get_position();
+        let Some(quarry) = self.quarry_build_behaviour.start_construction_at(
+            deposit_position,
+            agent_position,
+            self.agent_name.clone(),
+            self.parent_node.clone(),
+        ) else {
+            return false;
+        };
+        deposit.bind_mut().set_quarry(quarry.clone());
+        self.quarry = Some(quarry);
+        self.deposit = Some(deposit);
+        self.state = MinerState::QuarryBuilding;
+        true
+    }
+
+    fn start_moving_to_quarry(&mut self, agent_position: Vector2) {
+        godot_print!("Agent {}: Starting move to quarry", self.agent_name);
+        let work_position = self.quarry.as_ref().unwrap().bind().base().get_position() + self.config.work_offset;
+        self.move_behaviour.start_moving(agent_position, work_position);
+        self.state = MinerState::MovingToQuarry;
+    }
+
+    /// Extracts whatever the deposit yields this tick, false once the miner has to stop.
+    fn mine(&mut self, delta: f64) -> bool {
+        let mut deposit = self.deposit.clone().unwrap();
+        let mut deposit = deposit.bind_mut();
+        self.mining_progress += deposit.extraction_rate(self.config.extraction_rate) * delta as f32;
+        while self.mining_progress >= 1.0 && !self.inventory.is_full() && !deposit.is_exhausted() {
+            self.mining_progress -= 1.0;
+            if let Some(resource) = deposit.extract(&mut self.inventory, &self.agent_name) {
+                godot_print!("Agent {}: Mined {}", self.agent_name, resource);
+            }
+        }
+        !self.inventory.is_full() && !deposit.is_exhausted()
+    }
+
+    fn start_returning_to_home(&mut self, agent_position: Vector2) -> std::result::Result<(), FailureReason> {
+        start_returning_home(&mut self.move_behaviour, self.home.as_ref(), agent_position, &self.agent_name)?;
+        self.state = MinerState::ReturningToHome;
+        Ok(())
+    }
+
+    fn fail(&mut self, reason: FailureReason) -> WorkResult {
+        // Mined stone is kept for the next trip home
+        self.move_behaviour.stop();
+        self.forget_lost_quarry();
+        self.state = MinerState::Idle;
+        WorkResult { result: Result::Failure(reason), next_position: None }
+    }
+}
@@ -4,4 +4,25 @@ pub mod free_space_manager;
 pub mod move_and_build_behaviour;
 pub mod work_behaviour;
 pub mod farmer_behaviour;
+pub mod berry_picker_behaviour;
+pub mod woodcutter_behaviour;
+pub mod miner_behaviour;
+pub mod hauler_behaviour;
 pub mod behaviour_regestry;
+pub mod tour_planner;
+pub mod perception;
+pub mod random;
+pub mod threat_awareness;
+pub mod wolf_behaviour;
+pub mod tree_behaviour;
+pub mod tree_loader;
+pub mod guard_behaviour;
+pub mod utility;
+pub mod job_selector;
+pub mod goap;
+pub mod goap_behaviour;
+pub mod schedule_behaviour;
+pub mod introspection;
+pub mod pathfinding;
+pub mod flow_field;
+pub mod steering;
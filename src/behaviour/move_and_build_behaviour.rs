@@ -2,22 +2,28 @@ use godot::prelude::*;
 
 use super::{
     free_space_manager::FreeSpaceManager,
-    move_behaviour::{MoveBehaviour, Result},
+    introspection::StateStack,
+    move_behaviour::{FailureReason, MoveBehaviour, Result},
 };
 use crate::building::IBuilding;
+use crate::resources::logistics::Logistics;
+use crate::resources::transaction_log::InventoryOwner;
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum State {
-    IdleState,
-    MovingState,
-    BuildingState,
+    Idle,
+    Moving,
+    Building,
 }
 
 #[derive(Clone)]
 pub struct MoveAndBuildBehaviourConfig {
     pub building_radius: f32,
     pub build_offset: Vector2,
-    pub building_duration: f32,
+    /// Work this agent adds to a construction site per second
+    pub build_rate: f32,
+    /// How far the agent goes for construction jobs on the job board, 0 to never help
+    pub help_radius: f32,
 }
 
 pub struct MoveAndBuildBehaviour<T: IBuilding> {
@@ -26,26 +32,32 @@ pub struct MoveAndBuildBehaviour<T: IBuilding> {
     building: Option<Gd<T>>,
     agent_name: String,
     is_construction: bool,
-    building_progress: f32,
+    reserved_position: Option<Vector2>,
+    /// The building was put up by this behaviour, so it is removed again when abandoned
+    started_building: bool,
+    /// Work was taken off the building being deconstructed, so it is restored when abandoned
+    deconstruction_started: bool,
     config: MoveAndBuildBehaviourConfig,
 }
 
 impl<T: IBuilding + Inherits<Node>> MoveAndBuildBehaviour<T> {
     pub fn new(move_behaviour: MoveBehaviour, config: MoveAndBuildBehaviourConfig) -> Self {
         Self {
-            state: State::IdleState,
+            state: State::Idle,
             move_behaviour,
             building: None,
             agent_name: String::new(),
             is_construction: true,
-            building_progress: 0.0,
+            reserved_position: None,
+            started_building: false,
+            deconstruction_started: false,
             config,
         }
     }
 
     fn calculate_free_space_position(&self, target_position: Vector2, radius: f32) -> Vector2 {
         let mut free_space_manager = FreeSpaceManager::singleton();
-        let build_position = free_space_manager.bind().find_random_free_position_near(target_position, radius);
+        let build_position = free_space_manager.bind().find_random_free_position_near(target_position, radius, T::structure());
         free_space_manager.bind_mut().add_occupied_position(build_position);
         build_position
     }
@@ -60,20 +72,90 @@ impl<T: IBuilding + Inherits<Node>> MoveAndBuildBehaviour<T> {
     }
 
     pub fn start_construction(&mut self, current_position: Vector2, agent_name: String, parent_node: Option<Gd<Node>>) -> Gd<T> {
-        self.agent_name = agent_name;
         let building_position = self.calculate_free_space_position(current_position, self.config.building_radius);
-        let building = T::from_position(building_position);
+        let building = self.put_up(building_position, current_position, agent_name, parent_node);
+        self.reserved_position = Some(building_position);
+        building
+    }
+
+    /// Puts a new building up at a given spot without reserving its cell, for buildings that go on
+    /// top of something already holding the cell, like a quarry on a deposit. None when the
+    /// terrain there doesn't allow the building.
+    pub fn start_construction_at(
+        &mut self,
+        building_position: Vector2,
+        current_position: Vector2,
+        agent_name: String,
+        parent_node: Option<Gd<Node>>,
+    ) -> Option<Gd<T>> {
+        if !allows_structure::<T>(building_position) {
+            godot_print!("Agent {}: Terrain doesn't allow a {:?} there", agent_name, T::structure());
+            return None;
+        }
+        Some(self.put_up(building_position, current_position, agent_name, parent_node))
+    }
+
+    fn put_up(
+        &mut self,
+        building_position: Vector2,
+        current_position: Vector2,
+        agent_name: String,
+        parent_node: Option<Gd<Node>>,
+    ) -> Gd<T> {
+        self.agent_name = agent_name;
+        let mut building = T::from_position(building_position);
+        building.bind_mut().construction_mut().join(&self.agent_name);
         self.add_building_to_parent(&building, parent_node);
+        self.reserved_position = None;
+        self.started_building = true;
         self.is_construction = true;
-        self.building_progress = 0.0;
         self.start_move_to_build(building.clone(), current_position);
         building
     }
 
+    pub fn help_radius(&self) -> f32 {
+        self.config.help_radius
+    }
+
+    /// Current state with the move target and how far the building is along.
+    pub fn state_stack(&self) -> StateStack {
+        let progress = match self.building.as_ref().filter(|building| building.is_instance_valid()) {
+            Some(building) => building.bind().construction().progress(),
+            None => 0.0,
+        };
+        let stack = match self.state {
+            State::Idle => StateStack::of("MoveAndBuild", self.state),
+            State::Moving => {
+                let target = self.move_behaviour.target().unwrap_or_default();
+                StateStack::of("MoveAndBuild", format_args!("{:?}({}, {:.2})", self.state, target, progress))
+                    .with_move_target(self.move_behaviour.target())
+            }
+            State::Building => StateStack::of("MoveAndBuild", format_args!("{:?}({:.2})", self.state, progress)),
+        };
+        stack.with_reserved_cell(self.reserved_position)
+    }
+
+    /// Joins the construction of a building that already exists, one someone else started or a field
+    /// to plant again. The building stays when this agent gives up, it only adds work while it has a slot.
+    pub fn start_joining_construction(&mut self, building: Gd<T>, current_position: Vector2, agent_name: String) -> bool {
+        self.agent_name = agent_name;
+        if !building.clone().bind_mut().construction_mut().join(&self.agent_name) {
+            return false;
+        }
+        godot_print!("Agent {}: Joining construction", self.agent_name);
+        self.reserved_position = None;
+        self.started_building = false;
+        self.is_construction = true;
+        self.start_move_to_build(building, current_position);
+        true
+    }
+
     pub fn start_deconstruction(&mut self, building: Gd<T>, current_position: Vector2) {
         godot_print!("Agent {}: Starting move to deconstruct", self.agent_name);
         self.is_construction = false;
-        self.building_progress = self.config.building_duration;
+        self.reserved_position = None;
+        self.started_building = false;
+        self.deconstruction_started = false;
         self.start_move_to_build(building, current_position);
     }
 
@@ -83,63 +165,157 @@ impl<T: IBuilding + Inherits<Node>> MoveAndBuildBehaviour<T> {
         self.building = Some(building);
         let build_move_target = build_position + self.config.build_offset;
         self.move_behaviour.start_moving(current_position, build_move_target);
-        self.state = State::MovingState;
+        self.state = State::Moving;
+    }
+
+    /// Gives the builder slot up while the work is on hold, unless this agent started the
+    /// building, so others can take the agent's place. The slot is taken again on arrival.
+    pub fn pause(&mut self) {
+        if self.state == State::Idle || !self.is_construction || self.started_building {
+            return;
+        }
+        if let Some(building) = self.building.as_mut().filter(|building| building.is_instance_valid()) {
+            building.bind_mut().construction_mut().leave(&self.agent_name);
+        }
+    }
+
+    /// Walks back to the building after an interruption, progress made so far is kept.
+    pub fn resume(&mut self, current_position: Vector2) {
+        if self.state == State::Idle || !self.is_building_valid() {
+            return;
+        }
+        let building = self.building.clone().unwrap();
+        self.start_move_to_build(building, current_position);
     }
 
     pub fn build(&mut self, delta: f64) -> (Result, Option<Vector2>) {
         loop {
             godot_print!("Agent {}: Building state {}", self.agent_name, format!("{:?}", self.state));
             match self.state {
-                State::IdleState => {
+                State::Idle => {
                     return (Result::Success, None);
                 }
-                State::MovingState => {
+                State::Moving => {
+                    if !self.is_building_valid() {
+                        return (self.fail(FailureReason::TargetLost), None);
+                    }
                     let (result, next_position) = self.move_behaviour.move_agent(delta);
                     godot_print!("Agent {}: Moving result {}", self.agent_name, format!("{:?}", result));
-                    if result == Result::Running {
-                        return (result, Some(next_position));
+                    match result {
+                        Result::Running => return (result, Some(next_position)),
+                        Result::Failure(reason) => return (self.fail(reason), None),
+                        Result::Success => {}
                     }
                     godot_print!("Agent {}: Starting build", self.agent_name);
-                    self.state = State::BuildingState;
+                    self.state = State::Building;
                 }
-                State::BuildingState => {
-                    let result = self.process_building(delta);
-                    if result == Result::Running {
-                        return (result, None);
+                State::Building => {
+                    if !self.is_building_valid() {
+                        return (self.fail(FailureReason::TargetLost), None);
+                    }
+                    match self.process_building(delta) {
+                        Result::Running => return (Result::Running, None),
+                        Result::Failure(reason) => return (self.fail(reason), None),
+                        Result::Success => {}
                     }
                     self.building = None;
-                    self.state = State::IdleState;
+                    self.reserved_position = None;
+                    self.started_building = false;
+                    self.deconstruction_started = false;
+                    self.state = State::Idle;
                 }
             }
         }
     }
 
-    fn process_building(&mut self, delta: f64) -> Result {
-        if !self.building.is_some() {
-            return Result::Success;
+    fn is_building_valid(&self) -> bool {
+        self.building.as_ref().is_some_and(|building| building.is_instance_valid())
+    }
+
+    /// Stops working on the current building at any point, see `abandon_building`.
+    pub fn cancel(&mut self) {
+        if self.state == State::Idle {
+            return;
         }
+        godot_print!("Agent {}: Building cancelled", self.agent_name);
+        self.abandon_building();
+    }
 
-        let progress = if self.is_construction {
-            self.building_progress += delta as f32;
-            (self.building_progress / self.config.building_duration).min(1.0)
-        } else {
-            self.building_progress -= delta as f32;
-            (self.building_progress / self.config.building_duration).max(0.0)
-        };
+    fn fail(&mut self, reason: FailureReason) -> Result {
+        godot_print!("Agent {}: Building failed {}", self.agent_name, format!("{:?}", reason));
+        self.abandon_building();
+        Result::Failure(reason)
+    }
 
-        self.building.as_mut().unwrap().bind_mut().build(progress);
+    /// A building this behaviour started is removed and its reserved cell released, so anyone
+    /// helping loses the site too. A building that was partly deconstructed is restored, one whose
+    /// deconstruction never started is left as it is.
+    fn abandon_building(&mut self) {
+        let building = self.building.take().filter(|building| building.is_instance_valid());
+        if let Some(mut building) = building.clone() {
+            building.bind_mut().construction_mut().leave(&self.agent_name);
+        }
+        if let Some(reserved_position) = self.reserved_position.take() {
+            FreeSpaceManager::singleton().bind_mut().remove_occupied_position(reserved_position);
+        }
+        if self.started_building {
+            if let Some(mut building) = building {
+                building.bind_mut().base_mut().queue_free();
+            }
+        } else if let Some(mut building) = building.filter(|_| self.deconstruction_started) {
+            let mut building = building.bind_mut();
+            building.construction_mut().complete();
+            building.build(1.0);
+        }
+        self.started_building = false;
+        self.deconstruction_started = false;
+        self.move_behaviour.stop();
+        self.state = State::Idle;
+    }
 
+    fn process_building(&mut self, delta: f64) -> Result {
+        let Some(building) = self.building.as_mut() else {
+            return Result::Success;
+        };
+        let mut building = building.bind_mut();
+        let work = self.config.build_rate * delta as f32;
+        let site = building.construction_mut();
         if self.is_construction {
-            if self.building_progress >= self.config.building_duration {
-                self.building.as_mut().unwrap().bind_mut().set_completed();
-                return Result::Success;
+            if !site.join(&self.agent_name) {
+                return Result::Failure(FailureReason::TargetLost);
             }
-        } else {
-            if self.building_progress <= 0.0 {
-                return Result::Success;
+            if !site.has_materials() {
+                // Nothing to build with yet, the builder waits on site for the haulers
+                let missing = site.missing_materials().to_vec();
+                let owner = InventoryOwner::Construction(building.base().instance_id().to_i64());
+                let position = building.base().get_position();
+                drop(building);
+                Logistics::singleton().bind_mut().request_materials(owner, position, &missing);
+                return Result::Running;
             }
+            site.add_work(work);
+        } else {
+            site.remove_work(work);
+            self.deconstruction_started = true;
         }
+        let progress = site.progress();
+        let finished = if self.is_construction { site.is_complete() } else { site.is_cleared() };
+        building.build(progress);
 
-        Result::Running
+        if !finished {
+            return Result::Running;
+        }
+        building.construction_mut().leave(&self.agent_name);
+        if self.is_construction {
+            building.set_completed();
+        }
+        Result::Success
     }
 }
+
+/// Whether the terrain at `position` allows buildings of type `T`.
+pub fn allows_structure<T: IBuilding>(position: Vector2) -> bool {
+    let free_space_manager = FreeSpaceManager::singleton();
+    let free_space_manager = free_space_manager.bind();
+    free_space_manager.terrain().allows(free_space_manager.cell_of(position), T::structure())
+}
@@ -1,9 +1,38 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use godot::prelude::*;
 
+use super::free_space_manager::FreeSpaceManager;
+use super::introspection::StateStack;
+use super::steering::{avoidance_velocity, queue_factor, Mover, SteeringConfig};
+use crate::world::world_clock::WorldClock;
+
+/// Ids of move behaviours in the crowd, handed out in the order they are made
+static NEXT_MOVER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Getting at least this much closer to the target counts as progress
+const PROGRESS_DISTANCE: f32 = 1.0;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum FailureReason {
+    /// Moving was requested before a target was set
+    NoTarget,
+    /// The building or field the behaviour worked on was freed
+    TargetLost,
+    /// The agent has no home to return to
+    NoHome,
+    /// The agent stopped getting any closer to the target
+    Stuck,
+    /// Buildings or terrain block every way to the target
+    NoPath,
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Result {
     Success,
     Running,
+    Failure(FailureReason),
 }
 
 
@@ -12,6 +41,13 @@ pub struct MoveBehaviourConfig {
     pub speed: f32,
     pub max_step_height: f32,
     pub step_period: f32,
+    /// Walk around occupied cells of the `FreeSpaceManager` instead of straight at the target
+    pub pathfinding: bool,
+    /// Keep clear of other moving agents, None walks through them
+    pub steering: Option<SteeringConfig>,
+    /// Seconds without getting closer to the next waypoint before the move fails as stuck, 0 waits
+    /// forever
+    pub stuck_timeout: f32,
 }
 
 #[derive(Clone)]
@@ -19,37 +55,231 @@ pub struct MoveBehaviour {
     moving_time: f32,
     move_reference_position: Option<Vector2>,
     target: Option<Vector2>,
+    /// Points still to pass on the way, the last one is the target
+    waypoints: VecDeque<Vector2>,
+    /// Waypoints come one at a time from the flow field of the target
+    following_flow: bool,
+    /// Set when the last path search found no way to the target
+    no_path: bool,
+    mover_id: u64,
+    velocity: Vector2,
+    /// Closest the agent got to the next waypoint since passing the last one
+    closest_to_waypoint: f32,
+    time_without_progress: f32,
     config: MoveBehaviourConfig,
 }
 
 impl MoveBehaviour {
     pub fn new(config: MoveBehaviourConfig) -> Self {
-        Self { move_reference_position: None, target: None, moving_time: 0.0, config }
+        Self {
+            move_reference_position: None,
+            target: None,
+            waypoints: VecDeque::new(),
+            following_flow: false,
+            no_path: false,
+            mover_id: NEXT_MOVER_ID.fetch_add(1, Ordering::Relaxed),
+            velocity: Vector2::ZERO,
+            closest_to_waypoint: f32::INFINITY,
+            time_without_progress: 0.0,
+            moving_time: 0.0,
+            config,
+        }
     }
 
     pub fn move_agent(&mut self, delta: f64) -> (Result, Vector2) {
-        let rotation = self.move_reference_position.unwrap().angle_to_point(self.target.unwrap());
-        let delta_distance = self.config.speed * delta as f32;
-        let distance_to_target = self.move_reference_position.unwrap().distance_to(self.target.unwrap());
-
-        if distance_to_target < delta_distance {
-            return (Result::Success, self.target.unwrap());
+        let (Some(reference_position), Some(target)) = (self.move_reference_position, self.target) else {
+            return (Result::Failure(FailureReason::NoTarget), self.move_reference_position.unwrap_or_default());
         };
+        if self.no_path {
+            return (Result::Failure(FailureReason::NoPath), reference_position);
+        }
 
-        self.move_reference_position =
-            Some(self.move_reference_position.unwrap() + Vector2::RIGHT.rotated(rotation) * delta_distance);
-        let next_position = self.move_reference_position.unwrap() + Vector2::UP * self.step_height();
+        let me = Mover { id: self.mover_id, position: reference_position, velocity: self.velocity, target };
+        let neighbours = self.neighbours(&me);
+
+        // Walks the whole step, passing as many waypoints as it reaches
+        let mut position = reference_position;
+        let mut passed_waypoint = false;
+        let mut delta_distance = self.config.speed * delta as f32;
+        if let Some(steering) = self.config.steering.as_ref() {
+            delta_distance *= queue_factor(&me, &neighbours, steering);
+        }
+        loop {
+            if self.waypoints.is_empty() && self.following_flow {
+                let Some(next) = FreeSpaceManager::singleton().bind_mut().flow_step(position, target) else {
+                    self.no_path = true;
+                    self.move_reference_position = Some(position);
+                    return (Result::Failure(FailureReason::NoPath), position);
+                };
+                self.following_flow = next != target;
+                self.waypoints.push_back(next);
+            }
+            let Some(&waypoint) = self.waypoints.front() else {
+                break;
+            };
+            let distance_to_waypoint = position.distance_to(waypoint);
+            if distance_to_waypoint > delta_distance {
+                position += Vector2::RIGHT.rotated(position.angle_to_point(waypoint)) * delta_distance;
+                break;
+            }
+            position = waypoint;
+            delta_distance -= distance_to_waypoint;
+            self.waypoints.pop_front();
+            passed_waypoint = true;
+        }
+        if self.waypoints.is_empty() {
+            self.velocity = Vector2::ZERO;
+            return (Result::Success, target);
+        }
+        if let Some(steering) = self.config.steering.as_ref() {
+            let offset = avoidance_velocity(&me, &neighbours, self.config.speed, steering) * delta as f32;
+            // Dodging never pushes the agent into water or a building
+            position = FreeSpaceManager::singleton().bind().steer(position, offset);
+            self.velocity = (position - reference_position) / delta as f32;
+            self.report(Mover { position, velocity: self.velocity, ..me });
+        }
+
+        self.move_reference_position = Some(position);
+        if self.is_stuck(position, passed_waypoint, delta as f32) {
+            self.time_without_progress = 0.0;
+            return (Result::Failure(FailureReason::Stuck), position);
+        }
+        let next_position = position + Vector2::UP * self.step_height();
         self.moving_time += delta as f32;
         (Result::Running, next_position)
     }
 
+    /// Whether the agent went `stuck_timeout` seconds without passing a waypoint or getting any
+    /// closer to the next one. A detour around an obstacle may lead away from the target for a while.
+    fn is_stuck(&mut self, position: Vector2, passed_waypoint: bool, delta: f32) -> bool {
+        let distance = self.waypoints.front().map_or(0.0, |waypoint| position.distance_to(*waypoint));
+        if passed_waypoint || distance < self.closest_to_waypoint - PROGRESS_DISTANCE {
+            self.closest_to_waypoint = distance;
+            self.time_without_progress = 0.0;
+            return false;
+        }
+        self.time_without_progress += delta;
+        self.config.stuck_timeout > 0.0 && self.time_without_progress >= self.config.stuck_timeout
+    }
+
+    /// Moving agents around `me` as of the last physics frame, none without steering.
+    fn neighbours(&self, me: &Mover) -> Vec<Mover> {
+        let Some(steering) = self.config.steering.as_ref() else {
+            return Vec::new();
+        };
+        // Far enough to see someone walking into the agent within the horizon
+        let radius = steering.separation_radius + 2.0 * self.config.speed * steering.avoidance_horizon;
+        let frame = WorldClock::tick();
+        FreeSpaceManager::singleton().bind_mut().crowd_mut().neighbours(frame, me.id, me.position, radius)
+    }
+
+    fn report(&self, mover: Mover) {
+        let frame = WorldClock::tick();
+        FreeSpaceManager::singleton().bind_mut().crowd_mut().report(frame, mover);
+    }
+
     fn step_height(&self) -> f32 {
         self.config.max_step_height * (self.moving_time / self.config.step_period).sin().abs()
     }
 
+    /// Heads for `target`, around occupied cells when pathfinding is on. Popular targets are
+    /// reached over their shared flow field, others over a path of their own. If there is no way
+    /// through, the next `move_agent` fails with `NoPath`.
     pub fn start_moving(&mut self, current_position: Vector2, target: Vector2) {
+        let waypoints = if self.config.pathfinding {
+            let mut free_space_manager = FreeSpaceManager::singleton();
+            if free_space_manager.bind_mut().prefers_flow_field(current_position, target) {
+                self.start_following_flow(current_position, target);
+                return;
+            }
+            let waypoints = free_space_manager.bind().find_path(current_position, target);
+            waypoints
+        } else {
+            Some(vec![target])
+        };
+        match waypoints {
+            Some(waypoints) => self.start_moving_along(current_position, waypoints),
+            None => {
+                self.start_moving_along(current_position, vec![target]);
+                self.no_path = true;
+            }
+        }
+    }
+
+    /// Walks through `waypoints` in order, the last one is the target.
+    pub fn start_moving_along(&mut self, current_position: Vector2, waypoints: Vec<Vector2>) {
         self.moving_time = 0.0;
         self.move_reference_position = Some(current_position);
+        self.target = waypoints.last().copied();
+        self.waypoints = waypoints.into();
+        self.following_flow = false;
+        self.no_path = false;
+        self.closest_to_waypoint = f32::INFINITY;
+        self.time_without_progress = 0.0;
+    }
+
+    /// Walks the flow field of `target`, asking it for the next cell whenever one is reached.
+    pub fn start_following_flow(&mut self, current_position: Vector2, target: Vector2) {
+        self.start_moving_along(current_position, Vec::new());
         self.target = Some(target);
+        self.following_flow = true;
+    }
+
+    /// Changes the target of a move in progress, e.g. when following something that moves. While
+    /// the target stays in the same cell only the end of the way moves along with it. Once it moves
+    /// to another cell, the way is planned again from where the agent is, see `start_moving`.
+    pub fn retarget(&mut self, target: Vector2) {
+        let old_target = self.target.replace(target);
+        let same_cell = !self.config.pathfinding
+            || old_target.is_some_and(|old_target| {
+                let free_space_manager = FreeSpaceManager::singleton();
+                let free_space_manager = free_space_manager.bind();
+                free_space_manager.cell_of(old_target) == free_space_manager.cell_of(target)
+            });
+        if !same_cell {
+            if let Some(position) = self.move_reference_position {
+                let moving_time = self.moving_time;
+                self.start_moving(position, target);
+                self.moving_time = moving_time;
+                return;
+            }
+        }
+        // The flow field already leads into the target's cell and ends at the current target
+        if self.following_flow {
+            return;
+        }
+        match self.waypoints.back_mut() {
+            Some(last) => *last = target,
+            None => self.waypoints.push_back(target),
+        }
+    }
+
+    /// Continues towards the current target from wherever the agent ended up.
+    pub fn resume(&mut self, current_position: Vector2) {
+        if let Some(target) = self.target {
+            self.start_moving(current_position, target);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.move_reference_position = None;
+        self.target = None;
+        self.waypoints.clear();
+        self.following_flow = false;
+        self.no_path = false;
+    }
+
+    pub fn target(&self) -> Option<Vector2> {
+        self.target
+    }
+
+    pub fn state_stack(&self) -> StateStack {
+        match self.target {
+            Some(target) if self.following_flow => {
+                StateStack::of("Move", format_args!("FollowingFlow({})", target)).with_move_target(Some(target))
+            }
+            Some(target) => StateStack::of("Move", format_args!("Moving({})", target)).with_move_target(Some(target)),
+            None => StateStack::of("Move", format_args!("Stopped")),
+        }
     }
 }
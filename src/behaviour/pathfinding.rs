@@ -0,0 +1,113 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Cell of the `FreeSpaceManager` grid
+pub type Cell = (i32, i32);
+
+/// Cells the search may expand before it gives up, the grid has no edges so a walled in goal
+/// would otherwise be searched for forever
+pub const MAX_EXPANDED_CELLS: usize = 5000;
+
+pub(crate) const NEIGHBOURS: [Cell; 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Passability and move costs of the cells agents walk over.
+pub trait CostGrid {
+    /// Cost of stepping into `cell`, at least 1.0, or None when the cell can't be entered.
+    fn move_cost(&self, cell: Cell) -> Option<f32>;
+}
+
+/// Cell waiting in a search queue, ordered by its cost estimate
+#[derive(PartialEq)]
+pub(crate) struct OpenCell {
+    pub(crate) estimate: f32,
+    pub(crate) cell: Cell,
+}
+
+impl Eq for OpenCell {}
+
+impl Ord for OpenCell {
+    // Reversed, the heap pops the lowest estimate first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then_with(|| self.cell.cmp(&other.cell))
+    }
+}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Octile distance, never more than the cheapest walk since every cell costs at least 1.0.
+fn heuristic(from: Cell, to: Cell) -> f32 {
+    let dx = (from.0 - to.0).abs() as f32;
+    let dy = (from.1 - to.1).abs() as f32;
+    dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy)
+}
+
+/// Cheapest 8-connected walk from `start` to `goal`, without the start cell and ending with the
+/// goal. The start and goal cells can always be entered, agents leave their home and walk up to
+/// buildings that hold their cell. Diagonal steps cost `SQRT_2` times the cell cost and don't cut
+/// corners of cells that can't be entered. None when the goal can't be reached within
+/// `MAX_EXPANDED_CELLS`.
+pub fn find_path(grid: &impl CostGrid, start: Cell, goal: Cell) -> Option<Vec<Cell>> {
+    let cost = |cell: Cell| {
+        if cell == start || cell == goal {
+            Some(grid.move_cost(cell).unwrap_or(1.0))
+        } else {
+            grid.move_cost(cell)
+        }
+    };
+
+    let mut open = BinaryHeap::new();
+    let mut walked: HashMap<Cell, f32> = HashMap::new();
+    let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+    walked.insert(start, 0.0);
+    open.push(OpenCell { estimate: heuristic(start, goal), cell: start });
+
+    let mut expanded = 0;
+    while let Some(OpenCell { estimate, cell }) = open.pop() {
+        if cell == goal {
+            let mut path = vec![goal];
+            let mut current = goal;
+            while let Some(&previous) = came_from.get(&current) {
+                if previous == start {
+                    break;
+                }
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        let walked_here = walked[&cell];
+        // Stale entry of a cell reached more cheaply since
+        if estimate > walked_here + heuristic(cell, goal) {
+            continue;
+        }
+        expanded += 1;
+        if expanded > MAX_EXPANDED_CELLS {
+            return None;
+        }
+
+        for (dx, dy) in NEIGHBOURS {
+            let next = (cell.0 + dx, cell.1 + dy);
+            let Some(step_cost) = cost(next) else {
+                continue;
+            };
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal && (cost((cell.0 + dx, cell.1)).is_none() || cost((cell.0, cell.1 + dy)).is_none()) {
+                continue;
+            }
+            let step_cost = if diagonal { step_cost * std::f32::consts::SQRT_2 } else { step_cost };
+            let walked_next = walked_here + step_cost;
+            if walked.get(&next).is_some_and(|&known| known <= walked_next) {
+                continue;
+            }
+            walked.insert(next, walked_next);
+            came_from.insert(next, cell);
+            open.push(OpenCell { estimate: walked_next + heuristic(next, goal), cell: next });
+        }
+    }
+    None
+}
@@ -0,0 +1,32 @@
+use godot::classes::{Engine, SceneTree};
+use godot::prelude::*;
+
+use crate::agent::wolf::{Wolf, WOLF_GROUP};
+
+/// Nodes of a scene group in the running scene. Nodes of other classes are skipped.
+pub fn nodes_in_group<T: GodotClass + Inherits<Node>>(group: &str) -> Vec<Gd<T>> {
+    let Some(main_loop) = Engine::singleton().get_main_loop() else {
+        return Vec::new();
+    };
+    let mut scene_tree = main_loop.cast::<SceneTree>();
+    scene_tree.get_nodes_in_group(group).iter_shared().filter_map(|node| node.try_cast::<T>().ok()).collect()
+}
+
+/// Index of the position closest to `origin` that is at most `radius` away.
+pub fn nearest_within(origin: Vector2, radius: f32, positions: &[Vector2]) -> Option<usize> {
+    positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| (index, origin.distance_to(*position)))
+        .filter(|(_, distance)| *distance <= radius)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+/// Closest wolf the agent at `position` can see. Wolves already running off are ignored.
+pub fn nearest_threat(position: Vector2, sight_radius: f32) -> Option<Gd<Wolf>> {
+    let wolves: Vec<Gd<Wolf>> =
+        nodes_in_group::<Wolf>(WOLF_GROUP).into_iter().filter(|wolf| !wolf.bind().is_driven_off()).collect();
+    let positions: Vec<Vector2> = wolves.iter().map(|wolf| wolf.get_position()).collect();
+    nearest_within(position, sight_radius, &positions).map(|index| wolves[index].clone())
+}
@@ -0,0 +1,26 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+static WORLD_SEED: AtomicU64 = AtomicU64::new(0);
+
+/// Seed every random choice in the simulation derives from, so a run can be replayed.
+pub fn set_world_seed(seed: u64) {
+    WORLD_SEED.store(seed, Ordering::Relaxed);
+}
+
+pub fn world_seed() -> u64 {
+    WORLD_SEED.load(Ordering::Relaxed)
+}
+
+/// Random numbers for one user of randomness, e.g. an agent by its name. The same world seed and
+/// name always give the same numbers, no matter in which order the agents draw theirs.
+pub fn seeded_rng(name: &str) -> StdRng {
+    StdRng::seed_from_u64(world_seed() ^ stable_hash(name))
+}
+
+/// FNV-1a, unlike the std hasher it is the same on every run and platform.
+fn stable_hash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
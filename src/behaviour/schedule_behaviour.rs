@@ -0,0 +1,106 @@
+use godot::prelude::*;
+
+use super::agent_behaviour::{AgentBehaviourResult, IAgentBehaviour};
+use super::introspection::StateStack;
+use super::move_behaviour::{MoveBehaviour, Result};
+use super::utility::JobScore;
+use crate::world::clock::ScheduledActivity;
+use crate::world::world_clock::WorldClock;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum ScheduleState {
+    Working,
+    GoingHome,
+    Resting,
+}
+
+/// Layer on top of any agent behaviour that follows the daily schedule: at dusk the current
+/// task is paused and the agent walks home, at dawn the task is resumed where it was left.
+/// Agents without a home keep working through the night until they have one.
+pub struct ScheduledBehaviour {
+    behaviour: Box<dyn IAgentBehaviour>,
+    move_behaviour: MoveBehaviour,
+    state: ScheduleState,
+    agent_name: String,
+}
+
+impl ScheduledBehaviour {
+    pub fn new(behaviour: Box<dyn IAgentBehaviour>, move_behaviour: MoveBehaviour) -> Self {
+        Self { behaviour, move_behaviour, state: ScheduleState::Working, agent_name: String::new() }
+    }
+}
+
+impl IAgentBehaviour for ScheduledBehaviour {
+    fn start(&mut self, agent_name: String, parent_node: Option<Gd<Node>>) {
+        self.agent_name = agent_name.clone();
+        self.state = ScheduleState::Working;
+        self.behaviour.start(agent_name, parent_node);
+    }
+
+    fn tick(&mut self, delta: f64, agent_position: Vector2) -> AgentBehaviourResult {
+        let world_clock = WorldClock::singleton();
+        let hour = world_clock.bind().now().hour;
+        let activity = world_clock.bind().schedule().activity(hour);
+
+        match (activity, self.state) {
+            (ScheduledActivity::Work, ScheduleState::Working) => self.behaviour.tick(delta, agent_position),
+            (ScheduledActivity::Work, _) => {
+                godot_print!("Agent {} Dawn, back to work", self.agent_name);
+                self.move_behaviour.stop();
+                self.state = ScheduleState::Working;
+                self.behaviour.resume(agent_position);
+                self.behaviour.tick(delta, agent_position)
+            }
+            (ScheduledActivity::Rest, ScheduleState::Working) => {
+                let Some(home_position) = self.behaviour.home_position() else {
+                    return self.behaviour.tick(delta, agent_position);
+                };
+                godot_print!("Agent {} Dusk, going home", self.agent_name);
+                self.behaviour.pause();
+                self.move_behaviour.start_moving(agent_position, home_position);
+                self.state = ScheduleState::GoingHome;
+                AgentBehaviourResult { next_position: None }
+            }
+            (ScheduledActivity::Rest, ScheduleState::GoingHome) => {
+                let (result, next_position) = self.move_behaviour.move_agent(delta);
+                if result != Result::Running {
+                    self.state = ScheduleState::Resting;
+                }
+                AgentBehaviourResult { next_position: Some(next_position) }
+            }
+            (ScheduledActivity::Rest, ScheduleState::Resting) => AgentBehaviourResult { next_position: None },
+        }
+    }
+
+    fn cancel(&mut self) {
+        self.move_behaviour.stop();
+        self.state = ScheduleState::Working;
+        self.behaviour.cancel();
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        match self.state {
+            ScheduleState::Working => self.behaviour.resume(agent_position),
+            ScheduleState::GoingHome => self.move_behaviour.resume(agent_position),
+            ScheduleState::Resting => {}
+        }
+    }
+
+    fn home_position(&self) -> Option<Vector2> {
+        self.behaviour.home_position()
+    }
+
+    fn job_scores(&self) -> Vec<JobScore> {
+        self.behaviour.job_scores()
+    }
+
+    fn state_stack(&self) -> StateStack {
+        match self.state {
+            ScheduleState::Working => self.behaviour.state_stack(),
+            ScheduleState::GoingHome => {
+                StateStack::of("Schedule", self.state).then(self.move_behaviour.state_stack())
+            }
+            ScheduleState::Resting => StateStack::of("Schedule", self.state),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::mem;
+
+use godot::builtin::Vector2;
+
+use super::pathfinding::{Cell, CostGrid};
+
+/// Closer than this two positions count as the same spot
+const COINCIDENT_DISTANCE: f32 = 0.01;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct SteeringConfig {
+    /// Agents closer than this push each other apart
+    pub separation_radius: f32,
+    /// Seconds ahead an agent looks for someone walking into it
+    pub avoidance_horizon: f32,
+    /// Steering never moves an agent faster than this share of its speed
+    pub max_steering_share: f32,
+    /// Agents this close to their target wait for the ones ahead of them going to the same place
+    pub queue_radius: f32,
+    /// Gap kept to the agent ahead in a queue
+    pub queue_spacing: f32,
+}
+
+/// An agent on the move as the others see it.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Mover {
+    pub id: u64,
+    pub position: Vector2,
+    /// Distance per second walked in the last step
+    pub velocity: Vector2,
+    pub target: Vector2,
+}
+
+/// Direction two agents on the same spot push `me` apart, opposite for the other one and the
+/// same on every run.
+fn tie_break_direction(me: u64, other: u64) -> Vector2 {
+    let (low, high) = (me.min(other), me.max(other));
+    let angle = (low.wrapping_mul(31).wrapping_add(high) % 360) as f32;
+    let direction = Vector2::RIGHT.rotated(angle.to_radians());
+    if me == low {
+        direction
+    } else {
+        -direction
+    }
+}
+
+/// Where `offset` takes an agent at `position` without entering a cell of `grid` it can't walk
+/// on: the whole offset, only its horizontal or only its vertical part, or nowhere. Staying on the
+/// cell it is on is always allowed, agents start out on their own home.
+pub fn walkable_offset(
+    grid: &impl CostGrid,
+    cell_of: impl Fn(Vector2) -> Cell,
+    position: Vector2,
+    offset: Vector2,
+) -> Vector2 {
+    let cell = cell_of(position);
+    let walkable = |to: Vector2| {
+        let to_cell = cell_of(to);
+        to_cell == cell || grid.move_cost(to_cell).is_some()
+    };
+    [offset, Vector2::new(offset.x, 0.0), Vector2::new(0.0, offset.y)]
+        .into_iter()
+        .map(|offset| position + offset)
+        .find(|&to| walkable(to))
+        .unwrap_or(position)
+}
+
+/// Share of its speed `me` walks at, below 1.0 while it queues behind an agent that is closer to
+/// the same target. Agents at the same distance let the lower id go first, so there is always
+/// someone at the front who walks on.
+pub fn queue_factor(me: &Mover, neighbours: &[Mover], config: &SteeringConfig) -> f32 {
+    let distance = me.position.distance_to(me.target);
+    if distance > config.queue_radius {
+        return 1.0;
+    }
+    let half_spacing = config.queue_spacing / 2.0;
+    neighbours
+        .iter()
+        .filter(|other| other.id != me.id && other.target.distance_to(me.target) < COINCIDENT_DISTANCE)
+        .filter(|other| {
+            let other_distance = other.position.distance_to(other.target);
+            other_distance < distance || (other_distance == distance && other.id < me.id)
+        })
+        .map(|other| ((me.position.distance_to(other.position) - half_spacing) / half_spacing).clamp(0.0, 1.0))
+        .fold(1.0, f32::min)
+}
+
+/// Velocity added to `me` walking at `speed` to keep away from `neighbours`: a push away from
+/// everyone too close, and a step to the right when someone is about to walk into it, so two
+/// agents meeting head on both give way to their right. `neighbours` has to be in a fixed order
+/// for the result to be the same on every run.
+pub fn avoidance_velocity(me: &Mover, neighbours: &[Mover], speed: f32, config: &SteeringConfig) -> Vector2 {
+    let radius = config.separation_radius;
+    let mut steering = Vector2::ZERO;
+    for other in neighbours.iter().filter(|other| other.id != me.id) {
+        let offset = me.position - other.position;
+        let distance = offset.length();
+        if distance < radius {
+            let away =
+                if distance > COINCIDENT_DISTANCE { offset / distance } else { tie_break_direction(me.id, other.id) };
+            steering += away * (1.0 - distance / radius) * speed;
+        }
+
+        let closing_velocity = me.velocity - other.velocity;
+        let closing_speed_squared = closing_velocity.length_squared();
+        if closing_speed_squared < COINCIDENT_DISTANCE || me.velocity.length_squared() < COINCIDENT_DISTANCE {
+            continue;
+        }
+        let time_to_closest = -offset.dot(closing_velocity) / closing_speed_squared;
+        if time_to_closest <= 0.0 || time_to_closest >= config.avoidance_horizon {
+            continue;
+        }
+        if (offset + closing_velocity * time_to_closest).length() < radius {
+            // Screen y points down, so this is the right hand side of the heading
+            let right = Vector2::new(-me.velocity.y, me.velocity.x).normalized();
+            steering += right * (1.0 - time_to_closest / config.avoidance_horizon) * speed;
+        }
+    }
+    steering.limit_length(Some(speed * config.max_steering_share))
+}
+
+/// Where the moving agents are, bucketed by cell. Movers report during a physics frame and read
+/// what everyone reported in the frame before, so the order agents are ticked in doesn't matter.
+pub struct Crowd {
+    cell_size: f32,
+    frame: u64,
+    previous: HashMap<Cell, Vec<Mover>>,
+    current: HashMap<Cell, Vec<Mover>>,
+}
+
+impl Crowd {
+    pub fn new(cell_size: f32) -> Self {
+        Self { cell_size, frame: 0, previous: HashMap::new(), current: HashMap::new() }
+    }
+
+    fn cell_of(&self, position: Vector2) -> Cell {
+        ((position.x / self.cell_size).floor() as i32, (position.y / self.cell_size).floor() as i32)
+    }
+
+    fn advance_to(&mut self, frame: u64) {
+        if frame == self.frame {
+            return;
+        }
+        if frame == self.frame + 1 {
+            self.previous = mem::take(&mut self.current);
+        } else {
+            // Nobody moved for a while, what was reported back then is stale
+            self.previous.clear();
+            self.current.clear();
+        }
+        self.frame = frame;
+    }
+
+    pub fn report(&mut self, frame: u64, mover: Mover) {
+        self.advance_to(frame);
+        let cell = self.cell_of(mover.position);
+        self.current.entry(cell).or_default().push(mover);
+    }
+
+    /// Movers of the last frame within `radius` of `position`, except `id`, ordered by id.
+    pub fn neighbours(&mut self, frame: u64, id: u64, position: Vector2, radius: f32) -> Vec<Mover> {
+        self.advance_to(frame);
+        let (cell_x, cell_y) = self.cell_of(position);
+        let reach = (radius / self.cell_size).ceil() as i32;
+        let mut neighbours: Vec<Mover> = (cell_x - reach..=cell_x + reach)
+            .flat_map(|x| (cell_y - reach..=cell_y + reach).map(move |y| (x, y)))
+            .filter_map(|cell| self.previous.get(&cell))
+            .flatten()
+            .filter(|mover| mover.id != id && mover.position.distance_to(position) <= radius)
+            .copied()
+            .collect();
+        neighbours.sort_by_key(|mover| mover.id);
+        neighbours
+    }
+}
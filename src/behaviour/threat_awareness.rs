@@ -0,0 +1,169 @@
+use godot::prelude::*;
+
+use super::agent_behaviour::{AgentBehaviourResult, IAgentBehaviour};
+use super::introspection::StateStack;
+use super::move_behaviour::{MoveBehaviour, Result};
+use super::utility::JobScore;
+use crate::agent::wolf::Wolf;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct ThreatAwarenessConfig {
+    /// A threat closer than this interrupts the agent
+    pub flee_distance: f32,
+    /// The agent goes back to work once every threat is further away than this
+    pub safe_distance: f32,
+}
+
+/// Decides whether an agent is in danger. Uses two distances so agents do not flicker
+/// between fleeing and working at the edge of the flee distance.
+pub struct ThreatAwareness {
+    config: ThreatAwarenessConfig,
+    in_danger: bool,
+}
+
+impl ThreatAwareness {
+    pub fn new(config: ThreatAwarenessConfig) -> Self {
+        Self { config, in_danger: false }
+    }
+
+    pub fn is_in_danger(&self) -> bool {
+        self.in_danger
+    }
+
+    pub fn update(&mut self, agent_position: Vector2, threat_positions: &[Vector2]) -> bool {
+        let nearest_distance = nearest_threat(agent_position, threat_positions)
+            .map_or(f32::INFINITY, |threat_position| agent_position.distance_to(threat_position));
+        let limit = if self.in_danger { self.config.safe_distance } else { self.config.flee_distance };
+        self.in_danger = nearest_distance < limit;
+        self.in_danger
+    }
+
+    /// Where to run: home unless a threat stands on the way there, otherwise straight away from
+    /// the nearest threat.
+    pub fn flee_target(
+        &self,
+        agent_position: Vector2,
+        home_position: Option<Vector2>,
+        threat_positions: &[Vector2],
+    ) -> Vector2 {
+        let safe_home = home_position.filter(|home_position| {
+            !threat_positions.iter().any(|threat_position| {
+                blocks_way(*threat_position, agent_position, *home_position, self.config.flee_distance)
+            })
+        });
+        if let Some(home_position) = safe_home {
+            return home_position;
+        }
+        match nearest_threat(agent_position, threat_positions) {
+            Some(threat_position) => {
+                let away = (agent_position - threat_position).try_normalized().unwrap_or(Vector2::RIGHT);
+                threat_position + away * self.config.safe_distance * 1.1
+            }
+            None => agent_position,
+        }
+    }
+}
+
+/// Whether `threat` is ahead on the way from `from` to `to` and closer than `clearance` to it.
+/// Threats beside or behind the agent don't block the way, it runs away from them anyway.
+fn blocks_way(threat: Vector2, from: Vector2, to: Vector2, clearance: f32) -> bool {
+    let way = to - from;
+    let length_squared = way.length_squared();
+    if length_squared == 0.0 {
+        return false;
+    }
+    let along = (threat - from).dot(way) / length_squared;
+    along > 0.0 && threat.distance_to(from + way * along.min(1.0)) < clearance
+}
+
+fn nearest_threat(agent_position: Vector2, threat_positions: &[Vector2]) -> Option<Vector2> {
+    threat_positions
+        .iter()
+        .copied()
+        .min_by(|a, b| agent_position.distance_to(*a).total_cmp(&agent_position.distance_to(*b)))
+}
+
+/// Layer on top of any agent behaviour: when a wolf comes close the current task is put on hold
+/// and the agent flees, once it is safe again the task is resumed where it was left.
+pub struct ThreatAwareBehaviour {
+    behaviour: Box<dyn IAgentBehaviour>,
+    awareness: ThreatAwareness,
+    flee_behaviour: MoveBehaviour,
+    agent_name: String,
+}
+
+impl ThreatAwareBehaviour {
+    pub fn new(
+        behaviour: Box<dyn IAgentBehaviour>,
+        flee_behaviour: MoveBehaviour,
+        config: ThreatAwarenessConfig,
+    ) -> Self {
+        Self { behaviour, awareness: ThreatAwareness::new(config), flee_behaviour, agent_name: String::new() }
+    }
+}
+
+impl IAgentBehaviour for ThreatAwareBehaviour {
+    fn start(&mut self, agent_name: String, parent_node: Option<Gd<Node>>) {
+        self.agent_name = agent_name.clone();
+        self.behaviour.start(agent_name, parent_node);
+    }
+
+    fn tick(&mut self, delta: f64, agent_position: Vector2) -> AgentBehaviourResult {
+        let threat_positions = Wolf::positions();
+        let was_in_danger = self.awareness.is_in_danger();
+        let in_danger = self.awareness.update(agent_position, &threat_positions);
+
+        if !in_danger {
+            if was_in_danger {
+                godot_print!("Agent {} Safe again, resuming work", self.agent_name);
+                self.behaviour.resume(agent_position);
+            }
+            return self.behaviour.tick(delta, agent_position);
+        }
+
+        // The threats keep moving, so the way home may close or open up while fleeing
+        let home_position = self.behaviour.home_position();
+        let flee_target = self.awareness.flee_target(agent_position, home_position, &threat_positions);
+        if was_in_danger {
+            self.flee_behaviour.retarget(flee_target);
+        } else {
+            godot_print!("Agent {} Threat nearby, fleeing", self.agent_name);
+            self.flee_behaviour.start_moving(agent_position, flee_target);
+        }
+        let (result, next_position) = self.flee_behaviour.move_agent(delta);
+        match result {
+            Result::Running => AgentBehaviourResult { next_position: Some(next_position) },
+            // Stay hidden at the flee target until the threat is gone
+            _ => AgentBehaviourResult { next_position: None },
+        }
+    }
+
+    fn cancel(&mut self) {
+        self.behaviour.cancel();
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        if !self.awareness.is_in_danger() {
+            self.behaviour.resume(agent_position);
+        }
+    }
+
+    fn pause(&mut self) {
+        self.behaviour.pause();
+    }
+
+    fn home_position(&self) -> Option<Vector2> {
+        self.behaviour.home_position()
+    }
+
+    fn job_scores(&self) -> Vec<JobScore> {
+        self.behaviour.job_scores()
+    }
+
+    fn state_stack(&self) -> StateStack {
+        if !self.awareness.is_in_danger() {
+            return self.behaviour.state_stack();
+        }
+        StateStack::of("ThreatAware", format_args!("Fleeing")).then(self.flee_behaviour.state_stack())
+    }
+}
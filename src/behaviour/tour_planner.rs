@@ -0,0 +1,116 @@
+use godot::builtin::Vector2;
+
+// Held-Karp is exponential in the number of stops, above this we fall back to nearest neighbour
+const EXACT_STOP_LIMIT: usize = 10;
+
+/// Returns indices into `stops` in visiting order. The tour starts at `start`, visits
+/// `max_stops` stops (or all of them if there are fewer) and ends at `end` if given.
+pub fn plan_shortest_tour(start: Vector2, stops: &[Vector2], end: Option<Vector2>, max_stops: usize) -> Vec<usize> {
+    let stop_count = max_stops.min(stops.len());
+    if stop_count == 0 {
+        return Vec::new();
+    }
+    if stops.len() <= EXACT_STOP_LIMIT {
+        plan_exact_tour(start, stops, end, stop_count)
+    } else {
+        plan_nearest_neighbour_tour(start, stops, end, stop_count)
+    }
+}
+
+pub fn tour_length(start: Vector2, stops: &[Vector2], end: Option<Vector2>, order: &[usize]) -> f32 {
+    let mut length = 0.0;
+    let mut position = start;
+    for &index in order {
+        length += position.distance_to(stops[index]);
+        position = stops[index];
+    }
+    if let Some(end) = end {
+        length += position.distance_to(end);
+    }
+    length
+}
+
+fn plan_exact_tour(start: Vector2, stops: &[Vector2], end: Option<Vector2>, stop_count: usize) -> Vec<usize> {
+    let n = stops.len();
+    let mask_count = 1usize << n;
+    let mut cost = vec![vec![f32::INFINITY; n]; mask_count];
+    let mut parent = vec![vec![usize::MAX; n]; mask_count];
+
+    for (index, stop) in stops.iter().enumerate() {
+        cost[1 << index][index] = start.distance_to(*stop);
+    }
+
+    for mask in 1..mask_count {
+        if mask.count_ones() as usize >= stop_count {
+            continue;
+        }
+        for last in 0..n {
+            if mask & (1 << last) == 0 || cost[mask][last].is_infinite() {
+                continue;
+            }
+            for next in 0..n {
+                if mask & (1 << next) != 0 {
+                    continue;
+                }
+                let next_mask = mask | (1 << next);
+                let next_cost = cost[mask][last] + stops[last].distance_to(stops[next]);
+                if next_cost < cost[next_mask][next] {
+                    cost[next_mask][next] = next_cost;
+                    parent[next_mask][next] = last;
+                }
+            }
+        }
+    }
+
+    let mut best: Option<(f32, usize, usize)> = None;
+    for mask in (1..mask_count).filter(|mask| mask.count_ones() as usize == stop_count) {
+        for last in 0..n {
+            if cost[mask][last].is_infinite() {
+                continue;
+            }
+            let total = cost[mask][last] + end.map_or(0.0, |end| stops[last].distance_to(end));
+            if best.is_none_or(|(best_total, _, _)| total < best_total) {
+                best = Some((total, mask, last));
+            }
+        }
+    }
+
+    let (_, mut mask, mut last) = best.unwrap();
+    let mut order = Vec::with_capacity(stop_count);
+    while last != usize::MAX {
+        order.push(last);
+        let previous = parent[mask][last];
+        mask &= !(1 << last);
+        last = previous;
+    }
+    order.reverse();
+    order
+}
+
+/// Always walks to the closest unvisited stop, except that the last stop is picked with the leg to
+/// `end` counted in.
+fn plan_nearest_neighbour_tour(
+    start: Vector2,
+    stops: &[Vector2],
+    end: Option<Vector2>,
+    stop_count: usize,
+) -> Vec<usize> {
+    let mut visited = vec![false; stops.len()];
+    let mut order = Vec::with_capacity(stop_count);
+    let mut position = start;
+    while order.len() < stop_count {
+        let is_last = order.len() + 1 == stop_count;
+        let leg_cost = |index: usize| {
+            let return_leg = if is_last { end.map_or(0.0, |end| stops[index].distance_to(end)) } else { 0.0 };
+            position.distance_to(stops[index]) + return_leg
+        };
+        let next = (0..stops.len())
+            .filter(|index| !visited[*index])
+            .min_by(|a, b| leg_cost(*a).total_cmp(&leg_cost(*b)))
+            .unwrap();
+        visited[next] = true;
+        position = stops[next];
+        order.push(next);
+    }
+    order
+}
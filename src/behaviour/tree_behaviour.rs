@@ -0,0 +1,412 @@
+use bonsai_bt::{Behavior, Event, Status, UpdateArgs, BT, RUNNING};
+use godot::prelude::*;
+
+use super::agent_behaviour::{AgentBehaviourResult, IAgentBehaviour};
+use super::farmer_behaviour::{
+    can_carry_harvest, field_instance_id, forget_stopped_field, harvest_field, next_field_to_harvest,
+    next_field_to_plant, FieldTask,
+};
+use super::introspection::StateStack;
+use super::move_and_build_behaviour::MoveAndBuildBehaviour;
+use super::move_behaviour::{MoveBehaviour, Result};
+use super::utility::JobScore;
+use crate::building::{Building, Field, FieldState};
+use crate::resources::inventory::Inventory;
+use crate::resources::transaction_log::InventoryOwner;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MoveTarget {
+    Home,
+    TargetField,
+    TargetBuyer,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum BuildingKind {
+    Home,
+    Field,
+}
+
+/// Leaves of an agent behaviour tree. Conditions finish in the tick they are evaluated,
+/// actions run over several ticks through the wrapped move and build behaviours.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum AgentAction {
+    HasHome,
+    CanBuildField,
+    CanCarryHarvest,
+    IsCarrying,
+    SelectGrownField,
+    /// Picks the nearest other household short of food
+    SelectBuyer,
+    SelectEmptyField,
+    /// Runs until one of the agent's fields is grown, fails if none is planted
+    WaitForGrownField,
+    /// Runs until one of the agent's fallow fields can be planted again, fails if none lies fallow
+    WaitForFallowField,
+    MoveTo(MoveTarget),
+    Construct(BuildingKind),
+    /// Plants the selected empty field again
+    Plant,
+    Deconstruct,
+    Harvest,
+    Deposit,
+    /// Hands the carried goods to the selected buyer
+    Supply,
+}
+
+/// State shared by all nodes of an agent tree.
+pub struct AgentBlackboard {
+    pub agent_name: String,
+    pub parent_node: Option<Gd<Node>>,
+    pub agent_position: Vector2,
+    pub next_position: Option<Vector2>,
+    pub home: Option<Gd<Building>>,
+    pub fields: Vec<Gd<Field>>,
+    pub target_field: Option<Gd<Field>>,
+    pub target_buyer: Option<Gd<Building>>,
+    pub inventory: Inventory,
+    pub max_field_count: usize,
+    pub fallow_duration: f32,
+}
+
+impl AgentBlackboard {
+    pub fn new(carry_capacity: i32, max_field_count: usize, fallow_duration: f32) -> Self {
+        Self {
+            agent_name: String::new(),
+            parent_node: None,
+            agent_position: Vector2::ZERO,
+            next_position: None,
+            home: None,
+            fields: Vec::new(),
+            target_field: None,
+            target_buyer: None,
+            inventory: Inventory::with_capacity(InventoryOwner::default(), carry_capacity),
+            max_field_count,
+            fallow_duration,
+        }
+    }
+
+    fn home_position(&self) -> Option<Vector2> {
+        self.home.as_ref().map(|home| home.bind().base().get_position())
+    }
+}
+
+/// Runs the long running actions of a tree. An action is started the first tick it is ticked
+/// and finished once the wrapped behaviour succeeds.
+pub struct ActionExecutor {
+    move_behaviour: MoveBehaviour,
+    home_build_behaviour: MoveAndBuildBehaviour<Building>,
+    field_build_behaviour: MoveAndBuildBehaviour<Field>,
+    active_action: Option<AgentAction>,
+    /// Instance id of the field the running Construct(Field) builds
+    building_field: Option<i64>,
+}
+
+impl ActionExecutor {
+    pub fn new(
+        move_behaviour: MoveBehaviour,
+        home_build_behaviour: MoveAndBuildBehaviour<Building>,
+        field_build_behaviour: MoveAndBuildBehaviour<Field>,
+    ) -> Self {
+        Self { move_behaviour, home_build_behaviour, field_build_behaviour, active_action: None, building_field: None }
+    }
+
+    pub fn reset(&mut self) {
+        self.active_action = None;
+    }
+
+    /// The long running action in progress and the behaviour carrying it out.
+    pub fn state_stack(&self) -> StateStack {
+        let Some(action) = self.active_action else {
+            return StateStack::of("ActionExecutor", format_args!("Idle"));
+        };
+        let stack = StateStack::of("ActionExecutor", action);
+        match action {
+            AgentAction::MoveTo(_) => stack.then(self.move_behaviour.state_stack()),
+            AgentAction::Construct(BuildingKind::Home) => stack.then(self.home_build_behaviour.state_stack()),
+            AgentAction::Construct(BuildingKind::Field) | AgentAction::Plant | AgentAction::Deconstruct => {
+                stack.then(self.field_build_behaviour.state_stack())
+            }
+            _ => stack,
+        }
+    }
+
+    /// Restarts the movement of the running action from the new agent position.
+    pub fn resume(&mut self, blackboard: &AgentBlackboard) {
+        match self.active_action {
+            Some(AgentAction::MoveTo(_)) => self.move_behaviour.resume(blackboard.agent_position),
+            Some(AgentAction::Construct(BuildingKind::Home)) => {
+                self.home_build_behaviour.resume(blackboard.agent_position)
+            }
+            Some(AgentAction::Construct(BuildingKind::Field))
+            | Some(AgentAction::Plant)
+            | Some(AgentAction::Deconstruct) => self.field_build_behaviour.resume(blackboard.agent_position),
+            _ => {}
+        }
+    }
+
+    /// Aborts the running action and undoes what it left half done.
+    pub fn cancel(&mut self, blackboard: &mut AgentBlackboard) {
+        match self.active_action.take() {
+            Some(AgentAction::MoveTo(_)) => self.move_behaviour.stop(),
+            Some(AgentAction::Construct(BuildingKind::Home)) => {
+                self.home_build_behaviour.cancel();
+                blackboard.home = None;
+            }
+            Some(AgentAction::Construct(BuildingKind::Field)) => {
+                self.field_build_behaviour.cancel();
+                if let Some(field) = self.building_field.take() {
+                    forget_stopped_field(&mut blackboard.fields, FieldTask::Building(field), field_instance_id);
+                }
+            }
+            Some(AgentAction::Plant) | Some(AgentAction::Deconstruct) => {
+                self.field_build_behaviour.cancel();
+                blackboard.target_field = None;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn execute(&mut self, action: AgentAction, dt: f64, blackboard: &mut AgentBlackboard) -> (Status, f64) {
+        match action {
+            AgentAction::HasHome => condition(blackboard.home.is_some(), dt),
+            AgentAction::CanBuildField => condition(blackboard.fields.len() < blackboard.max_field_count, dt),
+            AgentAction::CanCarryHarvest => condition(can_carry_harvest(&blackboard.inventory), dt),
+            AgentAction::IsCarrying => condition(!blackboard.inventory.is_empty(), dt),
+            AgentAction::SelectGrownField => {
+                blackboard.target_field = next_field_to_harvest(
+                    &blackboard.fields,
+                    blackboard.agent_position,
+                    blackboard.home_position(),
+                    &blackboard.inventory,
+                );
+                condition(blackboard.target_field.is_some(), dt)
+            }
+            AgentAction::SelectBuyer => {
+                blackboard.target_buyer = nearest_buyer(blackboard.home.as_ref(), blackboard.agent_position);
+                condition(blackboard.target_buyer.is_some(), dt)
+            }
+            AgentAction::SelectEmptyField => {
+                blackboard.target_field = next_field_to_plant(&blackboard.fields, blackboard.agent_position);
+                condition(blackboard.target_field.is_some(), dt)
+            }
+            AgentAction::WaitForGrownField => {
+                wait_for_field(&blackboard.fields, FieldState::Grown, &[FieldState::Growing], dt)
+            }
+            AgentAction::WaitForFallowField => {
+                wait_for_field(&blackboard.fields, FieldState::Seeding, &[FieldState::Fallow], dt)
+            }
+            AgentAction::Harvest => {
+                let Some(mut field) = blackboard.target_field.take() else {
+                    return (Status::Failure, dt);
+                };
+                harvest_field(&mut field, &mut blackboard.inventory, &blackboard.agent_name, blackboard.fallow_duration);
+                (Status::Success, dt)
+            }
+            AgentAction::Deposit => {
+                let Some(home) = blackboard.home.as_mut() else {
+                    return (Status::Failure, dt);
+                };
+                home.bind_mut().deposit_from(&mut blackboard.inventory, &blackboard.agent_name);
+                (Status::Success, dt)
+            }
+            AgentAction::Supply => {
+                let Some(mut buyer) = blackboard.target_buyer.take().filter(|buyer| buyer.is_instance_valid()) else {
+                    return (Status::Failure, dt);
+                };
+                buyer.bind_mut().supply_from(&mut blackboard.inventory, &blackboard.agent_name);
+                (Status::Success, dt)
+            }
+            AgentAction::MoveTo(_) | AgentAction::Construct(_) | AgentAction::Plant | AgentAction::Deconstruct => {
+                if self.active_action != Some(action) {
+                    if !self.start_action(action, blackboard) {
+                        return (Status::Failure, dt);
+                    }
+                    self.active_action = Some(action);
+                }
+                let (result, next_position) = self.run_action(action, dt);
+                blackboard.next_position = next_position;
+                match result {
+                    Result::Running => RUNNING,
+                    Result::Success => {
+                        self.active_action = None;
+                        (Status::Success, 0.0)
+                    }
+                    Result::Failure(reason) => {
+                        godot_print!("Agent {}: {:?} failed {:?}", blackboard.agent_name, action, reason);
+                        self.active_action = None;
+                        (Status::Failure, 0.0)
+                    }
+                }
+            }
+        }
+    }
+
+    fn start_action(&mut self, action: AgentAction, blackboard: &mut AgentBlackboard) -> bool {
+        let agent_position = blackboard.agent_position;
+        match action {
+            AgentAction::MoveTo(target) => {
+                let target_position = match target {
+                    MoveTarget::Home => blackboard.home_position(),
+                    MoveTarget::TargetField => {
+                        blackboard.target_field.as_ref().map(|field| field.bind().base().get_position())
+                    }
+                    MoveTarget::TargetBuyer => blackboard
+                        .target_buyer
+                        .as_ref()
+                        .filter(|buyer| buyer.is_instance_valid())
+                        .map(|buyer| buyer.bind().base().get_position()),
+                };
+                let Some(target_position) = target_position else {
+                    return false;
+                };
+                self.move_behaviour.start_moving(agent_position, target_position);
+            }
+            AgentAction::Construct(BuildingKind::Home) => {
+                let home = self.home_build_behaviour.start_construction(
+                    agent_position,
+                    blackboard.agent_name.clone(),
+                    blackboard.parent_node.clone(),
+                );
+                blackboard.home = Some(home);
+            }
+            AgentAction::Construct(BuildingKind::Field) => {
+                let field = self.field_build_behaviour.start_construction(
+                    agent_position,
+                    blackboard.agent_name.clone(),
+                    blackboard.parent_node.clone(),
+                );
+                self.building_field = Some(field_instance_id(&field));
+                blackboard.fields.push(field);
+            }
+            AgentAction::Plant => {
+                let Some(field) = blackboard.target_field.clone() else {
+                    return false;
+                };
+                return self.field_build_behaviour.start_joining_construction(
+                    field,
+                    agent_position,
+                    blackboard.agent_name.clone(),
+                );
+            }
+            AgentAction::Deconstruct => {
+                let Some(field) = blackboard.target_field.clone() else {
+                    return false;
+                };
+                self.field_build_behaviour.start_deconstruction(field, agent_position);
+            }
+            _ => unreachable!("{:?} is not a long running action", action),
+        }
+        true
+    }
+
+    fn run_action(&mut self, action: AgentAction, dt: f64) -> (Result, Option<Vector2>) {
+        match action {
+            AgentAction::MoveTo(_) => {
+                let (result, next_position) = self.move_behaviour.move_agent(dt);
+                (result, Some(next_position))
+            }
+            AgentAction::Construct(BuildingKind::Home) => self.home_build_behaviour.build(dt),
+            AgentAction::Construct(BuildingKind::Field) | AgentAction::Plant | AgentAction::Deconstruct => {
+                self.field_build_behaviour.build(dt)
+            }
+            _ => unreachable!("{:?} is not a long running action", action),
+        }
+    }
+}
+
+/// Closest household other than `home` that is short of food.
+fn nearest_buyer(home: Option<&Gd<Building>>, agent_position: Vector2) -> Option<Gd<Building>> {
+    let distance = |building: &Gd<Building>| agent_position.distance_to(building.bind().base().get_position());
+    Building::all()
+        .into_iter()
+        .filter(|building| home != Some(building) && building.bind().is_short_of_food())
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+}
+
+/// Succeeds once a field is in the `awaited` state, runs while one is still on its way there.
+fn wait_for_field(fields: &[Gd<Field>], awaited: FieldState, on_the_way: &[FieldState], dt: f64) -> (Status, f64) {
+    if fields.iter().any(|field| field.bind().state == awaited) {
+        (Status::Success, dt)
+    } else if fields.iter().any(|field| on_the_way.contains(&field.bind().state)) {
+        RUNNING
+    } else {
+        (Status::Failure, dt)
+    }
+}
+
+fn condition(value: bool, dt: f64) -> (Status, f64) {
+    if value {
+        (Status::Success, dt)
+    } else {
+        (Status::Failure, dt)
+    }
+}
+
+/// Agent behaviour that ticks a bonsai tree. The tree restarts from its root once it finishes.
+pub struct TreeAgentBehaviour {
+    tree: BT<AgentAction, AgentBlackboard>,
+    executor: ActionExecutor,
+}
+
+impl TreeAgentBehaviour {
+    pub fn new(behavior: Behavior<AgentAction>, blackboard: AgentBlackboard, executor: ActionExecutor) -> Self {
+        Self { tree: BT::new(behavior, blackboard), executor }
+    }
+}
+
+impl IAgentBehaviour for TreeAgentBehaviour {
+    fn start(&mut self, agent_name: String, parent_node: Option<Gd<Node>>) {
+        let blackboard = self.tree.blackboard_mut();
+        blackboard.inventory.set_owner(InventoryOwner::Agent(agent_name.clone()));
+        blackboard.agent_name = agent_name;
+        blackboard.parent_node = parent_node;
+        self.tree.reset_bt();
+        self.executor.reset();
+    }
+
+    fn cancel(&mut self) {
+        godot_print!("Agent {}: Cancelling tree", self.tree.blackboard().agent_name);
+        self.executor.cancel(self.tree.blackboard_mut());
+        self.tree.reset_bt();
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        let blackboard = self.tree.blackboard_mut();
+        blackboard.agent_position = agent_position;
+        self.executor.resume(self.tree.blackboard());
+    }
+
+    fn home_position(&self) -> Option<Vector2> {
+        self.tree.blackboard().home.as_ref().filter(|home| home.is_instance_valid()).map(|home| home.bind().base().get_position())
+    }
+
+    fn job_scores(&self) -> Vec<JobScore> {
+        // The tree only knows farming
+        Vec::new()
+    }
+
+    fn state_stack(&self) -> StateStack {
+        StateStack::of("TreeAgentBehaviour", format_args!("Running")).then(self.executor.state_stack())
+    }
+
+    fn tick(&mut self, delta: f64, agent_position: Vector2) -> AgentBehaviourResult {
+        let blackboard = self.tree.blackboard_mut();
+        blackboard.agent_position = agent_position;
+        blackboard.next_position = None;
+        blackboard.fields.retain(|field| field.is_instance_valid());
+        if blackboard.home.as_ref().is_some_and(|home| !home.is_instance_valid()) {
+            blackboard.home = None;
+        }
+
+        let event: Event = UpdateArgs { dt: delta }.into();
+        let executor = &mut self.executor;
+        let status =
+            self.tree.tick(&event, &mut |args, blackboard| executor.execute(*args.action, args.dt, blackboard));
+        if !matches!(status, Some((Status::Running, _))) {
+            self.tree.reset_bt();
+            self.executor.reset();
+        }
+        AgentBehaviourResult { next_position: self.tree.blackboard().next_position }
+    }
+}
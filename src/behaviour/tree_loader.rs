@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use bonsai_bt::{
+    AlwaysSucceed, Behavior, Invert, Select, Sequence, Timeout, Wait, WaitForever, WhenAll, WhenAny, While,
+};
+use serde_json::{Map, Value};
+
+use super::tree_behaviour::{AgentAction, BuildingKind, MoveTarget};
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct TreeLoadError {
+    /// Path of the offending node from the root, or the line and column of a syntax error
+    pub location: String,
+    pub message: String,
+}
+
+impl Display for TreeLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Parameters of a leaf node as written in the tree file.
+pub struct LeafParameters<'a> {
+    values: Option<&'a Map<String, Value>>,
+}
+
+impl LeafParameters<'_> {
+    pub fn string(&self, name: &str) -> Result<&str, String> {
+        match self.values.and_then(|values| values.get(name)) {
+            Some(Value::String(value)) => Ok(value),
+            Some(value) => Err(format!("parameter `{}` must be a string, got {}", name, value)),
+            None => Err(format!("missing parameter `{}`", name)),
+        }
+    }
+}
+
+type LeafBuilder = Box<dyn Fn(&LeafParameters) -> Result<AgentAction, String>>;
+
+struct LeafDefinition {
+    parameters: &'static [&'static str],
+    build: LeafBuilder,
+}
+
+/// Action and condition nodes a tree file can reference by name.
+#[derive(Default)]
+pub struct TreeNodeRegistry {
+    leaves: HashMap<String, LeafDefinition>,
+}
+
+impl TreeNodeRegistry {
+    pub fn new() -> Self {
+        Self { leaves: HashMap::new() }
+    }
+
+    pub fn register(
+        &mut self,
+        name: &str,
+        parameters: &'static [&'static str],
+        build: impl Fn(&LeafParameters) -> Result<AgentAction, String> + 'static,
+    ) {
+        self.leaves.insert(name.to_string(), LeafDefinition { parameters, build: Box::new(build) });
+    }
+
+    pub fn register_simple(&mut self, name: &str, action: AgentAction) {
+        self.register(name, &[], move |_| Ok(action));
+    }
+
+    /// Registry with every node of `AgentAction`.
+    pub fn agent_nodes() -> Self {
+        let mut registry = Self::new();
+        registry.register_simple("HasHome", AgentAction::HasHome);
+        registry.register_simple("CanBuildField", AgentAction::CanBuildField);
+        registry.register_simple("CanCarryHarvest", AgentAction::CanCarryHarvest);
+        registry.register_simple("IsCarrying", AgentAction::IsCarrying);
+        registry.register_simple("SelectGrownField", AgentAction::SelectGrownField);
+        registry.register_simple("SelectBuyer", AgentAction::SelectBuyer);
+        registry.register_simple("SelectEmptyField", AgentAction::SelectEmptyField);
+        registry.register_simple("WaitForGrownField", AgentAction::WaitForGrownField);
+        registry.register_simple("WaitForFallowField", AgentAction::WaitForFallowField);
+        registry.register_simple("Plant", AgentAction::Plant);
+        registry.register_simple("Deconstruct", AgentAction::Deconstruct);
+        registry.register_simple("Harvest", AgentAction::Harvest);
+        registry.register_simple("Deposit", AgentAction::Deposit);
+        registry.register_simple("Supply", AgentAction::Supply);
+        registry.register("MoveTo", &["target"], |parameters| {
+            let target = match parameters.string("target")? {
+                "Home" => MoveTarget::Home,
+                "TargetField" => MoveTarget::TargetField,
+                "TargetBuyer" => MoveTarget::TargetBuyer,
+                other => {
+                    return Err(format!("unknown move target `{}`, expected Home, TargetField or TargetBuyer", other))
+                }
+            };
+            Ok(AgentAction::MoveTo(target))
+        });
+        registry.register("Construct", &["building"], |parameters| {
+            let building = match parameters.string("building")? {
+                "Home" => BuildingKind::Home,
+                "Field" => BuildingKind::Field,
+                other => return Err(format!("unknown building `{}`, expected Home or Field", other)),
+            };
+            Ok(AgentAction::Construct(building))
+        });
+        registry
+    }
+}
+
+/// Parses a JSON behaviour tree. Every problem found is reported, not only the first one.
+///
+/// Composites are written as `{"Sequence": [...]}`, `{"Select": [...]}`, `{"WhenAll": [...]}`,
+/// `{"WhenAny": [...]}`, `{"Invert": node}`, `{"AlwaysSucceed": node}`, `{"Wait": {"seconds": 1.0}}`,
+/// `{"Timeout": {"seconds": 1.0, "child": node}}`, `{"While": {"condition": node, "children": [...]}}`
+/// and `"WaitForever"`. Leaves are `"Name"` or `{"Name": {"parameter": value}}`.
+pub fn load_behaviour_tree(
+    source: &str,
+    registry: &TreeNodeRegistry,
+) -> Result<Behavior<AgentAction>, Vec<TreeLoadError>> {
+    let root: Value = serde_json::from_str(source).map_err(|error| {
+        vec![TreeLoadError {
+            location: format!("line {}, column {}", error.line(), error.column()),
+            message: error.to_string(),
+        }]
+    })?;
+    let mut parser = TreeParser { registry, errors: Vec::new() };
+    let behaviour = parser.parse_node(&root, "root");
+    match behaviour {
+        Some(behaviour) if parser.errors.is_empty() => Ok(behaviour),
+        _ => Err(parser.errors),
+    }
+}
+
+struct TreeParser<'a> {
+    registry: &'a TreeNodeRegistry,
+    errors: Vec<TreeLoadError>,
+}
+
+impl TreeParser<'_> {
+    fn parse_node(&mut self, node: &Value, location: &str) -> Option<Behavior<AgentAction>> {
+        match node {
+            Value::String(name) if name == "WaitForever" => Some(WaitForever),
+            Value::String(name) => self.parse_leaf(name, None, location),
+            Value::Object(object) if object.len() == 1 => {
+                let (name, body) = object.iter().next().unwrap();
+                let location = format!("{}.{}", location, name);
+                self.parse_named_node(name, body, &location)
+            }
+            _ => self.error(location, "expected a node name or an object with exactly one node name".to_string()),
+        }
+    }
+
+    fn parse_named_node(&mut self, name: &str, body: &Value, location: &str) -> Option<Behavior<AgentAction>> {
+        match name {
+            "Sequence" => self.parse_children(body, location).map(Sequence),
+            "Select" => self.parse_children(body, location).map(Select),
+            "WhenAll" => self.parse_children(body, location).map(WhenAll),
+            "WhenAny" => self.parse_children(body, location).map(WhenAny),
+            "Invert" => self.parse_node(body, location).map(|child| Invert(Box::new(child))),
+            "AlwaysSucceed" => self.parse_node(body, location).map(|child| AlwaysSucceed(Box::new(child))),
+            "Wait" => {
+                let fields = self.parse_fields(body, location, &["seconds"])?;
+                self.parse_seconds(fields, location).map(Wait)
+            }
+            "Timeout" => {
+                let fields = self.parse_fields(body, location, &["seconds", "child"])?;
+                let seconds = self.parse_seconds(fields, location);
+                let child = self.parse_field_node(fields, "child", location);
+                Some(Timeout(seconds?, Box::new(child?)))
+            }
+            "While" => {
+                let fields = self.parse_fields(body, location, &["condition", "children"])?;
+                let condition = self.parse_field_node(fields, "condition", location);
+                let children = match fields.get("children") {
+                    Some(children) => self.parse_children(children, &format!("{}.children", location)),
+                    None => self.error(location, "missing field `children`".to_string()),
+                };
+                Some(While(Box::new(condition?), children?))
+            }
+            _ => match body {
+                Value::Object(parameters) => self.parse_leaf(name, Some(parameters), location),
+                _ => self.error(location, format!("parameters of `{}` must be an object", name)),
+            },
+        }
+    }
+
+    fn parse_leaf(
+        &mut self,
+        name: &str,
+        parameters: Option<&Map<String, Value>>,
+        location: &str,
+    ) -> Option<Behavior<AgentAction>> {
+        let Some(definition) = self.registry.leaves.get(name) else {
+            return self.error(location, format!("unknown node `{}`", name));
+        };
+        let unknown_parameters: Vec<&String> = parameters
+            .into_iter()
+            .flat_map(|parameters| parameters.keys())
+            .filter(|parameter| !definition.parameters.contains(&parameter.as_str()))
+            .collect();
+        for parameter in unknown_parameters {
+            self.errors.push(TreeLoadError {
+                location: location.to_string(),
+                message: format!(
+                    "unknown parameter `{}` for `{}`, expected one of [{}]",
+                    parameter,
+                    name,
+                    definition.parameters.join(", ")
+                ),
+            });
+        }
+        match (definition.build)(&LeafParameters { values: parameters }) {
+            Ok(action) => Some(Behavior::Action(action)),
+            Err(message) => self.error(location, message),
+        }
+    }
+
+    fn parse_children(&mut self, body: &Value, location: &str) -> Option<Vec<Behavior<AgentAction>>> {
+        let Value::Array(children) = body else {
+            return self.error(location, "expected a list of child nodes".to_string());
+        };
+        let parsed: Vec<Option<Behavior<AgentAction>>> = children
+            .iter()
+            .enumerate()
+            .map(|(index, child)| self.parse_node(child, &format!("{}[{}]", location, index)))
+            .collect();
+        parsed.into_iter().collect()
+    }
+
+    fn parse_fields<'b>(
+        &mut self,
+        body: &'b Value,
+        location: &str,
+        expected: &[&str],
+    ) -> Option<&'b Map<String, Value>> {
+        let Value::Object(fields) = body else {
+            return self.error(location, format!("expected an object with fields [{}]", expected.join(", ")));
+        };
+        for field in fields.keys().filter(|field| !expected.contains(&field.as_str())) {
+            self.errors.push(TreeLoadError {
+                location: location.to_string(),
+                message: format!("unknown field `{}`, expected one of [{}]", field, expected.join(", ")),
+            });
+        }
+        Some(fields)
+    }
+
+    fn parse_seconds(&mut self, fields: &Map<String, Value>, location: &str) -> Option<f64> {
+        match fields.get("seconds").and_then(Value::as_f64) {
+            Some(seconds) if seconds >= 0.0 => Some(seconds),
+            _ => self.error(location, "field `seconds` must be a non negative number".to_string()),
+        }
+    }
+
+    fn parse_field_node(
+        &mut self,
+        fields: &Map<String, Value>,
+        field: &str,
+        location: &str,
+    ) -> Option<Behavior<AgentAction>> {
+        match fields.get(field) {
+            Some(node) => self.parse_node(node, &format!("{}.{}", location, field)),
+            None => self.error(location, format!("missing field `{}`", field)),
+        }
+    }
+
+    fn error<T>(&mut self, location: &str, message: String) -> Option<T> {
+        self.errors.push(TreeLoadError { location: location.to_string(), message });
+        None
+    }
+}
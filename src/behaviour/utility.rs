@@ -0,0 +1,91 @@
+use std::fmt::Display;
+
+use crate::resources::inventory::{Inventory, InventoryResource};
+
+/// Stock of a good a household wants to keep, below it producing the good becomes more urgent.
+pub const HOUSEHOLD_STOCK_TARGET: i32 = 10;
+
+/// What a work behaviour reports about itself so it can be compared with other jobs.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct UtilityInputs {
+    /// How badly the household needs what the job produces, from 0 to 1
+    pub household_need: f32,
+    /// Value of one round of work in coins
+    pub price: f32,
+    /// Distance to the next work site
+    pub distance: f32,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct UtilityWeights {
+    pub household_need: f32,
+    pub price: f32,
+    pub distance: f32,
+    pub skill: f32,
+    /// Distance at which the distance term has dropped to half
+    pub distance_scale: f32,
+}
+
+/// Score of one job with the weighted terms it was summed from, kept for debugging.
+#[derive(PartialEq, Clone, Debug)]
+pub struct JobScore {
+    pub job: &'static str,
+    pub available: bool,
+    pub household_need: f32,
+    pub price: f32,
+    pub distance: f32,
+    pub skill: f32,
+    pub total: f32,
+}
+
+impl Display for JobScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.available {
+            return write!(f, "{}: no work available", self.job);
+        }
+        write!(
+            f,
+            "{}: {:.2} (need {:.2}, price {:.2}, distance {:.2}, skill {:.2})",
+            self.job, self.total, self.household_need, self.price, self.distance, self.skill
+        )
+    }
+}
+
+/// Need for `resource` in a household, 1 when it has none and 0 once the stock target is reached.
+pub fn household_need(household: &Inventory, resource: InventoryResource) -> f32 {
+    let stock = household.amount(resource).min(HOUSEHOLD_STOCK_TARGET);
+    1.0 - stock as f32 / HOUSEHOLD_STOCK_TARGET as f32
+}
+
+pub fn score_job(job: &'static str, inputs: UtilityInputs, skill: f32, weights: &UtilityWeights) -> JobScore {
+    let household_need = weights.household_need * inputs.household_need;
+    let price = weights.price * inputs.price;
+    let distance = weights.distance * weights.distance_scale / (weights.distance_scale + inputs.distance.max(0.0));
+    let skill = weights.skill * skill;
+    JobScore {
+        job,
+        available: true,
+        household_need,
+        price,
+        distance,
+        skill,
+        total: household_need + price + distance + skill,
+    }
+}
+
+pub fn unavailable_job(job: &'static str) -> JobScore {
+    JobScore { job, available: false, household_need: 0.0, price: 0.0, distance: 0.0, skill: 0.0, total: 0.0 }
+}
+
+/// Index of the available job with the highest score, the first one wins a tie.
+pub fn pick_best_job(scores: &[JobScore]) -> Option<usize> {
+    scores
+        .iter()
+        .enumerate()
+        .filter(|(_, score)| score.available)
+        .fold(None, |best: Option<(usize, f32)>, (index, score)| match best {
+            Some((_, best_total)) if best_total >= score.total => best,
+            _ => Some((index, score.total)),
+        })
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,85 @@
+use godot::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use super::move_behaviour::{MoveBehaviour, Result};
+use super::random::seeded_rng;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum WolfState {
+    Resting,
+    Roaming,
+    /// Running from a guard towards a new den
+    DrivenOff,
+}
+
+pub struct WolfBehaviourConfig {
+    pub roam_radius: f32,
+    pub rest_duration: f32,
+    /// How far a scared wolf moves its den away from whoever scared it
+    pub driven_off_distance: f32,
+}
+
+/// Wolves roam between random points around their den and rest in between. A scared wolf
+/// runs off and settles a new den further away.
+pub struct WolfBehaviour {
+    state: WolfState,
+    move_behaviour: MoveBehaviour,
+    config: WolfBehaviourConfig,
+    den: Vector2,
+    rest_time: f32,
+    rng: StdRng,
+}
+
+impl WolfBehaviour {
+    pub fn new(move_behaviour: MoveBehaviour, config: WolfBehaviourConfig) -> Self {
+        Self { state: WolfState::Resting, move_behaviour, config, den: Vector2::ZERO, rest_time: 0.0, rng: seeded_rng("") }
+    }
+
+    pub fn start(&mut self, wolf_name: &str, den: Vector2) {
+        self.den = den;
+        self.rng = seeded_rng(wolf_name);
+        self.rest_time = 0.0;
+        self.state = WolfState::Resting;
+    }
+
+    pub fn tick(&mut self, delta: f64, wolf_position: Vector2) -> Option<Vector2> {
+        match self.state {
+            WolfState::Resting => {
+                self.rest_time += delta as f32;
+                if self.rest_time >= self.config.rest_duration {
+                    self.start_roaming(wolf_position);
+                }
+                None
+            }
+            WolfState::Roaming | WolfState::DrivenOff => {
+                let (result, next_position) = self.move_behaviour.move_agent(delta);
+                if result != Result::Running {
+                    self.rest_time = 0.0;
+                    self.state = WolfState::Resting;
+                }
+                Some(next_position)
+            }
+        }
+    }
+
+    pub fn is_driven_off(&self) -> bool {
+        self.state == WolfState::DrivenOff
+    }
+
+    /// Drives the wolf away from `scared_from`, its den moves along with it.
+    pub fn scare(&mut self, wolf_position: Vector2, scared_from: Vector2) {
+        let away = (wolf_position - scared_from).try_normalized().unwrap_or(Vector2::RIGHT);
+        self.den = wolf_position + away * self.config.driven_off_distance;
+        self.move_behaviour.start_moving(wolf_position, self.den);
+        self.state = WolfState::DrivenOff;
+    }
+
+    fn start_roaming(&mut self, wolf_position: Vector2) {
+        let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+        let distance = self.rng.gen_range(0.0..self.config.roam_radius);
+        let target = self.den + Vector2::RIGHT.rotated(angle) * distance;
+        self.move_behaviour.start_moving(wolf_position, target);
+        self.state = WolfState::Roaming;
+    }
+}
@@ -0,0 +1,298 @@
+use super::introspection::StateStack;
+use super::move_behaviour::{FailureReason, MoveBehaviour, Result};
+use super::perception::nearest_within;
+use super::utility::{household_need, UtilityInputs};
+use super::work_behaviour::{leave_for_hauler, return_home, start_returning_home, IWorkBehaviour, WorkResult};
+use crate::building::{Building, ForestTree};
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::prices::Prices;
+use crate::resources::transaction_log::InventoryOwner;
+use godot::prelude::*;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum WoodcutterState {
+    Idle,
+    MovingToTree,
+    Felling,
+    Replanting,
+    ReturningToHome,
+}
+
+pub struct WoodcutterBehaviourConfig {
+    pub carry_capacity: i32,
+    pub fell_duration: f32,
+    pub replant_duration: f32,
+    /// Whether felled trees are replanted as saplings or cleared, freeing the land
+    pub replant: bool,
+    pub work_offset: Vector2,
+}
+
+/// Fells the closest grown tree, carries the wood home, or leaves a full load for a hauler, and,
+/// if configured to, puts a sapling where the tree stood.
+pub struct WoodcutterBehaviour {
+    state: WoodcutterState,
+    move_behaviour: MoveBehaviour,
+    config: WoodcutterBehaviourConfig,
+    target_tree: Option<Gd<ForestTree>>,
+    work_progress: f32,
+    inventory: Inventory,
+    agent_name: String,
+    home: Option<Gd<Building>>,
+}
+
+impl IWorkBehaviour for WoodcutterBehaviour {
+    fn work(&mut self, delta: f64, agent_position: Vector2) -> WorkResult {
+        loop {
+            match self.state {
+                WoodcutterState::Idle => {
+                    let tree = if self.inventory.is_full() { None } else { self.find_next_tree(agent_position) };
+                    if let Some(tree) = tree {
+                        self.start_moving_to_tree(tree, agent_position);
+                    } else if !self.inventory.is_empty() {
+                        if let Err(reason) = self.start_returning_to_home(agent_position) {
+                            return WorkResult { result: Result::Failure(reason), next_position: None };
+                        }
+                    } else {
+                        return WorkResult { result: Result::Success, next_position: None };
+                    }
+                }
+                WoodcutterState::MovingToTree => {
+                    if !self.is_target_tree_valid() {
+                        return self.fail(FailureReason::TargetLost);
+                    }
+                    let (result, next_position) = self.move_behaviour.move_agent(delta);
+                    match result {
+                        Result::Running => {
+                            return WorkResult { result, next_position: Some(next_position) };
+                        }
+                        Result::Success => {
+                            self.start_working_on_tree();
+                            return WorkResult { result: Result::Running, next_position: Some(next_position) };
+                        }
+                        Result::Failure(reason) => return self.fail(reason),
+                    }
+                }
+                WoodcutterState::Felling => {
+                    if !self.is_target_tree_valid() {
+                        return self.fail(FailureReason::TargetLost);
+                    }
+                    self.work_progress += delta as f32;
+                    if self.work_progress < self.config.fell_duration {
+                        return WorkResult { result: Result::Running, next_position: None };
+                    }
+                    self.finish_felling();
+                }
+                WoodcutterState::Replanting => {
+                    if !self.is_target_tree_valid() {
+                        return self.fail(FailureReason::TargetLost);
+                    }
+                    self.work_progress += delta as f32;
+                    if self.work_progress < self.config.replant_duration {
+                        return WorkResult { result: Result::Running, next_position: None };
+                    }
+                    self.finish_replanting();
+                }
+                WoodcutterState::ReturningToHome => {
+                    let work_result = return_home(
+                        &mut self.move_behaviour,
+                        self.home.as_mut(),
+                        &mut self.inventory,
+                        delta,
+                        &self.agent_name,
+                    );
+                    if work_result.result != Result::Running {
+                        // Whatever is carried stays in the inventory for the next trip
+                        self.state = WoodcutterState::Idle;
+                    }
+                    return work_result;
+                }
+            }
+        }
+    }
+
+    fn start_work(&mut self, home: Gd<Building>, agent_name: String, _parent_node: Option<Gd<Node>>) {
+        self.home = Some(home);
+        self.state = WoodcutterState::Idle;
+        self.inventory.set_owner(InventoryOwner::Agent(agent_name.clone()));
+        self.agent_name = agent_name;
+    }
+
+    fn resume(&mut self, agent_position: Vector2) {
+        match self.state {
+            WoodcutterState::Idle => {}
+            // Felling or replanting restarts once the woodcutter is back at the tree, unless someone
+            // else took the tree while it was let go of, then the next tree is picked
+            WoodcutterState::MovingToTree | WoodcutterState::Felling | WoodcutterState::Replanting => {
+                if !self.reserve_target_tree() {
+                    self.move_behaviour.stop();
+                    self.target_tree = None;
+                    self.state = WoodcutterState::Idle;
+                    return;
+                }
+                self.move_behaviour.resume(agent_position);
+                self.state = WoodcutterState::MovingToTree;
+            }
+            WoodcutterState::ReturningToHome => self.move_behaviour.resume(agent_position),
+        }
+    }
+
+    fn pause(&mut self) {
+        // The tree is kept as the target but free for others until the woodcutter resumes
+        if let Some(mut tree) = self.target_tree.clone().filter(|tree| tree.is_instance_valid()) {
+            tree.bind_mut().reservation_mut().release(&self.agent_name);
+        }
+    }
+
+    fn is_work_available(&self) -> bool {
+        !self.inventory.is_empty()
+            || ForestTree::all().iter().any(|tree| tree.bind().can_be_felled() || self.needs_replanting(tree))
+    }
+
+    fn is_idle(&self) -> bool {
+        self.state == WoodcutterState::Idle
+    }
+
+    fn carried_goods(&mut self) -> Option<&mut Inventory> {
+        Some(&mut self.inventory)
+    }
+
+    fn job_name(&self) -> &'static str {
+        "Woodcutter"
+    }
+
+    fn state_stack(&self) -> StateStack {
+        let stack = StateStack::of("WoodcutterBehaviour", self.state);
+        match self.state {
+            WoodcutterState::MovingToTree | WoodcutterState::ReturningToHome => {
+                stack.then(self.move_behaviour.state_stack())
+            }
+            _ => stack,
+        }
+    }
+
+    fn utility_inputs(&self, agent_position: Vector2, home: &Gd<Building>, prices: &Prices) -> UtilityInputs {
+        let distance = self
+            .find_next_tree(agent_position)
+            .map_or(0.0, |tree| agent_position.distance_to(tree.bind().base().get_position()));
+        UtilityInputs {
+            household_need: household_need(&home.bind().inventory, InventoryResource::Wood),
+            price: prices.price(InventoryResource::Wood) * self.config.carry_capacity as f32,
+            distance,
+        }
+    }
+
+    fn cancel(&mut self) {
+        godot_print!("Agent {}: Woodcutter cancelled in state {:?}", self.agent_name, self.state);
+        self.move_behaviour.stop();
+        self.release_target_tree();
+        self.state = WoodcutterState::Idle;
+    }
+}
+
+impl WoodcutterBehaviour {
+    pub fn new(move_behaviour: MoveBehaviour, config: WoodcutterBehaviourConfig) -> Self {
+        Self {
+            state: WoodcutterState::Idle,
+            move_behaviour,
+            target_tree: None,
+            work_progress: 0.0,
+            inventory: Inventory::with_capacity(InventoryOwner::default(), config.carry_capacity),
+            config,
+            agent_name: String::new(),
+            home: None,
+        }
+    }
+
+    /// A felled tree left behind, e.g. by a woodcutter that was called away, is replanted first.
+    fn needs_replanting(&self, tree: &Gd<ForestTree>) -> bool {
+        self.config.replant && tree.bind().is_felled()
+    }
+
+    fn find_next_tree(&self, position: Vector2) -> Option<Gd<ForestTree>> {
+        let trees: Vec<Gd<ForestTree>> = ForestTree::all()
+            .into_iter()
+            .filter(|tree| tree.bind().is_free_for(&self.agent_name))
+            .filter(|tree| tree.bind().can_be_felled() || self.needs_replanting(tree))
+            .collect();
+        let positions: Vec<Vector2> = trees.iter().map(|tree| tree.bind().base().get_position()).collect();
+        nearest_within(position, f32::INFINITY, &positions).map(|index| trees[index].clone())
+    }
+
+    fn release_target_tree(&mut self) {
+        if let Some(mut tree) = self.target_tree.take().filter(|tree| tree.is_instance_valid()) {
+            tree.bind_mut().reservation_mut().release(&self.agent_name);
+        }
+    }
+
+    /// Takes the target tree again after a pause, fails if it is gone or someone else has it.
+    fn reserve_target_tree(&mut self) -> bool {
+        let agent_name = &self.agent_name;
+        self.target_tree
+            .as_mut()
+            .filter(|tree| tree.is_instance_valid())
+            .is_some_and(|tree| tree.bind_mut().reservation_mut().reserve(agent_name))
+    }
+
+    fn is_target_tree_valid(&self) -> bool {
+        self.target_tree.as_ref().is_some_and(|tree| tree.is_instance_valid())
+    }
+
+    fn start_moving_to_tree(&mut self, mut tree: Gd<ForestTree>, agent_position: Vector2) {
+        godot_print!("Agent {}: Starting move to tree", self.agent_name);
+        tree.bind_mut().reservation_mut().reserve(&self.agent_name);
+        let work_position = tree.bind().base().get_position() + self.config.work_offset;
+        self.move_behaviour.start_moving(agent_position, work_position);
+        self.target_tree = Some(tree);
+        self.state = WoodcutterState::MovingToTree;
+    }
+
+    fn start_working_on_tree(&mut self) {
+        self.work_progress = 0.0;
+        let is_felled = self.target_tree.as_ref().unwrap().bind().is_felled();
+        self.state = if is_felled { WoodcutterState::Replanting } else { WoodcutterState::Felling };
+    }
+
+    fn finish_felling(&mut self) {
+        let amount = self.inventory.free_space();
+        let mut tree = self.target_tree.take().unwrap();
+        let cut = tree.bind_mut().fell(&mut self.inventory, amount, &self.agent_name);
+        godot_print!("Agent {}: Cut {} wood", self.agent_name, cut);
+        if self.inventory.is_full() {
+            leave_for_hauler(&mut self.inventory, &tree, self.home.as_ref(), &self.agent_name);
+        }
+        self.state = WoodcutterState::Idle;
+        if !tree.bind().is_felled() {
+            tree.bind_mut().reservation_mut().release(&self.agent_name);
+            return;
+        }
+        if self.config.replant {
+            self.target_tree = Some(tree);
+            self.work_progress = 0.0;
+            self.state = WoodcutterState::Replanting;
+        } else {
+            tree.bind_mut().clear();
+        }
+    }
+
+    fn finish_replanting(&mut self) {
+        godot_print!("Agent {}: Planted a sapling", self.agent_name);
+        let mut tree = self.target_tree.take().unwrap();
+        tree.bind_mut().replant();
+        tree.bind_mut().reservation_mut().release(&self.agent_name);
+        self.state = WoodcutterState::Idle;
+    }
+
+    fn start_returning_to_home(&mut self, agent_position: Vector2) -> std::result::Result<(), FailureReason> {
+        start_returning_home(&mut self.move_behaviour, self.home.as_ref(), agent_position, &self.agent_name)?;
+        self.state = WoodcutterState::ReturningToHome;
+        Ok(())
+    }
+
+    fn fail(&mut self, reason: FailureReason) -> WorkResult {
+        // Cut wood is kept for the next trip home
+        self.move_behaviour.stop();
+        self.release_target_tree();
+        self.state = WoodcutterState::Idle;
+        WorkResult { result: Result::Failure(reason), next_position: None }
+    }
+}
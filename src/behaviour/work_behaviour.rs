@@ -1,6 +1,12 @@
 use godot::prelude::*;
-use super::move_behaviour::Result;
+use super::introspection::StateStack;
+use super::move_behaviour::{FailureReason, MoveBehaviour, Result};
+use super::utility::{JobScore, UtilityInputs};
 use crate::building::Building;
+use crate::resources::inventory::Inventory;
+use crate::resources::logistics::Logistics;
+use crate::resources::prices::Prices;
+use crate::world::job_board::{Job, Skill};
 
 pub struct WorkResult {
     pub result: Result,
@@ -8,7 +14,115 @@ pub struct WorkResult {
 }
 
 pub trait IWorkBehaviour {
+    /// On `Result::Failure` the behaviour has already cleaned up and is back in its idle state.
     fn work(&mut self, delta: f64, agent_position: Vector2) -> WorkResult;
     fn start_work(&mut self, home: Gd<Building>, agent_name: String, parent_node: Option<Gd<Node>>);
     fn is_work_available(&self) -> bool;
+    /// Aborts the current task, leaving the world as if it was never started. Carried goods are kept.
+    fn cancel(&mut self);
+    /// Picks the current task up again after the agent was called away, e.g. to flee.
+    fn resume(&mut self, agent_position: Vector2);
+    /// Lets go of anything shared with other agents while the task is on hold, see
+    /// `IAgentBehaviour::pause`.
+    fn pause(&mut self) {}
+    /// Whether the behaviour is between two tasks, so the agent can change jobs without losing work.
+    fn is_idle(&self) -> bool;
+    /// Goods carried for this job, they are taken home when the agent changes jobs.
+    fn carried_goods(&mut self) -> Option<&mut Inventory> {
+        None
+    }
+    /// Gives the job up for good, e.g. when changing profession. Unlike `cancel` this may
+    /// tear down what the job built up.
+    fn abandon(&mut self) {
+        self.cancel();
+    }
+    /// Name the job is shown under when comparing jobs.
+    fn job_name(&self) -> &'static str;
+    /// Inputs for scoring this job against others, see `utility::score_job`.
+    fn utility_inputs(&self, agent_position: Vector2, home: &Gd<Building>, prices: &Prices) -> UtilityInputs;
+    /// Current state, followed by the states of the behaviours doing the work for it.
+    fn state_stack(&self) -> StateStack;
+    /// Job board work this behaviour can take on besides its own.
+    fn skills(&self) -> Vec<Skill> {
+        Vec::new()
+    }
+    /// Starts on a job claimed from the board, called after `start_work`. False if the job
+    /// can't be done after all, the claim is then released again.
+    fn take_job(&mut self, _job: &Job, _agent_position: Vector2) -> bool {
+        false
+    }
+    /// Scores from the last job choice, only behaviours choosing between jobs have any.
+    fn job_scores(&self) -> Vec<JobScore> {
+        Vec::new()
+    }
+}
+
+/// Starts the walk home to drop off what the agent carries, fails if the home was freed.
+pub fn start_returning_home(
+    move_behaviour: &mut MoveBehaviour,
+    home: Option<&Gd<Building>>,
+    agent_position: Vector2,
+    agent_name: &str,
+) -> std::result::Result<(), FailureReason> {
+    let Some(home) = home.filter(|home| home.is_instance_valid()) else {
+        return Err(FailureReason::NoHome);
+    };
+    godot_print!("Agent {}: Starting returning to home", agent_name);
+    move_behaviour.start_moving(agent_position, home.bind().base().get_position());
+    Ok(())
+}
+
+/// Walks the agent home and deposits `inventory` there on arrival. When the way home fails or
+/// the home is freed on the way, the goods stay in the inventory for the next trip.
+pub fn return_home(
+    move_behaviour: &mut MoveBehaviour,
+    home: Option<&mut Gd<Building>>,
+    inventory: &mut Inventory,
+    delta: f64,
+    agent_name: &str,
+) -> WorkResult {
+    let Some(home) = home.filter(|home| home.is_instance_valid()) else {
+        move_behaviour.stop();
+        return WorkResult { result: Result::Failure(FailureReason::NoHome), next_position: None };
+    };
+    let (result, next_position) = move_behaviour.move_agent(delta);
+    match result {
+        Result::Running => WorkResult { result, next_position: Some(next_position) },
+        Result::Success => {
+            godot_print!("Agent {}: Returning to home complete", agent_name);
+            home.bind_mut().deposit_from(inventory, agent_name);
+            WorkResult { result, next_position: Some(next_position) }
+        }
+        Result::Failure(_) => {
+            move_behaviour.stop();
+            WorkResult { result, next_position: None }
+        }
+    }
+}
+
+/// Leaves a load at the worksite `site` for a hauler to carry home, see `Logistics::set_down`.
+/// False when the producer has to carry it home itself.
+pub fn leave_for_hauler<T: Inherits<Node2D>>(
+    inventory: &mut Inventory,
+    site: &Gd<T>,
+    home: Option<&Gd<Building>>,
+    agent_name: &str,
+) -> bool {
+    let Some(home) = home.filter(|home| home.is_instance_valid()) else {
+        return false;
+    };
+    let site = site.clone().upcast::<Node2D>();
+    let home = home.bind();
+    let left = Logistics::singleton().bind_mut().set_down(
+        inventory,
+        site.instance_id().to_i64(),
+        site.get_position(),
+        home.inventory.owner().clone(),
+        home.base().get_position(),
+        agent_name,
+    );
+    if left {
+        godot_print!("Agent {}: Left the load for a hauler", agent_name);
+    }
+    left
 }
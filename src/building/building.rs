@@ -1,10 +1,30 @@
 use crate::building::BuildingConfig;
-use crate::resources::inventory::Inventory;
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::logistics::Logistics;
+use crate::resources::transaction_log::{InventoryOwner, TransactionReason};
+use crate::resources::transport::TransportRequest;
+use crate::resources::treasury::Treasury;
 use godot::classes::{CompressedTexture2D, ISprite2D, Label, ResourceLoader, Shader, ShaderMaterial, Sprite2D};
 use godot::obj::WithBaseField;
 use godot::prelude::*;
 
-use super::home_building_config;
+use super::{home_building_config, Bush, ConstructionSite, Deposit, Field, ForestTree, Quarry};
+use crate::behaviour::perception::nodes_in_group;
+use crate::world::job_board::{Job, JobKind};
+use crate::world::job_office::JobOffice;
+use crate::world::terrain::Structure;
+
+pub const BUILDING_GROUP: &str = "buildings";
+/// Households with less food than this buy from farmers and ask for food to be brought over
+/// from other households
+pub const FOOD_SHORTAGE_THRESHOLD: i32 = 3;
+const FOOD: [InventoryResource; 2] = [InventoryResource::Wheat, InventoryResource::Berries];
+const FOOD_REQUEST_AMOUNT: i32 = 2;
+const FOOD_REQUEST_INTERVAL: f32 = 5.0;
+const CONSTRUCT_JOB_PRIORITY: f32 = 1.0;
+/// Coins the treasury pays an agent for helping finish a home
+const CONSTRUCT_JOB_WAGE: i32 = 1;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum BuildingState {
@@ -13,8 +33,18 @@ pub enum BuildingState {
 }
 
 impl IBuilding for Building {
+    fn structure() -> Structure {
+        Structure::Home
+    }
     fn set_completed(&mut self) {
         self.state = BuildingState::Completed;
+        JobOffice::singleton().bind_mut().board.withdraw(self.base().instance_id().to_i64(), JobKind::Construct);
+    }
+    fn construction(&self) -> &ConstructionSite {
+        &self.construction
+    }
+    fn construction_mut(&mut self) -> &mut ConstructionSite {
+        &mut self.construction
     }
 }
 #[derive(GodotClass)]
@@ -26,24 +56,43 @@ pub struct Building {
     resouce_label: Option<Gd<Label>>,
     resource_label_position: Vector2,
     state: BuildingState,
+    construction: ConstructionSite,
+    request_time: f32,
+    /// Whether the construct job for helpers is up on the job board
+    construct_job_posted: bool,
 }
 
 #[godot_api]
 impl ISprite2D for Building {
     fn init(base: Base<Sprite2D>) -> Self {
+        let owner = InventoryOwner::Building(base.to_gd().instance_id().to_i64());
+        let config = home_building_config();
         Self {
             base,
-            inventory: Inventory::new(),
+            inventory: Inventory::new(owner),
             resouce_label: None,
             resource_label_position: Vector2::new(0.0, -600.0),
             state: BuildingState::Building,
+            construction: ConstructionSite::new(config.build_work, config.max_builders),
+            request_time: 0.0,
+            construct_job_posted: false,
         }
     }
 
-    fn physics_process(&mut self, _delta: f64) {
+    fn ready(&mut self) {
+        self.base_mut().add_to_group(BUILDING_GROUP);
+    }
+
+    fn physics_process(&mut self, delta: f64) {
         if self.state == BuildingState::Building {
+            self.update_construct_job();
             return;
         }
+        self.request_time += delta as f32;
+        if self.request_time >= FOOD_REQUEST_INTERVAL {
+            self.request_time = 0.0;
+            self.request_food();
+        }
         if self.resouce_label.is_none() {
             self.resouce_label = Some(Label::new_alloc());
             let node = self.resouce_label.as_mut().unwrap().clone().upcast::<Node>();
@@ -55,6 +104,147 @@ impl ISprite2D for Building {
     }
 }
 
+impl Building {
+    /// Every home in the running scene, finished or not.
+    pub fn all() -> Vec<Gd<Building>> {
+        nodes_in_group(BUILDING_GROUP)
+    }
+
+    /// The building an inventory owner stands for, if it is a building that still exists.
+    pub fn from_owner(owner: &InventoryOwner) -> Option<Gd<Building>> {
+        let InventoryOwner::Building(instance_id) = owner else {
+            return None;
+        };
+        Gd::try_from_instance_id(InstanceId::from_i64(*instance_id)).ok()
+    }
+
+    /// Runs `f` on the inventory `owner` stands for, if it still exists. Agents carry their goods
+    /// inside their behaviours and the world has no inventory, neither resolves.
+    pub fn with_owner_inventory<R>(owner: &InventoryOwner, f: impl FnOnce(&mut Inventory) -> R) -> Option<R> {
+        match owner {
+            InventoryOwner::Building(_) => {
+                Self::from_owner(owner).map(|mut building| f(&mut building.bind_mut().inventory))
+            }
+            InventoryOwner::ResourceNode(instance_id) => {
+                let node = Gd::<Node>::try_from_instance_id(InstanceId::from_i64(*instance_id)).ok()?;
+                if let Ok(mut tree) = node.clone().try_cast::<ForestTree>() {
+                    return Some(f(tree.bind_mut().wood_mut()));
+                }
+                if let Ok(mut bush) = node.clone().try_cast::<Bush>() {
+                    return Some(f(bush.bind_mut().berries_mut()));
+                }
+                let mut deposit = node.try_cast::<Deposit>().ok()?;
+                let result = f(deposit.bind_mut().resources_mut());
+                Some(result)
+            }
+            InventoryOwner::Stockpile(site) => Some(f(Logistics::singleton().bind_mut().stockpile_mut(*site))),
+            InventoryOwner::Treasury => Some(f(&mut Treasury::singleton().bind_mut().inventory)),
+            InventoryOwner::World | InventoryOwner::Agent(_) | InventoryOwner::Construction(_) => None,
+        }
+    }
+
+    /// Builds up to `amount` of `resource` from `inventory` into the construction site of the node
+    /// `site` and returns how much it took, `None` once the site is gone.
+    pub fn deliver_materials(
+        site: i64,
+        inventory: &mut Inventory,
+        resource: InventoryResource,
+        amount: i32,
+        agent_name: &str,
+    ) -> Option<i32> {
+        let amount = amount.min(inventory.amount(resource));
+        let node = Gd::<Node>::try_from_instance_id(InstanceId::from_i64(site)).ok()?;
+        let taken = if let Ok(mut building) = node.clone().try_cast::<Building>() {
+            building.bind_mut().construction_mut().deliver(resource, amount)
+        } else if let Ok(mut quarry) = node.clone().try_cast::<Quarry>() {
+            quarry.bind_mut().construction_mut().deliver(resource, amount)
+        } else {
+            node.try_cast::<Field>().ok()?.bind_mut().construction_mut().deliver(resource, amount)
+        };
+        let context = InventoryLedger::context(agent_name, TransactionReason::Construction);
+        Some(InventoryLedger::singleton().bind_mut().log.consume(inventory, resource, taken, &context))
+    }
+
+    /// Posts a transport request for food from the best stocked household when running short.
+    fn request_food(&mut self) {
+        if !self.is_short_of_food() {
+            return;
+        }
+        let food = self.food();
+        let owner = self.inventory.owner().clone();
+        let mut logistics = Logistics::singleton();
+        if logistics.bind().board.has_request_to(&owner) {
+            return;
+        }
+        let own_id = self.base().instance_id();
+        let minimum_stock = FOOD_SHORTAGE_THRESHOLD + FOOD_REQUEST_AMOUNT;
+        let supplier = Building::all()
+            .into_iter()
+            .filter(|building| building.instance_id() != own_id)
+            .flat_map(|building| {
+                FOOD.map(|resource| (building.bind().inventory.amount(resource), resource, building.clone()))
+            })
+            .filter(|(amount, _, _)| *amount >= minimum_stock)
+            .max_by_key(|(amount, _, _)| *amount);
+        let Some((_, resource, supplier)) = supplier else {
+            return;
+        };
+        let supplier = supplier.bind();
+        let request = TransportRequest::new(
+            resource,
+            FOOD_REQUEST_AMOUNT,
+            supplier.inventory.owner().clone(),
+            supplier.base().get_position(),
+            owner,
+            self.base().get_position(),
+        )
+        .with_urgency(1.0 - food as f32 / FOOD_SHORTAGE_THRESHOLD as f32);
+        godot_print!("Household short of food, requesting {} {}", FOOD_REQUEST_AMOUNT, resource);
+        logistics.bind_mut().board.post(request);
+    }
+
+    /// Asks for helpers once someone started building, one opening per builder slot besides the starter's.
+    /// The job is only posted or withdrawn when that changes, not every frame.
+    fn update_construct_job(&mut self) {
+        let site = &self.construction;
+        let openings = site.max_builders().saturating_sub(1);
+        let wants_helpers = !site.builders().is_empty() && !site.is_complete() && openings > 0;
+        if wants_helpers == self.construct_job_posted {
+            return;
+        }
+        self.construct_job_posted = wants_helpers;
+        if !wants_helpers {
+            JobOffice::singleton().bind_mut().board.withdraw(self.base().instance_id().to_i64(), JobKind::Construct);
+            return;
+        }
+        let job = Job::new(JobKind::Construct, self.base().instance_id().to_i64(), self.base().get_position())
+            .with_priority(CONSTRUCT_JOB_PRIORITY)
+            .with_wage(CONSTRUCT_JOB_WAGE)
+            .with_openings(openings);
+        JobOffice::singleton().bind_mut().board.post(job);
+    }
+
+    /// Moves everything the agent carries into the building inventory.
+    pub fn deposit_from(&mut self, inventory: &mut Inventory, agent_name: &str) {
+        let context = InventoryLedger::context(agent_name, TransactionReason::Deposit);
+        InventoryLedger::singleton().bind_mut().log.transfer_all(inventory, &mut self.inventory, &context);
+    }
+
+    /// Hands everything the agent carries to another household.
+    pub fn supply_from(&mut self, inventory: &mut Inventory, agent_name: &str) {
+        let context = InventoryLedger::context(agent_name, TransactionReason::Trade);
+        InventoryLedger::singleton().bind_mut().log.transfer_all(inventory, &mut self.inventory, &context);
+    }
+
+    pub fn food(&self) -> i32 {
+        FOOD.iter().map(|resource| self.inventory.amount(*resource)).sum()
+    }
+
+    pub fn is_short_of_food(&self) -> bool {
+        self.food() < FOOD_SHORTAGE_THRESHOLD
+    }
+}
+
 pub trait IBuilding: ISprite2D + WithBaseField {
     fn from_position(position: Vector2) -> Gd<Self> {
         Self::from_config_and_position(home_building_config(), position)
@@ -70,6 +260,8 @@ pub trait IBuilding: ISprite2D + WithBaseField {
         building.bind_mut().base_mut().set_scale(config.scale);
         building.bind_mut().base_mut().set_position(position);
         building.bind_mut().base_mut().set_z_index(0);
+        *building.bind_mut().construction_mut() =
+            ConstructionSite::new(config.build_work, config.max_builders).with_materials(&config.materials);
 
         let shader_code = "
             shader_type canvas_item;
@@ -109,5 +301,9 @@ pub trait IBuilding: ISprite2D + WithBaseField {
         }
     }
 
+    /// What the building counts as when looking for terrain to put it on
+    fn structure() -> Structure;
     fn set_completed(&mut self);
+    fn construction(&self) -> &ConstructionSite;
+    fn construction_mut(&mut self) -> &mut ConstructionSite;
 }
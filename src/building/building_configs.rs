@@ -1,9 +1,17 @@
 use godot::prelude::{Vector2, GString};
 
+use crate::resources::inventory::InventoryResource;
+
 pub struct BuildingConfig {
     pub sprite_path: GString,
     pub scale: Vector2,
     pub building_name: GString,
+    /// Builder seconds it takes to put the building up
+    pub build_work: f32,
+    /// How many agents can work on the construction site at once
+    pub max_builders: usize,
+    /// Goods that have to be brought to the site before any work is done
+    pub materials: Vec<(InventoryResource, i32)>,
 }
 
 pub fn home_building_config() -> BuildingConfig {
@@ -11,6 +19,10 @@ pub fn home_building_config() -> BuildingConfig {
         sprite_path: "res://.godot/imported/farmer_tent.png-b0a81620f2308971a68ea826e6d01872.ctex".into(),
         scale: Vector2::new(0.25, 0.25),
         building_name: "Home".into(),
+        build_work: 4.0,
+        max_builders: 3,
+        // Tents go up from what is at hand, so newcomers can always settle
+        materials: Vec::new(),
     }
 }
 
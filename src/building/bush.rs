@@ -0,0 +1,150 @@
+use godot::classes::{ISprite2D, Sprite2D};
+use godot::prelude::*;
+
+use crate::behaviour::free_space_manager::FreeSpaceManager;
+use crate::behaviour::perception::nodes_in_group;
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::transaction_log::{InventoryOwner, TransactionReason};
+
+pub const BUSH_GROUP: &str = "bushes";
+
+/// Berries a bush regrew that are not in the ledger yet. They are entered in one go when the
+/// bush is full or picked, so a bush adds an entry per harvest rather than one per berry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BerryRegrowth {
+    progress: f32,
+    grown: i32,
+}
+
+impl BerryRegrowth {
+    /// Grows one berry every `regrow_period` seconds until `room` berries are grown and returns
+    /// how many grew in this step.
+    pub fn grow(&mut self, delta: f32, regrow_period: f32, room: i32) -> i32 {
+        if self.grown >= room {
+            return 0;
+        }
+        self.progress += delta / regrow_period;
+        let before = self.grown;
+        while self.progress >= 1.0 && self.grown < room {
+            self.progress -= 1.0;
+            self.grown += 1;
+        }
+        self.grown - before
+    }
+
+    pub fn grown(&self) -> i32 {
+        self.grown
+    }
+
+    /// Hands over the grown berries to be entered in the ledger.
+    pub fn take(&mut self) -> i32 {
+        std::mem::take(&mut self.grown)
+    }
+}
+
+/// Resource node with a berry stock that regrows one berry every `regrow_period` seconds.
+#[derive(GodotClass)]
+#[class(base=Sprite2D)]
+pub struct Bush {
+    #[base]
+    base: Base<Sprite2D>,
+    #[export]
+    max_berries: i32,
+    #[export]
+    regrow_period: f32,
+    regrowth: BerryRegrowth,
+    berries: Inventory,
+}
+
+#[godot_api]
+impl ISprite2D for Bush {
+    fn init(base: Base<Sprite2D>) -> Self {
+        let owner = InventoryOwner::ResourceNode(base.to_gd().instance_id().to_i64());
+        Self {
+            base,
+            max_berries: 5,
+            regrow_period: 4.0,
+            regrowth: BerryRegrowth::default(),
+            berries: Inventory::new(owner),
+        }
+    }
+
+    fn ready(&mut self) {
+        self.base_mut().add_to_group(BUSH_GROUP);
+        let position = self.base().get_position();
+        FreeSpaceManager::singleton().bind_mut().add_occupied_position(position);
+        let max_berries = self.max_berries;
+        self.grow_berries(max_berries);
+    }
+
+    fn physics_process(&mut self, delta: f64) {
+        let room = self.max_berries - self.berries.amount(InventoryResource::Berries);
+        if self.regrowth.grow(delta as f32, self.regrow_period, room) > 0 {
+            self.update_appearance();
+        }
+        if self.regrowth.grown() > 0 && self.regrowth.grown() >= room {
+            self.record_regrowth();
+        }
+    }
+}
+
+impl Bush {
+    /// Every bush in the running scene.
+    pub fn all() -> Vec<Gd<Bush>> {
+        nodes_in_group(BUSH_GROUP)
+    }
+
+    /// The berries on the bush, owned by it as a resource node.
+    pub fn berries_mut(&mut self) -> &mut Inventory {
+        &mut self.berries
+    }
+
+    pub fn berry_count(&self) -> i32 {
+        self.berries.amount(InventoryResource::Berries) + self.regrowth.grown()
+    }
+
+    pub fn has_berries(&self) -> bool {
+        self.berry_count() > 0
+    }
+
+    /// Moves up to `amount` berries into the inventory and returns how many were gathered.
+    pub fn gather(&mut self, inventory: &mut Inventory, amount: i32, agent_name: &str) -> i32 {
+        self.record_regrowth();
+        let context = InventoryLedger::context(agent_name, TransactionReason::Harvest);
+        let gathered = InventoryLedger::singleton().bind_mut().log.transfer(
+            &mut self.berries,
+            inventory,
+            InventoryResource::Berries,
+            amount,
+            &context,
+        );
+        self.update_appearance();
+        gathered
+    }
+
+    fn record_regrowth(&mut self) {
+        let grown = self.regrowth.take();
+        if grown > 0 {
+            self.grow_berries(grown);
+        }
+    }
+
+    fn grow_berries(&mut self, amount: i32) {
+        let context = InventoryLedger::world_context(TransactionReason::Growth);
+        InventoryLedger::singleton().bind_mut().log.produce(
+            &mut self.berries,
+            InventoryResource::Berries,
+            amount,
+            &context,
+        );
+        self.update_appearance();
+    }
+
+    fn update_appearance(&mut self) {
+        // Picked bushes fade towards grey until they regrow
+        let fullness = self.berry_count() as f32 / self.max_berries.max(1) as f32;
+        let shade = 0.5 + 0.5 * fullness;
+        self.base_mut().set_self_modulate(Color::from_rgb(shade, shade, shade));
+    }
+}
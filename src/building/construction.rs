@@ -0,0 +1,107 @@
+use crate::resources::inventory::InventoryResource;
+
+/// Construction state kept on a building, so any number of agents up to `max_builders`
+/// can work on it at once, each adding work at its own rate. Work only starts once the
+/// materials the building needs are on site.
+#[derive(Clone, Debug)]
+pub struct ConstructionSite {
+    /// Builder seconds needed to finish the building
+    work_required: f32,
+    work_done: f32,
+    max_builders: usize,
+    builders: Vec<String>,
+    /// Materials still to be brought to the site
+    missing_materials: Vec<(InventoryResource, i32)>,
+}
+
+impl ConstructionSite {
+    pub fn new(work_required: f32, max_builders: usize) -> Self {
+        Self { work_required, work_done: 0.0, max_builders, builders: Vec::new(), missing_materials: Vec::new() }
+    }
+
+    pub fn with_materials(mut self, materials: &[(InventoryResource, i32)]) -> Self {
+        self.missing_materials = materials.iter().copied().filter(|(_, amount)| *amount > 0).collect();
+        self
+    }
+
+    pub fn missing_materials(&self) -> &[(InventoryResource, i32)] {
+        &self.missing_materials
+    }
+
+    pub fn has_materials(&self) -> bool {
+        self.missing_materials.is_empty()
+    }
+
+    /// Takes up to `amount` of `resource` for the building and returns how much it took.
+    pub fn deliver(&mut self, resource: InventoryResource, amount: i32) -> i32 {
+        let Some(missing) = self.missing_materials.iter_mut().find(|(missing, _)| *missing == resource) else {
+            return 0;
+        };
+        let taken = amount.clamp(0, missing.1);
+        missing.1 -= taken;
+        self.missing_materials.retain(|(_, amount)| *amount > 0);
+        taken
+    }
+
+    /// Fraction finished, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        if self.work_required <= 0.0 {
+            return 1.0;
+        }
+        (self.work_done / self.work_required).clamp(0.0, 1.0)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.work_done >= self.work_required
+    }
+
+    pub fn is_cleared(&self) -> bool {
+        self.work_done <= 0.0
+    }
+
+    pub fn builders(&self) -> &[String] {
+        &self.builders
+    }
+
+    pub fn max_builders(&self) -> usize {
+        self.max_builders
+    }
+
+    pub fn has_free_slot(&self) -> bool {
+        self.builders.len() < self.max_builders
+    }
+
+    /// Takes a builder slot. Joining twice is fine, a full site turns new builders away.
+    pub fn join(&mut self, builder: &str) -> bool {
+        if self.builders.iter().any(|existing| existing == builder) {
+            return true;
+        }
+        if !self.has_free_slot() {
+            return false;
+        }
+        self.builders.push(builder.to_string());
+        true
+    }
+
+    pub fn leave(&mut self, builder: &str) {
+        self.builders.retain(|existing| existing != builder);
+    }
+
+    /// Adds work once the materials are on site, before that builders have nothing to work with.
+    pub fn add_work(&mut self, amount: f32) {
+        if !self.has_materials() {
+            return;
+        }
+        self.work_done = (self.work_done + amount).min(self.work_required);
+    }
+
+    /// Takes the building apart again, used when deconstructing.
+    pub fn remove_work(&mut self, amount: f32) {
+        self.work_done = (self.work_done - amount).max(0.0);
+    }
+
+    pub fn complete(&mut self) {
+        self.missing_materials.clear();
+        self.work_done = self.work_required;
+    }
+}
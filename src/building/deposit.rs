@@ -0,0 +1,149 @@
+use godot::classes::{ISprite2D, Sprite2D};
+use godot::prelude::*;
+
+use super::Quarry;
+use crate::behaviour::free_space_manager::FreeSpaceManager;
+use crate::behaviour::perception::nodes_in_group;
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::transaction_log::{InventoryOwner, TransactionReason};
+
+pub const DEPOSIT_GROUP: &str = "deposits";
+
+/// Share of the full extraction rate that is left when a deposit is nearly empty.
+pub const MIN_EXTRACTION_SHARE: f32 = 0.25;
+
+/// Units per second extracted from a deposit with `remaining` of its `initial` units left.
+/// Full deposits give `base_rate`, the rate falls linearly as the deposit runs out.
+pub fn extraction_rate(base_rate: f32, remaining: i32, initial: i32) -> f32 {
+    if initial <= 0 || remaining <= 0 {
+        return 0.0;
+    }
+    let fullness = (remaining as f32 / initial as f32).min(1.0);
+    base_rate * (MIN_EXTRACTION_SHARE + (1.0 - MIN_EXTRACTION_SHARE) * fullness)
+}
+
+/// Which of the remaining `stone` and `ore` is extracted next. Picks whichever has more of its
+/// initial amount left, stone on a tie, so ore comes out evenly spread and the same way every
+/// time. None once both are gone.
+pub fn next_extracted(stone: i32, ore: i32, initial_stone: i32, initial_ore: i32) -> Option<InventoryResource> {
+    match (stone > 0, ore > 0) {
+        (false, false) => None,
+        (true, false) => Some(InventoryResource::Stone),
+        (false, true) => Some(InventoryResource::Ore),
+        (true, true) if ore * initial_stone.max(1) > stone * initial_ore.max(1) => Some(InventoryResource::Ore),
+        (true, true) => Some(InventoryResource::Stone),
+    }
+}
+
+/// Finite stone and ore in the ground. It is mined through a quarry built on top of it, and once
+/// empty it is marked exhausted, its quarry is torn down and its cell released.
+#[derive(GodotClass)]
+#[class(base=Sprite2D)]
+pub struct Deposit {
+    #[base]
+    base: Base<Sprite2D>,
+    #[export]
+    stone: i32,
+    #[export]
+    ore: i32,
+    initial_stone: i32,
+    initial_ore: i32,
+    resources: Inventory,
+    quarry: Option<Gd<Quarry>>,
+    exhausted: bool,
+}
+
+#[godot_api]
+impl ISprite2D for Deposit {
+    fn init(base: Base<Sprite2D>) -> Self {
+        let owner = InventoryOwner::ResourceNode(base.to_gd().instance_id().to_i64());
+        Self {
+            base,
+            stone: 20,
+            ore: 5,
+            initial_stone: 0,
+            initial_ore: 0,
+            resources: Inventory::new(owner),
+            quarry: None,
+            exhausted: false,
+        }
+    }
+
+    fn ready(&mut self) {
+        self.base_mut().add_to_group(DEPOSIT_GROUP);
+        let position = self.base().get_position();
+        FreeSpaceManager::singleton().bind_mut().add_occupied_position(position);
+        let context = InventoryLedger::world_context(TransactionReason::Growth);
+        let (stone, ore) = (self.stone, self.ore);
+        let mut ledger = InventoryLedger::singleton();
+        ledger.bind_mut().log.produce(&mut self.resources, InventoryResource::Stone, stone, &context);
+        ledger.bind_mut().log.produce(&mut self.resources, InventoryResource::Ore, ore, &context);
+        (self.initial_stone, self.initial_ore) = (stone, ore);
+    }
+}
+
+impl Deposit {
+    /// Every deposit in the running scene, exhausted ones included.
+    pub fn all() -> Vec<Gd<Deposit>> {
+        nodes_in_group(DEPOSIT_GROUP)
+    }
+
+    /// The stone and ore still in the ground, owned by the deposit as a resource node.
+    pub fn resources_mut(&mut self) -> &mut Inventory {
+        &mut self.resources
+    }
+
+    pub fn remaining(&self) -> i32 {
+        self.resources.amount(InventoryResource::Stone) + self.resources.amount(InventoryResource::Ore)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    pub fn has_quarry(&self) -> bool {
+        self.quarry.as_ref().is_some_and(|quarry| quarry.is_instance_valid())
+    }
+
+    /// Not exhausted and nobody has started a quarry on it.
+    pub fn is_free(&self) -> bool {
+        !self.exhausted && !self.has_quarry()
+    }
+
+    pub fn set_quarry(&mut self, quarry: Gd<Quarry>) {
+        self.quarry = Some(quarry);
+    }
+
+    pub fn extraction_rate(&self, base_rate: f32) -> f32 {
+        extraction_rate(base_rate, self.remaining(), self.initial_stone + self.initial_ore)
+    }
+
+    /// Moves one unit into the inventory, see `next_extracted` for which one.
+    pub fn extract(&mut self, inventory: &mut Inventory, agent_name: &str) -> Option<InventoryResource> {
+        let resource = next_extracted(
+            self.resources.amount(InventoryResource::Stone),
+            self.resources.amount(InventoryResource::Ore),
+            self.initial_stone,
+            self.initial_ore,
+        )?;
+        let context = InventoryLedger::context(agent_name, TransactionReason::Harvest);
+        let extracted =
+            InventoryLedger::singleton().bind_mut().log.transfer(&mut self.resources, inventory, resource, 1, &context);
+        if self.remaining() == 0 {
+            self.exhaust();
+        }
+        (extracted > 0).then_some(resource)
+    }
+
+    fn exhaust(&mut self) {
+        godot_print!("Deposit exhausted");
+        self.exhausted = true;
+        self.base_mut().set_self_modulate(Color::from_rgb(0.3, 0.3, 0.3));
+        let position = self.base().get_position();
+        FreeSpaceManager::singleton().bind_mut().remove_occupied_position(position);
+        if let Some(mut quarry) = self.quarry.take().filter(|quarry| quarry.is_instance_valid()) {
+            quarry.bind_mut().base_mut().queue_free();
+        }
+    }
+}
@@ -1,13 +1,27 @@
 use godot::prelude::*;
 use godot::{builtin::Vector2, classes::{Sprite2D, ISprite2D}};
 
-use super::{IBuilding, BuildingConfig};
+use super::{Building, IBuilding, BuildingConfig, ConstructionSite};
+use crate::behaviour::free_space_manager::FreeSpaceManager;
+use crate::behaviour::perception::nodes_in_group;
+use crate::world::job_board::{Job, JobKind};
+use crate::world::job_office::JobOffice;
+use crate::world::terrain::Structure;
+
+pub const FIELD_GROUP: &str = "fields";
+/// Harvesting ranks below helping with homes on the job board
+const HARVEST_JOB_PRIORITY: f32 = 0.5;
+/// Coins the treasury pays a farmer for harvesting someone else's field
+const HARVEST_JOB_WAGE: i32 = 1;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum FieldState {
+    /// Empty, waiting to be planted
     Seeding,
     Growing,
     Grown,
+    /// Harvested and recovering before it can be planted again
+    Fallow,
 }
 
 pub fn field_building_config() -> BuildingConfig {
@@ -15,6 +29,9 @@ pub fn field_building_config() -> BuildingConfig {
         sprite_path: "res://.godot/imported/field.png-e3ee637cd0bc190899026182c03fbba0.ctex".into(),
         scale: Vector2::new(0.15, 0.15),
         building_name: "Field".into(),
+        build_work: 1.0,
+        max_builders: 1,
+        materials: Vec::new(),
     }
 }
 
@@ -23,6 +40,9 @@ pub fn empty_field_building_config() -> BuildingConfig {
         sprite_path: "res://.godot/imported/empty_field.png-63272e1c00bbd5487b70086bc0094907.ctex".into(),
         scale: Vector2::new(0.15, 0.15),
         building_name: "Empty Field".into(),
+        build_work: 1.0,
+        max_builders: 1,
+        materials: Vec::new(),
     }
 }
 
@@ -34,20 +54,32 @@ pub struct Field {
     base: Base<Sprite2D>,
     grow_progress: f32,
     grow_duration: f32,
+    fallow_left: f32,
     pub state: FieldState,
+    construction: ConstructionSite,
+    /// Home of the farmer who laid the field out, the crop belongs there
+    owner_home: Option<Gd<Building>>,
 }
 
 #[godot_api]
 impl ISprite2D for Field {
     fn init(base: Base<Sprite2D>) -> Self {
+        let config = empty_field_building_config();
         Self {
             base,
             grow_progress: 0.0,
             grow_duration: 10.0,
+            fallow_left: 0.0,
             state: FieldState::Seeding,
+            construction: ConstructionSite::new(config.build_work, config.max_builders),
+            owner_home: None,
         }
     }
 
+    fn ready(&mut self) {
+        self.base_mut().add_to_group(FIELD_GROUP);
+    }
+
     fn physics_process(&mut self, delta: f64) {
         if self.state == FieldState::Growing {
             self.grow_progress += delta as f32/ self.grow_duration;
@@ -55,22 +87,95 @@ impl ISprite2D for Field {
                 self.grow();
             }
         }   
+        if self.state == FieldState::Fallow {
+            self.fallow_left -= delta as f32;
+            if self.fallow_left <= 0.0 {
+                godot_print!("Field recovered");
+                self.set_state(FieldState::Seeding);
+            }
+        }
     }
 }
 
 impl IBuilding for Field {
+    fn structure() -> Structure {
+        Structure::Field
+    }
     fn set_completed(&mut self) {
-        self.state = FieldState::Growing;
+        self.set_state(FieldState::Growing);
     }
     fn from_position(position: Vector2) -> Gd<Self> {
         IBuilding::from_config_and_position(empty_field_building_config(), position)
     }
+    fn construction(&self) -> &ConstructionSite {
+        &self.construction
+    }
+    fn construction_mut(&mut self) -> &mut ConstructionSite {
+        &mut self.construction
+    }
 }
 
 impl Field {
+    /// Every field in the running scene.
+    pub fn all() -> Vec<Gd<Field>> {
+        nodes_in_group(FIELD_GROUP)
+    }
+
     pub fn grow(&mut self) {
         godot_print!("Field growing");
         self.set_new_config(field_building_config());
-        self.state = FieldState::Grown;
+        self.set_state(FieldState::Grown);
+    }
+
+    pub fn owner_home(&self) -> Option<Gd<Building>> {
+        self.owner_home.clone().filter(|home| home.is_instance_valid())
+    }
+
+    pub fn set_owner_home(&mut self, home: Option<Gd<Building>>) {
+        self.owner_home = home;
+    }
+
+    /// Empty and nobody is planting it, so it can be planted again.
+    pub fn is_replantable(&self) -> bool {
+        self.state == FieldState::Seeding && self.construction.builders().is_empty()
+    }
+
+    /// Takes the crop off and leaves the field empty, lying fallow for `fallow_duration` seconds first.
+    pub fn harvest(&mut self, fallow_duration: f32) {
+        self.set_new_config(empty_field_building_config());
+        self.build(1.0);
+        self.grow_progress = 0.0;
+        if fallow_duration > 0.0 {
+            self.fallow_left = fallow_duration;
+            self.set_state(FieldState::Fallow);
+        } else {
+            self.set_state(FieldState::Seeding);
+        }
+    }
+
+    /// Removes the field from the map for good and frees its cell.
+    pub fn demolish(&mut self) {
+        self.withdraw_harvest_job();
+        let position = self.base().get_position();
+        FreeSpaceManager::singleton().bind_mut().remove_occupied_position(position);
+        self.base_mut().queue_free();
+    }
+
+    /// Puts the harvest job up when the field becomes grown and takes it down when it stops being grown.
+    fn set_state(&mut self, state: FieldState) {
+        let was_grown = self.state == FieldState::Grown;
+        self.state = state;
+        if !was_grown && state == FieldState::Grown {
+            let job = Job::new(JobKind::Harvest, self.base().instance_id().to_i64(), self.base().get_position())
+                .with_priority(HARVEST_JOB_PRIORITY)
+                .with_wage(HARVEST_JOB_WAGE);
+            JobOffice::singleton().bind_mut().board.post(job);
+        } else if was_grown && state != FieldState::Grown {
+            self.withdraw_harvest_job();
+        }
+    }
+
+    fn withdraw_harvest_job(&mut self) {
+        JobOffice::singleton().bind_mut().board.withdraw(self.base().instance_id().to_i64(), JobKind::Harvest);
     }
 }
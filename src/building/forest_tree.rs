@@ -0,0 +1,185 @@
+use godot::classes::{ISprite2D, Sprite2D};
+use godot::prelude::*;
+
+use crate::behaviour::free_space_manager::FreeSpaceManager;
+use crate::behaviour::perception::nodes_in_group;
+use crate::resources::inventory::{Inventory, InventoryResource};
+use crate::resources::inventory_ledger::InventoryLedger;
+use crate::resources::transaction_log::{InventoryOwner, TransactionReason};
+
+pub const FOREST_GROUP: &str = "forest";
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ForestTreeState {
+    Sapling,
+    Mature,
+}
+
+/// Agent working on a resource node, so others look for another one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reservation {
+    holder: Option<String>,
+}
+
+impl Reservation {
+    pub fn is_free_for(&self, agent_name: &str) -> bool {
+        self.holder.as_deref().is_none_or(|holder| holder == agent_name)
+    }
+
+    /// Takes the node for `agent_name` unless someone else has it already.
+    pub fn reserve(&mut self, agent_name: &str) -> bool {
+        if !self.is_free_for(agent_name) {
+            return false;
+        }
+        self.holder = Some(agent_name.to_string());
+        true
+    }
+
+    /// Frees the node again if `agent_name` holds it.
+    pub fn release(&mut self, agent_name: &str) {
+        if self.holder.as_deref() == Some(agent_name) {
+            self.holder = None;
+        }
+    }
+}
+
+/// Tree that can be felled for wood. A felled tree is either gone for good, freeing its cell,
+/// or replanted as a sapling that grows back to a mature tree over `grow_duration` seconds.
+#[derive(GodotClass)]
+#[class(base=Sprite2D)]
+pub struct ForestTree {
+    #[base]
+    base: Base<Sprite2D>,
+    #[export]
+    wood_yield: i32,
+    #[export]
+    grow_duration: f32,
+    grow_progress: f32,
+    full_scale: Vector2,
+    state: ForestTreeState,
+    wood: Inventory,
+    reservation: Reservation,
+}
+
+#[godot_api]
+impl ISprite2D for ForestTree {
+    fn init(base: Base<Sprite2D>) -> Self {
+        let owner = InventoryOwner::ResourceNode(base.to_gd().instance_id().to_i64());
+        Self {
+            base,
+            wood_yield: 3,
+            grow_duration: 40.0,
+            grow_progress: 0.0,
+            full_scale: Vector2::ONE,
+            state: ForestTreeState::Sapling,
+            wood: Inventory::new(owner),
+            reservation: Reservation::default(),
+        }
+    }
+
+    fn ready(&mut self) {
+        self.base_mut().add_to_group(FOREST_GROUP);
+        let position = self.base().get_position();
+        FreeSpaceManager::singleton().bind_mut().add_occupied_position(position);
+        self.full_scale = self.base().get_scale();
+        // Trees placed in the scene start out fully grown
+        self.mature();
+    }
+
+    fn physics_process(&mut self, delta: f64) {
+        if self.state != ForestTreeState::Sapling {
+            return;
+        }
+        self.grow_progress += delta as f32 / self.grow_duration;
+        if self.grow_progress >= 1.0 {
+            self.mature();
+        } else {
+            self.update_appearance();
+        }
+    }
+}
+
+impl ForestTree {
+    /// Every tree in the running scene, saplings included.
+    pub fn all() -> Vec<Gd<ForestTree>> {
+        nodes_in_group(FOREST_GROUP)
+    }
+
+    pub fn state(&self) -> ForestTreeState {
+        self.state
+    }
+
+    /// The wood still standing, owned by the tree as a resource node.
+    pub fn wood_mut(&mut self) -> &mut Inventory {
+        &mut self.wood
+    }
+
+    pub fn wood_count(&self) -> i32 {
+        self.wood.amount(InventoryResource::Wood)
+    }
+
+    /// Reservation of the woodcutter felling or replanting the tree.
+    pub fn reservation_mut(&mut self) -> &mut Reservation {
+        &mut self.reservation
+    }
+
+    pub fn is_free_for(&self, agent_name: &str) -> bool {
+        self.reservation.is_free_for(agent_name)
+    }
+
+    pub fn can_be_felled(&self) -> bool {
+        self.state == ForestTreeState::Mature && self.wood_count() > 0
+    }
+
+    /// Moves up to `amount` wood into the inventory and returns how much was cut. The tree is down
+    /// once its wood is gone, see `replant` and `clear`.
+    pub fn fell(&mut self, inventory: &mut Inventory, amount: i32, agent_name: &str) -> i32 {
+        let context = InventoryLedger::context(agent_name, TransactionReason::Harvest);
+        InventoryLedger::singleton().bind_mut().log.transfer(
+            &mut self.wood,
+            inventory,
+            InventoryResource::Wood,
+            amount,
+            &context,
+        )
+    }
+
+    pub fn is_felled(&self) -> bool {
+        self.state == ForestTreeState::Mature && self.wood_count() == 0
+    }
+
+    /// Puts a sapling where the felled tree stood, keeping the cell.
+    pub fn replant(&mut self) {
+        self.state = ForestTreeState::Sapling;
+        self.grow_progress = 0.0;
+        self.update_appearance();
+    }
+
+    /// Removes the felled tree and frees its cell for other use.
+    pub fn clear(&mut self) {
+        let position = self.base().get_position();
+        FreeSpaceManager::singleton().bind_mut().remove_occupied_position(position);
+        self.base_mut().queue_free();
+    }
+
+    fn mature(&mut self) {
+        self.state = ForestTreeState::Mature;
+        self.grow_progress = 1.0;
+        let context = InventoryLedger::world_context(TransactionReason::Growth);
+        let wood_yield = self.wood_yield;
+        InventoryLedger::singleton().bind_mut().log.produce(
+            &mut self.wood,
+            InventoryResource::Wood,
+            wood_yield,
+            &context,
+        );
+        self.update_appearance();
+    }
+
+    fn update_appearance(&mut self) {
+        // Saplings start at a quarter of the full size
+        let size = 0.25 + 0.75 * self.grow_progress.clamp(0.0, 1.0);
+        let scale = self.full_scale * size;
+        self.base_mut().set_scale(scale);
+    }
+}
@@ -1,6 +1,21 @@
 mod building_configs;
 pub use building_configs::*; 
+#[allow(clippy::module_inception)]
 mod building;
 pub use building::*;
 mod field;
-pub use field::*;
\ No newline at end of file
+pub use field::*;
+// The exported properties of resource nodes are registered through closures returning godot's large CallError
+#[allow(clippy::result_large_err)]
+mod bush;
+pub use bush::*;
+#[allow(clippy::result_large_err)]
+mod forest_tree;
+pub use forest_tree::*;
+#[allow(clippy::result_large_err)]
+mod deposit;
+pub use deposit::*;
+mod quarry;
+pub use quarry::*;
+mod construction;
+pub use construction::*;
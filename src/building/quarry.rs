@@ -0,0 +1,66 @@
+use godot::classes::{ISprite2D, Sprite2D};
+use godot::prelude::*;
+
+use crate::resources::inventory::InventoryResource;
+
+use super::{BuildingConfig, ConstructionSite, IBuilding};
+use crate::world::terrain::Structure;
+
+pub fn quarry_building_config() -> BuildingConfig {
+    BuildingConfig {
+        sprite_path: "res://.godot/imported/farmer_tent.png-b0a81620f2308971a68ea826e6d01872.ctex".into(),
+        scale: Vector2::new(0.2, 0.2),
+        building_name: "Quarry".into(),
+        build_work: 6.0,
+        max_builders: 2,
+        // No stone, it only comes out of quarries
+        materials: vec![(InventoryResource::Wood, 4)],
+    }
+}
+
+/// Building put up on a deposit, miners extract stone and ore through it once it is finished.
+#[derive(GodotClass)]
+#[class(base=Sprite2D)]
+pub struct Quarry {
+    #[base]
+    base: Base<Sprite2D>,
+    completed: bool,
+    construction: ConstructionSite,
+}
+
+#[godot_api]
+impl ISprite2D for Quarry {
+    fn init(base: Base<Sprite2D>) -> Self {
+        let config = quarry_building_config();
+        Self { base, completed: false, construction: ConstructionSite::new(config.build_work, config.max_builders) }
+    }
+
+    fn ready(&mut self) {
+        // Shares the tent sprite, the grey tint sets it apart
+        self.base_mut().set_self_modulate(Color::from_rgb(0.6, 0.6, 0.65));
+    }
+}
+
+impl IBuilding for Quarry {
+    fn structure() -> Structure {
+        Structure::Quarry
+    }
+    fn from_position(position: Vector2) -> Gd<Self> {
+        IBuilding::from_config_and_position(quarry_building_config(), position)
+    }
+    fn set_completed(&mut self) {
+        self.completed = true;
+    }
+    fn construction(&self) -> &ConstructionSite {
+        &self.construction
+    }
+    fn construction_mut(&mut self) -> &mut ConstructionSite {
+        &mut self.construction
+    }
+}
+
+impl Quarry {
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+}
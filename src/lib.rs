@@ -5,8 +5,15 @@ pub mod agent;
 pub mod behaviour;
 pub mod building;
 pub mod resources;
+pub mod world;
 
 use behaviour::free_space_manager::FreeSpaceManager;
+use resources::inventory_ledger::InventoryLedger;
+use resources::labour_exchange::LabourExchange;
+use resources::logistics::Logistics;
+use world::job_office::JobOffice;
+use world::world_clock::WorldClock;
+use resources::treasury::{Treasury, STARTING_TREASURY_COINS};
 
 struct MyExtension;
 
@@ -22,6 +29,49 @@ unsafe impl ExtensionLibrary for MyExtension {
                     &StringName::from("FreeSpaceManager"),
                     &singleton.upcast::<Object>()
                 );
+            godot_print!("Registering InventoryLedger singleton");
+            let ledger = InventoryLedger::new_alloc();
+            Engine::singleton()
+                .register_singleton(
+                    &StringName::from("InventoryLedger"),
+                    &ledger.upcast::<Object>()
+                );
+            godot_print!("Registering Treasury singleton");
+            let mut treasury = Treasury::new_alloc();
+            treasury.bind_mut().fund(STARTING_TREASURY_COINS);
+            Engine::singleton()
+                .register_singleton(
+                    &StringName::from("Treasury"),
+                    &treasury.upcast::<Object>()
+                );
+            godot_print!("Registering LabourExchange singleton");
+            let labour_exchange = LabourExchange::new_alloc();
+            Engine::singleton()
+                .register_singleton(
+                    &StringName::from("LabourExchange"),
+                    &labour_exchange.upcast::<Object>()
+                );
+            godot_print!("Registering Logistics singleton");
+            let logistics = Logistics::new_alloc();
+            Engine::singleton()
+                .register_singleton(
+                    &StringName::from("Logistics"),
+                    &logistics.upcast::<Object>()
+                );
+            godot_print!("Registering JobOffice singleton");
+            let job_office = JobOffice::new_alloc();
+            Engine::singleton()
+                .register_singleton(
+                    &StringName::from("JobOffice"),
+                    &job_office.upcast::<Object>()
+                );
+            godot_print!("Registering WorldClock singleton");
+            let world_clock = WorldClock::new_alloc();
+            Engine::singleton()
+                .register_singleton(
+                    &StringName::from("WorldClock"),
+                    &world_clock.upcast::<Object>()
+                );
         }
     }
 
@@ -31,6 +81,24 @@ unsafe impl ExtensionLibrary for MyExtension {
             godot_print!("Unregistering FreeSpaceManager singleton");
             Engine::singleton()
                 .unregister_singleton(&StringName::from("FreeSpaceManager"));
+            godot_print!("Unregistering InventoryLedger singleton");
+            Engine::singleton()
+                .unregister_singleton(&StringName::from("InventoryLedger"));
+            godot_print!("Unregistering Treasury singleton");
+            Engine::singleton()
+                .unregister_singleton(&StringName::from("Treasury"));
+            godot_print!("Unregistering LabourExchange singleton");
+            Engine::singleton()
+                .unregister_singleton(&StringName::from("LabourExchange"));
+            godot_print!("Unregistering Logistics singleton");
+            Engine::singleton()
+                .unregister_singleton(&StringName::from("Logistics"));
+            godot_print!("Unregistering JobOffice singleton");
+            Engine::singleton()
+                .unregister_singleton(&StringName::from("JobOffice"));
+            godot_print!("Unregistering WorldClock singleton");
+            Engine::singleton()
+                .unregister_singleton(&StringName::from("WorldClock"));
         }
     }
 }
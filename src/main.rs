@@ -1,5 +1,27 @@
-use market_and_mastery::hello;
+use std::env;
+use std::process;
 
+use market_and_mastery::world::map_generation::{generate_map, map_generator_config, random_seed};
+
+/// Prints the world a seed generates, `market_and_mastery_bin [seed]`. Without a seed a new
+/// world is picked.
 fn main() {
-    hello();
-}
\ No newline at end of file
+    let seed = match env::args().nth(1) {
+        Some(seed) => seed.parse().unwrap_or_else(|_| {
+            eprintln!("Seed must be a whole number, got {}", seed);
+            eprintln!("Usage: market_and_mastery_bin [seed]");
+            process::exit(2);
+        }),
+        None => random_seed(),
+    };
+    let map = generate_map(seed, &map_generator_config());
+    println!("Seed {}", map.seed);
+    print!("{}", map.to_ascii());
+    println!(
+        "{} trees, {} bushes, {} deposits, {} spawn points",
+        map.trees.len(),
+        map.bushes.len(),
+        map.deposits.len(),
+        map.spawn_points.len()
+    );
+}
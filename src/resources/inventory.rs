@@ -2,45 +2,102 @@ use std::{collections::HashMap, fmt::Display};
 
 use godot::prelude::GodotClass;
 
+use super::transaction_log::InventoryOwner;
+
 #[derive(Eq, Hash, PartialEq, Copy, Clone, Debug)]
 pub enum InventoryResource {
     Wheat,
+    Berries,
+    Coins,
+    Wood,
+    Stone,
+    Ore,
 }
 
 impl Display for InventoryResource {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             InventoryResource::Wheat => write!(f, "Wheat"),
+            InventoryResource::Berries => write!(f, "Berries"),
+            InventoryResource::Coins => write!(f, "Coins"),
+            InventoryResource::Wood => write!(f, "Wood"),
+            InventoryResource::Stone => write!(f, "Stone"),
+            InventoryResource::Ore => write!(f, "Ore"),
         }
     }
 }
 
-#[derive(GodotClass)]
+#[derive(GodotClass, Default)]
 #[class(init)]
 pub struct Inventory {
-    pub items: HashMap<InventoryResource, i32>,
+    items: HashMap<InventoryResource, i32>,
+    capacity: Option<i32>,
+    owner: InventoryOwner,
 }
 
 impl Display for Inventory {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Inventory:\n")?;
+        writeln!(f, "Inventory:")?;
         for (resource, amount) in &self.items {
-            write!(f, "{}: {}\n", resource, amount)?;
+            writeln!(f, "{}: {}", resource, amount)?;
         }
         Ok(())
     }
 }
 impl Inventory {
-    pub fn new() -> Self {
-        Self {
-            items: HashMap::new(),
+    pub fn new(owner: InventoryOwner) -> Self {
+        Self { items: HashMap::new(), capacity: None, owner }
+    }
+
+    pub fn with_capacity(owner: InventoryOwner, capacity: i32) -> Self {
+        Self { items: HashMap::new(), capacity: Some(capacity), owner }
+    }
+
+    pub fn owner(&self) -> &InventoryOwner {
+        &self.owner
+    }
+
+    pub fn set_owner(&mut self, owner: InventoryOwner) {
+        self.owner = owner;
+    }
+
+    pub fn resources(&self) -> Vec<(InventoryResource, i32)> {
+        self.items.iter().map(|(resource, amount)| (*resource, *amount)).collect()
+    }
+
+    pub fn total(&self) -> i32 {
+        self.items.values().sum()
+    }
+
+    pub fn amount(&self, resource: InventoryResource) -> i32 {
+        self.items.get(&resource).copied().unwrap_or(0)
+    }
+
+    pub fn free_space(&self) -> i32 {
+        match self.capacity {
+            Some(capacity) => (capacity - self.total()).max(0),
+            None => i32::MAX,
         }
     }
-    pub fn add(&mut self, resource: InventoryResource, amount: i32) {
-        *self.items.entry(resource).or_insert(0) += amount;
+
+    pub fn is_full(&self) -> bool {
+        self.free_space() == 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// Adds as much as fits into the remaining capacity and returns the added amount.
+    pub(super) fn add(&mut self, resource: InventoryResource, amount: i32) -> i32 {
+        let added = amount.min(self.free_space());
+        if added > 0 {
+            *self.items.entry(resource).or_insert(0) += added;
+        }
+        added
     }
 
-    pub fn remove(&mut self, resource: InventoryResource, amount: i32) -> i32 {
+    pub(super) fn remove(&mut self, resource: InventoryResource, amount: i32) -> i32 {
         let inventory_amount = self.items.entry(resource).or_insert(0);
         let current_amount = *inventory_amount;
         if current_amount >= amount {
@@ -48,13 +105,6 @@ impl Inventory {
             return amount;
         }
         self.items.remove(&resource);
-        return current_amount;
-    }
-
-    pub fn move_full_inventory_from(&mut self, other: &mut Inventory) {
-        for (resource, amount) in other.items.iter_mut() {
-            self.add(*resource, *amount);
-        }
-        other.items.clear();
+        current_amount
     }
 }
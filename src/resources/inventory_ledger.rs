@@ -0,0 +1,65 @@
+use godot::classes::Engine;
+use godot::prelude::*;
+
+use super::transaction_log::{TransactionContext, TransactionLog, TransactionReason};
+use crate::world::world_clock::WorldClock;
+
+/// Engine-wide owner of the transaction log, registered as the `InventoryLedger` singleton.
+#[derive(GodotClass)]
+#[class(base=Object)]
+pub struct InventoryLedger {
+    #[base]
+    base: Base<Object>,
+    pub log: TransactionLog,
+}
+
+#[godot_api]
+impl IObject for InventoryLedger {
+    fn init(base: Base<Object>) -> Self {
+        Self { base, log: TransactionLog::new() }
+    }
+}
+
+#[godot_api]
+impl InventoryLedger {
+    pub fn singleton() -> Gd<InventoryLedger> {
+        Engine::singleton()
+            .get_singleton(&StringName::from("InventoryLedger"))
+            .expect("InventoryLedger singleton not found")
+            .try_cast::<InventoryLedger>()
+            .unwrap()
+    }
+
+    /// Context stamped with the current physics tick.
+    pub fn context(agent_name: &str, reason: TransactionReason) -> TransactionContext {
+        TransactionContext {
+            agent: Some(agent_name.to_string()),
+            tick: WorldClock::tick(),
+            reason,
+        }
+    }
+
+    /// Context for changes no agent caused, like regrowth.
+    pub fn world_context(reason: TransactionReason) -> TransactionContext {
+        TransactionContext { agent: None, tick: WorldClock::tick(), reason }
+    }
+
+    #[func]
+    fn get_transaction_count(&self) -> i64 {
+        self.log.entries().len() as i64
+    }
+
+    #[func]
+    fn describe_agent_transactions(&self, agent_name: GString) -> GString {
+        let lines: Vec<String> =
+            self.log.entries_for_agent(&agent_name.to_string()).iter().map(|entry| format!("{:?}", entry)).collect();
+        lines.join("\n").into()
+    }
+
+    #[func]
+    fn describe_building_transactions(&self, building_id: i64) -> GString {
+        let lines: Vec<String> =
+            self.log.entries_for_building(building_id).iter().map(|entry| format!("{:?}", entry)).collect();
+        lines.join("\n").into()
+    }
+}
@@ -0,0 +1,38 @@
+use godot::classes::Engine;
+use godot::prelude::*;
+
+use super::labour_market::LabourMarket;
+use crate::world::world_clock::WorldClock;
+
+/// Engine-wide owner of the labour market, registered as the `LabourExchange` singleton.
+#[derive(GodotClass)]
+#[class(base=Object)]
+pub struct LabourExchange {
+    #[base]
+    base: Base<Object>,
+    pub market: LabourMarket,
+}
+
+#[godot_api]
+impl IObject for LabourExchange {
+    fn init(base: Base<Object>) -> Self {
+        Self { base, market: LabourMarket::new() }
+    }
+}
+
+#[godot_api]
+impl LabourExchange {
+    pub fn singleton() -> Gd<LabourExchange> {
+        Engine::singleton()
+            .get_singleton(&StringName::from("LabourExchange"))
+            .expect("LabourExchange singleton not found")
+            .try_cast::<LabourExchange>()
+            .unwrap()
+    }
+
+    /// Market wage of `job` over the last `window` seconds, -1 if nobody did that job.
+    #[func]
+    fn get_market_wage(&self, job: GString, window: f64) -> f32 {
+        self.market.market_wage(&job.to_string(), WorldClock::seconds(), window).unwrap_or(-1.0)
+    }
+}
@@ -0,0 +1,72 @@
+/// Income an agent made in one round of a job.
+#[derive(PartialEq, Clone, Debug)]
+pub struct WageSample {
+    pub job: &'static str,
+    pub agent: String,
+    /// Value of everything the round brought home, in coins
+    pub income: f32,
+    /// Seconds the round took
+    pub duration: f32,
+    /// Seconds since the game started when the round ended
+    pub time: f64,
+}
+
+/// Record of what every job paid recently, agents compare their own income against it.
+#[derive(Default)]
+pub struct LabourMarket {
+    samples: Vec<WageSample>,
+}
+
+impl LabourMarket {
+    pub fn new() -> Self {
+        Self { samples: Vec::new() }
+    }
+
+    pub fn record(&mut self, sample: WageSample) {
+        self.samples.push(sample);
+    }
+
+    /// Drops samples that no window reaches anymore.
+    pub fn forget_before(&mut self, time: f64) {
+        self.samples.retain(|sample| sample.time >= time);
+    }
+
+    /// Income per second of everybody doing `job` within the last `window` seconds.
+    pub fn market_wage(&self, job: &str, now: f64, window: f64) -> Option<f32> {
+        wage(self.samples.iter().filter(|sample| sample.job == job && sample.time >= now - window))
+    }
+
+    /// Income per second of `agent` doing `job` within the last `window` seconds.
+    pub fn agent_wage(&self, agent: &str, job: &str, now: f64, window: f64) -> Option<f32> {
+        wage(
+            self.samples
+                .iter()
+                .filter(|sample| sample.agent == agent && sample.job == job && sample.time >= now - window),
+        )
+    }
+}
+
+fn wage<'a>(samples: impl Iterator<Item = &'a WageSample>) -> Option<f32> {
+    let (income, duration) =
+        samples.fold((0.0, 0.0), |(income, duration), sample| (income + sample.income, duration + sample.duration));
+    (duration > 0.0).then(|| income / duration)
+}
+
+/// The profession paying best if it beats `own_wage` by more than `switch_threshold`,
+/// e.g. 0.25 for a 25% raise. `retraining_cost` is what learning the new profession costs per
+/// second worked in it and is taken off its wage first. Professions without a known wage are not
+/// considered, and without a known own wage there is nothing to compare against.
+pub fn pick_better_profession(
+    own_wage: Option<f32>,
+    market_wages: &[(&'static str, Option<f32>)],
+    switch_threshold: f32,
+    retraining_cost: f32,
+) -> Option<&'static str> {
+    let own_wage = own_wage?;
+    market_wages
+        .iter()
+        .filter_map(|(job, wage)| wage.map(|wage| (*job, wage - retraining_cost)))
+        .filter(|(_, wage)| *wage > own_wage * (1.0 + switch_threshold) && *wage > 0.0)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(job, _)| job)
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use godot::classes::Engine;
+use godot::prelude::*;
+
+use super::inventory::{Inventory, InventoryResource};
+use super::inventory_ledger::InventoryLedger;
+use super::transaction_log::{InventoryOwner, TransactionReason};
+use super::transport::{TransportBoard, TransportRequest};
+use crate::building::Building;
+
+/// Below food for a household running out, above goods merely waiting at a worksite
+const MATERIAL_URGENCY: f32 = 0.5;
+
+/// Engine-wide owner of the transport board and of the stockpiles producers leave at their
+/// worksites, registered as the `Logistics` singleton.
+#[derive(GodotClass)]
+#[class(base=Object)]
+pub struct Logistics {
+    #[base]
+    base: Base<Object>,
+    pub board: TransportBoard,
+    /// Goods waiting for a hauler, by worksite node. They outlive the worksite, e.g. a cleared tree.
+    stockpiles: HashMap<i64, Inventory>,
+}
+
+#[godot_api]
+impl IObject for Logistics {
+    fn init(base: Base<Object>) -> Self {
+        Self { base, board: TransportBoard::new(), stockpiles: HashMap::new() }
+    }
+}
+
+#[godot_api]
+impl Logistics {
+    pub fn singleton() -> Gd<Logistics> {
+        Engine::singleton()
+            .get_singleton(&StringName::from("Logistics"))
+            .expect("Logistics singleton not found")
+            .try_cast::<Logistics>()
+            .unwrap()
+    }
+
+    pub fn stockpile_mut(&mut self, site: i64) -> &mut Inventory {
+        self.stockpiles.entry(site).or_insert_with(|| Inventory::new(InventoryOwner::Stockpile(site)))
+    }
+
+    /// Sets everything in `inventory` down at the worksite `site` and posts a request per resource
+    /// to carry it to `destination`. Refused while goods set down there earlier are still waiting
+    /// for a hauler, the producer then carries its goods itself.
+    pub fn set_down(
+        &mut self,
+        inventory: &mut Inventory,
+        site: i64,
+        site_position: Vector2,
+        destination: InventoryOwner,
+        destination_position: Vector2,
+        agent_name: &str,
+    ) -> bool {
+        let source = InventoryOwner::Stockpile(site);
+        if inventory.is_empty() || self.board.has_open_request_from(&source) {
+            return false;
+        }
+        let context = InventoryLedger::context(agent_name, TransactionReason::Deposit);
+        let mut ledger = InventoryLedger::singleton();
+        let stockpile = self.stockpiles.entry(site).or_insert_with(|| Inventory::new(source.clone()));
+        for (resource, amount) in inventory.resources() {
+            let moved = ledger.bind_mut().log.transfer(inventory, stockpile, resource, amount, &context);
+            if moved == 0 {
+                continue;
+            }
+            let request = TransportRequest::new(
+                resource,
+                moved,
+                source.clone(),
+                site_position,
+                destination.clone(),
+                destination_position,
+            );
+            self.board.post(request);
+        }
+        true
+    }
+
+    /// Posts a request per material `missing` at the construction site `site` that is not on its
+    /// way yet, from the household with the most of it in stock.
+    pub fn request_materials(
+        &mut self,
+        site: InventoryOwner,
+        site_position: Vector2,
+        missing: &[(InventoryResource, i32)],
+    ) {
+        for (resource, amount) in missing {
+            let ordered = self
+                .board
+                .requests()
+                .iter()
+                .any(|request| request.destination == site && request.resource == *resource);
+            if ordered {
+                continue;
+            }
+            let supplier = Building::all()
+                .into_iter()
+                .map(|building| {
+                    let stock = building.bind().inventory.amount(*resource);
+                    (stock, building)
+                })
+                .filter(|(stock, _)| *stock > 0)
+                .max_by_key(|(stock, _)| *stock);
+            let Some((stock, supplier)) = supplier else {
+                continue;
+            };
+            let supplier = supplier.bind();
+            let request = TransportRequest::new(
+                *resource,
+                (*amount).min(stock),
+                supplier.inventory.owner().clone(),
+                supplier.base().get_position(),
+                site.clone(),
+                site_position,
+            )
+            .with_urgency(MATERIAL_URGENCY);
+            godot_print!("Construction site requesting {} {}", amount, resource);
+            self.board.post(request);
+        }
+    }
+
+    /// Requests no hauler has claimed yet.
+    #[func]
+    fn get_open_request_count(&self) -> i64 {
+        self.board.open_requests().count() as i64
+    }
+}
@@ -1 +1,14 @@
 pub mod inventory;
+// godot_api registers #[func]s with arguments through closures returning godot's large CallError
+#[allow(clippy::result_large_err)]
+pub mod inventory_ledger;
+pub mod transaction_log;
+#[allow(clippy::result_large_err)]
+pub mod treasury;
+pub mod prices;
+pub mod labour_market;
+#[allow(clippy::result_large_err)]
+pub mod labour_exchange;
+pub mod transport;
+#[allow(clippy::result_large_err)]
+pub mod logistics;
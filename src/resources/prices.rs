@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+use super::inventory::InventoryResource;
+
+/// What goods are worth in coins. Agents compare jobs by the value of what they produce.
+#[derive(Clone, Debug)]
+pub struct Prices {
+    prices: HashMap<InventoryResource, f32>,
+}
+
+impl Default for Prices {
+    fn default() -> Self {
+        let mut prices = HashMap::new();
+        prices.insert(InventoryResource::Wheat, 2.0);
+        prices.insert(InventoryResource::Berries, 1.0);
+        prices.insert(InventoryResource::Coins, 1.0);
+        prices.insert(InventoryResource::Wood, 1.5);
+        prices.insert(InventoryResource::Stone, 2.0);
+        prices.insert(InventoryResource::Ore, 4.0);
+        Self { prices }
+    }
+}
+
+impl Prices {
+    pub fn price(&self, resource: InventoryResource) -> f32 {
+        self.prices.get(&resource).copied().unwrap_or(0.0)
+    }
+
+    pub fn set_price(&mut self, resource: InventoryResource, price: f32) {
+        self.prices.insert(resource, price);
+    }
+}
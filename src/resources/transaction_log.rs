@@ -0,0 +1,172 @@
+use super::inventory::{Inventory, InventoryResource};
+
+/// Who holds an inventory. `World` is the source of produced goods and the sink of consumed ones.
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Default)]
+pub enum InventoryOwner {
+    #[default]
+    World,
+    Building(i64),
+    /// Bushes and other nodes goods are gathered from
+    ResourceNode(i64),
+    /// Goods a producer set down at its worksite for a hauler, keyed by the worksite node
+    Stockpile(i64),
+    /// Construction site materials are brought to, keyed by the building node
+    Construction(i64),
+    Agent(String),
+    /// The town purse guards and other public workers are paid from
+    Treasury,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TransactionReason {
+    Harvest,
+    Growth,
+    Deposit,
+    Trade,
+    Spoilage,
+    /// Payment for a service, like guarding
+    Wage,
+    /// Money put into the treasury from outside the simulation
+    Funding,
+    /// Goods carried between inventories by a hauler
+    Transport,
+    /// Materials built into a construction site
+    Construction,
+}
+
+#[derive(Clone, Debug)]
+pub struct TransactionContext {
+    pub agent: Option<String>,
+    pub tick: u64,
+    pub reason: TransactionReason,
+}
+
+#[derive(Clone, Debug)]
+pub struct InventoryTransaction {
+    pub source: InventoryOwner,
+    pub destination: InventoryOwner,
+    pub agent: Option<String>,
+    pub tick: u64,
+    pub reason: TransactionReason,
+    pub resource: InventoryResource,
+    pub amount: i32,
+}
+
+/// Append-only log of every inventory change. Inventories can only be changed through it.
+#[derive(Default)]
+pub struct TransactionLog {
+    entries: Vec<InventoryTransaction>,
+}
+
+impl TransactionLog {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    pub fn entries(&self) -> &[InventoryTransaction] {
+        &self.entries
+    }
+
+    /// Adds newly created goods, e.g. a harvest, to `destination`. Returns the amount that fit.
+    pub fn produce(
+        &mut self,
+        destination: &mut Inventory,
+        resource: InventoryResource,
+        amount: i32,
+        context: &TransactionContext,
+    ) -> i32 {
+        let added = destination.add(resource, amount);
+        self.record(InventoryOwner::World, destination.owner().clone(), resource, added, context);
+        added
+    }
+
+    /// Removes goods from the economy, e.g. spoilage. Returns the amount that was removed.
+    pub fn consume(
+        &mut self,
+        source: &mut Inventory,
+        resource: InventoryResource,
+        amount: i32,
+        context: &TransactionContext,
+    ) -> i32 {
+        let removed = source.remove(resource, amount);
+        self.record(source.owner().clone(), InventoryOwner::World, resource, removed, context);
+        removed
+    }
+
+    /// Moves up to `amount` from `source` to `destination`. Returns the amount that was moved.
+    pub fn transfer(
+        &mut self,
+        source: &mut Inventory,
+        destination: &mut Inventory,
+        resource: InventoryResource,
+        amount: i32,
+        context: &TransactionContext,
+    ) -> i32 {
+        let moved = source.remove(resource, amount.min(destination.free_space()));
+        let added = destination.add(resource, moved);
+        self.record(source.owner().clone(), destination.owner().clone(), resource, added, context);
+        added
+    }
+
+    /// Moves everything that fits from `source` to `destination`.
+    pub fn transfer_all(&mut self, source: &mut Inventory, destination: &mut Inventory, context: &TransactionContext) {
+        for (resource, amount) in source.resources() {
+            self.transfer(source, destination, resource, amount, context);
+        }
+    }
+
+    pub fn entries_for_owner<'a, 'b>(
+        &'a self,
+        owner: &'b InventoryOwner,
+    ) -> impl Iterator<Item = &'a InventoryTransaction> + 'b
+    where
+        'a: 'b,
+    {
+        self.entries.iter().filter(move |entry| &entry.source == owner || &entry.destination == owner)
+    }
+
+    pub fn entries_for_building(&self, building_id: i64) -> Vec<&InventoryTransaction> {
+        self.entries_for_owner(&InventoryOwner::Building(building_id)).collect()
+    }
+
+    /// Entries the agent performed or that touched the agent's own inventory.
+    pub fn entries_for_agent(&self, agent_name: &str) -> Vec<&InventoryTransaction> {
+        let owner = InventoryOwner::Agent(agent_name.to_string());
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.agent.as_deref() == Some(agent_name) || entry.source == owner || entry.destination == owner
+            })
+            .collect()
+    }
+
+    /// Net amount of `resource` the log says `owner` should hold.
+    pub fn balance(&self, owner: &InventoryOwner, resource: InventoryResource) -> i32 {
+        self.entries_for_owner(owner)
+            .filter(|entry| entry.resource == resource && entry.source != entry.destination)
+            .map(|entry| if &entry.destination == owner { entry.amount } else { -entry.amount })
+            .sum()
+    }
+
+    fn record(
+        &mut self,
+        source: InventoryOwner,
+        destination: InventoryOwner,
+        resource: InventoryResource,
+        amount: i32,
+        context: &TransactionContext,
+    ) {
+        if amount == 0 {
+            return;
+        }
+        self.entries.push(InventoryTransaction {
+            source,
+            destination,
+            agent: context.agent.clone(),
+            tick: context.tick,
+            reason: context.reason,
+            resource,
+            amount,
+        });
+    }
+}
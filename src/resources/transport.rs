@@ -0,0 +1,198 @@
+use godot::builtin::Vector2;
+
+use super::inventory::InventoryResource;
+use super::transaction_log::InventoryOwner;
+use crate::behaviour::tour_planner::plan_shortest_tour;
+
+pub type TransportRequestId = u64;
+
+/// Goods to be carried from one inventory to another by whichever hauler claims the request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransportRequest {
+    pub id: TransportRequestId,
+    pub resource: InventoryResource,
+    pub amount: i32,
+    pub source: InventoryOwner,
+    pub source_position: Vector2,
+    pub destination: InventoryOwner,
+    pub destination_position: Vector2,
+    /// Requests with higher urgency are served first
+    pub urgency: f32,
+    pub hauler: Option<String>,
+}
+
+impl TransportRequest {
+    pub fn new(
+        resource: InventoryResource,
+        amount: i32,
+        source: InventoryOwner,
+        source_position: Vector2,
+        destination: InventoryOwner,
+        destination_position: Vector2,
+    ) -> Self {
+        Self {
+            id: 0,
+            resource,
+            amount,
+            source,
+            source_position,
+            destination,
+            destination_position,
+            urgency: 0.0,
+            hauler: None,
+        }
+    }
+
+    pub fn with_urgency(mut self, urgency: f32) -> Self {
+        self.urgency = urgency;
+        self
+    }
+}
+
+/// Requests the most urgent first, the oldest first among equally urgent ones. The first request
+/// is always taken, even when it asks for more than `capacity`, the others only while what they
+/// ask for still fits into what is left.
+pub fn select_batch(requests: &[&TransportRequest], capacity: i32) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..requests.len()).collect();
+    order.sort_by(|a, b| {
+        let (a, b) = (requests[*a], requests[*b]);
+        b.urgency.total_cmp(&a.urgency).then(a.id.cmp(&b.id))
+    });
+    let mut batch = Vec::new();
+    let mut space = capacity;
+    for index in order {
+        let amount = requests[index].amount;
+        if batch.is_empty() || amount <= space {
+            space -= amount;
+            batch.push(index);
+        }
+    }
+    batch
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TransportStopKind {
+    Pickup,
+    Dropoff,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct TransportStop {
+    pub request: TransportRequestId,
+    pub kind: TransportStopKind,
+    pub position: Vector2,
+}
+
+/// Route through a batch of requests: every pickup in the shortest order, then every dropoff.
+pub fn plan_transport_route(start: Vector2, batch: &[TransportRequest]) -> Vec<TransportStop> {
+    let pickups: Vec<Vector2> = batch.iter().map(|request| request.source_position).collect();
+    let pickup_order = plan_shortest_tour(start, &pickups, None, pickups.len());
+    let last_pickup = pickup_order.last().map_or(start, |index| pickups[*index]);
+    let dropoffs: Vec<Vector2> = batch.iter().map(|request| request.destination_position).collect();
+    let dropoff_order = plan_shortest_tour(last_pickup, &dropoffs, None, dropoffs.len());
+
+    let pickup_stops = pickup_order.into_iter().map(|index| TransportStop {
+        request: batch[index].id,
+        kind: TransportStopKind::Pickup,
+        position: pickups[index],
+    });
+    let dropoff_stops = dropoff_order.into_iter().map(|index| TransportStop {
+        request: batch[index].id,
+        kind: TransportStopKind::Dropoff,
+        position: dropoffs[index],
+    });
+    pickup_stops.chain(dropoff_stops).collect()
+}
+
+/// Open and claimed transport requests.
+#[derive(Default)]
+pub struct TransportBoard {
+    requests: Vec<TransportRequest>,
+    next_id: TransportRequestId,
+}
+
+impl TransportBoard {
+    pub fn new() -> Self {
+        Self { requests: Vec::new(), next_id: 0 }
+    }
+
+    pub fn requests(&self) -> &[TransportRequest] {
+        &self.requests
+    }
+
+    pub fn open_requests(&self) -> impl Iterator<Item = &TransportRequest> {
+        self.requests.iter().filter(|request| request.hauler.is_none())
+    }
+
+    /// Whether goods from `source` are still waiting for a hauler.
+    pub fn has_open_request_from(&self, source: &InventoryOwner) -> bool {
+        self.open_requests().any(|request| request.source == *source)
+    }
+
+    /// Whether goods are already on order for `destination`, claimed or not.
+    pub fn has_request_to(&self, destination: &InventoryOwner) -> bool {
+        self.requests.iter().any(|request| request.destination == *destination)
+    }
+
+    pub fn post(&mut self, mut request: TransportRequest) -> TransportRequestId {
+        let id = self.next_id;
+        self.next_id += 1;
+        request.id = id;
+        request.hauler = None;
+        self.requests.push(request);
+        id
+    }
+
+    /// Claims a batch of open requests for `hauler`, see `select_batch`. A request too big for
+    /// `capacity` is split, what does not fit stays open for the next hauler.
+    pub fn claim_batch(&mut self, hauler: &str, capacity: i32) -> Vec<TransportRequest> {
+        let open: Vec<&TransportRequest> = self.open_requests().collect();
+        let batch: Vec<(TransportRequestId, i32)> =
+            select_batch(&open, capacity).into_iter().map(|index| (open[index].id, open[index].amount)).collect();
+        for (id, amount) in &batch {
+            if *amount > capacity {
+                self.split_off(*id, amount - capacity);
+            }
+        }
+        let ids: Vec<TransportRequestId> = batch.into_iter().map(|(id, _)| id).collect();
+        self.requests
+            .iter_mut()
+            .filter(|request| ids.contains(&request.id))
+            .map(|request| {
+                request.hauler = Some(hauler.to_string());
+                request.clone()
+            })
+            .collect()
+    }
+
+    /// Moves `amount` of request `id` into a new open request, e.g. what a hauler could not pick
+    /// up. Returns the id of the new request.
+    pub fn split_off(&mut self, id: TransportRequestId, amount: i32) -> Option<TransportRequestId> {
+        let request = self.requests.iter_mut().find(|request| request.id == id && request.amount > amount)?;
+        request.amount -= amount;
+        let mut rest = request.clone();
+        rest.amount = amount;
+        Some(self.post(rest))
+    }
+
+    /// Removes a delivered request, or one that can no longer be delivered.
+    pub fn complete(&mut self, id: TransportRequestId) {
+        self.requests.retain(|request| request.id != id);
+    }
+
+    /// Puts request `id` back up for others, if `hauler` has claimed it.
+    pub fn release(&mut self, id: TransportRequestId, hauler: &str) {
+        for request in self.requests.iter_mut().filter(|request| request.id == id) {
+            if request.hauler.as_deref() == Some(hauler) {
+                request.hauler = None;
+            }
+        }
+    }
+
+    /// Puts every request `hauler` has claimed back up for others.
+    pub fn release_all(&mut self, hauler: &str) {
+        for request in self.requests.iter_mut().filter(|request| request.hauler.as_deref() == Some(hauler)) {
+            request.hauler = None;
+        }
+    }
+}
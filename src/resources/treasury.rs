@@ -0,0 +1,53 @@
+use godot::classes::Engine;
+use godot::prelude::*;
+
+use super::inventory::{Inventory, InventoryResource};
+use super::inventory_ledger::InventoryLedger;
+use super::transaction_log::{InventoryOwner, TransactionReason};
+
+/// Coins the town starts with, enough to pay a guard for a while.
+pub const STARTING_TREASURY_COINS: i32 = 50;
+
+/// Town purse that public workers like guards are paid from, registered as the `Treasury` singleton.
+#[derive(GodotClass)]
+#[class(base=Object)]
+pub struct Treasury {
+    #[base]
+    base: Base<Object>,
+    pub inventory: Inventory,
+}
+
+#[godot_api]
+impl IObject for Treasury {
+    fn init(base: Base<Object>) -> Self {
+        Self { base, inventory: Inventory::new(InventoryOwner::Treasury) }
+    }
+}
+
+#[godot_api]
+impl Treasury {
+    pub fn singleton() -> Gd<Treasury> {
+        Engine::singleton()
+            .get_singleton(&StringName::from("Treasury"))
+            .expect("Treasury singleton not found")
+            .try_cast::<Treasury>()
+            .unwrap()
+    }
+
+    /// Adds coins from outside the simulation, e.g. a player grant.
+    #[func]
+    pub fn fund(&mut self, coins: i32) {
+        let context = InventoryLedger::world_context(TransactionReason::Funding);
+        InventoryLedger::singleton().bind_mut().log.produce(
+            &mut self.inventory,
+            InventoryResource::Coins,
+            coins,
+            &context,
+        );
+    }
+
+    #[func]
+    fn get_coins(&self) -> i32 {
+        self.inventory.amount(InventoryResource::Coins)
+    }
+}
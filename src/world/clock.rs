@@ -0,0 +1,57 @@
+pub const HOURS_PER_DAY: f32 = 24.0;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct ClockConfig {
+    /// Real seconds one in-game day lasts
+    pub seconds_per_day: f32,
+    /// Hour the game starts at
+    pub start_hour: f32,
+}
+
+/// Point in game time. Days are counted from 0.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct DayTime {
+    pub day: u32,
+    pub hour: f32,
+}
+
+impl ClockConfig {
+    /// Game time after `seconds` of simulation.
+    pub fn time_at(&self, seconds: f64) -> DayTime {
+        let hours = self.start_hour as f64 + seconds * HOURS_PER_DAY as f64 / self.seconds_per_day as f64;
+        let day = (hours / HOURS_PER_DAY as f64).floor();
+        DayTime { day: day as u32, hour: (hours - day * HOURS_PER_DAY as f64) as f32 }
+    }
+}
+
+/// How bright it is at `hour`, 0 at night and 1 during the day with an hour of twilight
+/// around dawn and dusk.
+pub fn daylight(hour: f32, schedule: &DailySchedule) -> f32 {
+    let dawn = ((hour - schedule.dawn_hour) + 0.5).clamp(0.0, 1.0);
+    let dusk = ((schedule.dusk_hour - hour) + 0.5).clamp(0.0, 1.0);
+    dawn.min(dusk)
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ScheduledActivity {
+    Work,
+    /// Go home and stay there until dawn
+    Rest,
+}
+
+/// Working hours shared by the villagers.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct DailySchedule {
+    pub dawn_hour: f32,
+    pub dusk_hour: f32,
+}
+
+impl DailySchedule {
+    pub fn activity(&self, hour: f32) -> ScheduledActivity {
+        if hour >= self.dawn_hour && hour < self.dusk_hour {
+            ScheduledActivity::Work
+        } else {
+            ScheduledActivity::Rest
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use godot::classes::{CanvasModulate, ICanvasModulate};
+use godot::prelude::*;
+
+use super::world_clock::WorldClock;
+
+/// Darkens the scene at night following the world clock.
+#[derive(GodotClass)]
+#[class(base=CanvasModulate)]
+pub struct DaylightTint {
+    base: Base<CanvasModulate>,
+    #[export]
+    day_color: Color,
+    #[export]
+    night_color: Color,
+}
+
+#[godot_api]
+impl ICanvasModulate for DaylightTint {
+    fn init(base: Base<CanvasModulate>) -> Self {
+        Self { base, day_color: Color::WHITE, night_color: Color::from_rgb(0.25, 0.3, 0.5) }
+    }
+
+    fn process(&mut self, _delta: f64) {
+        let daylight = WorldClock::singleton().bind().get_daylight();
+        let color = self.night_color.lerp(self.day_color, daylight as f64);
+        self.base_mut().set_color(color);
+    }
+}
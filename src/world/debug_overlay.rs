@@ -0,0 +1,119 @@
+use godot::classes::{INode2D, InputEvent, InputEventKey, Node2D, ThemeDb};
+use godot::global::Key;
+use godot::prelude::*;
+
+use crate::agent::AGENT_GROUP;
+use crate::behaviour::free_space_manager::FreeSpaceManager;
+use crate::behaviour::perception::nodes_in_group;
+
+const TOGGLE_KEY: Key = Key::F3;
+/// Line height of the state stack relative to the font size
+const LINE_SPACING: f32 = 1.2;
+
+/// Draws the state stack, the move target and the reserved cell of every agent on top of the
+/// scene. Hidden at the start, F3 toggles it.
+#[derive(GodotClass)]
+#[class(base=Node2D)]
+pub struct DebugOverlay {
+    base: Base<Node2D>,
+    #[export]
+    font_size: i32,
+    /// Where the bottom line of the stack is drawn, relative to the agent
+    #[export]
+    text_offset: Vector2,
+    #[export]
+    text_color: Color,
+    #[export]
+    target_color: Color,
+    #[export]
+    cell_color: Color,
+}
+
+#[godot_api]
+impl INode2D for DebugOverlay {
+    fn init(base: Base<Node2D>) -> Self {
+        Self {
+            base,
+            font_size: 24,
+            text_offset: Vector2::new(-100.0, -80.0),
+            text_color: Color::WHITE,
+            target_color: Color::from_rgba(1.0, 0.8, 0.2, 0.8),
+            cell_color: Color::from_rgba(0.2, 0.8, 1.0, 0.8),
+        }
+    }
+
+    fn ready(&mut self) {
+        self.base_mut().set_z_index(100);
+        self.base_mut().set_visible(false);
+    }
+
+    fn process(&mut self, _delta: f64) {
+        if self.base().is_visible() {
+            self.base_mut().queue_redraw();
+        }
+    }
+
+    fn unhandled_input(&mut self, event: Gd<InputEvent>) {
+        let Ok(key) = event.try_cast::<InputEventKey>() else {
+            return;
+        };
+        if key.is_pressed() && !key.is_echo() && key.get_keycode() == TOGGLE_KEY {
+            let visible = self.base().is_visible();
+            self.base_mut().set_visible(!visible);
+        }
+    }
+
+    fn draw(&mut self) {
+        let Some(font) = ThemeDb::singleton().get_fallback_font() else {
+            return;
+        };
+        let cell_size = FreeSpaceManager::singleton().bind().cell_size();
+        let (font_size, text_color, target_color, cell_color) =
+            (self.font_size, self.text_color, self.target_color, self.cell_color);
+        let line_height = font_size as f32 * LINE_SPACING;
+        for mut agent in nodes_in_group::<Node2D>(AGENT_GROUP) {
+            let stack = agent.call("get_state_stack", &[]).to::<Dictionary>();
+            let position = self.base().to_local(agent.get_global_position());
+
+            if let Some(target) = stack.get("move_target").and_then(|target| target.try_to::<Vector2>().ok()) {
+                let target = self.agent_space_to_local(&agent, target);
+                self.base_mut().draw_line_ex(position, target, target_color).width(3.0).done();
+                self.base_mut().draw_circle(target, 8.0, target_color);
+            }
+            if let Some(cell) = stack.get("reserved_cell").and_then(|cell| cell.try_to::<Vector2>().ok()) {
+                let cell = self.agent_space_to_local(&agent, cell);
+                let rect = Rect2::new(cell - cell_size / 2.0, cell_size);
+                self.base_mut().draw_rect_ex(rect, cell_color).filled(false).width(4.0).done();
+            }
+
+            let frames: Vec<Variant> =
+                stack.get("frames").map_or_else(Vec::new, |frames| frames.to::<VariantArray>().iter_shared().collect());
+            // Innermost state at the bottom, right above the agent
+            for (line, frame) in frames.iter().rev().enumerate() {
+                let frame = frame.to::<Dictionary>();
+                let text = format!(
+                    "{}::{}",
+                    frame.get("behaviour").unwrap_or_default(),
+                    frame.get("state").unwrap_or_default()
+                );
+                let line_position = position + self.text_offset - Vector2::new(0.0, line as f32 * line_height);
+                self.base_mut()
+                    .draw_string_ex(&font, line_position, &text)
+                    .font_size(font_size)
+                    .modulate(text_color)
+                    .done();
+            }
+        }
+    }
+}
+
+impl DebugOverlay {
+    /// Agents report positions in the space of their parent, like their own position.
+    fn agent_space_to_local(&self, agent: &Gd<Node2D>, position: Vector2) -> Vector2 {
+        let global = match agent.get_parent().and_then(|parent| parent.try_cast::<Node2D>().ok()) {
+            Some(parent) => parent.to_global(position),
+            None => position,
+        };
+        self.base().to_local(global)
+    }
+}
@@ -0,0 +1,216 @@
+use godot::builtin::Vector2;
+
+pub type JobId = u64;
+
+/// Seconds a claim holds without being renewed
+pub const CLAIM_TIMEOUT: f64 = 30.0;
+
+/// Work a building or field asks for. Deliveries are not jobs, they go through the transport board
+/// of the `Logistics` singleton, where haulers batch them. Staffing waits for buildings that have
+/// recipes to run.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum JobKind {
+    /// Help finish a building someone started
+    Construct,
+    /// Take the crop off a grown field
+    Harvest,
+}
+
+/// What an agent knows how to do, every job kind needs one skill.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Skill {
+    Building,
+    Farming,
+}
+
+impl JobKind {
+    pub fn skill(&self) -> Skill {
+        match self {
+            JobKind::Construct => Skill::Building,
+            JobKind::Harvest => Skill::Farming,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobClaim {
+    pub agent: String,
+    /// Seconds since the game started after which the claim lapses unless renewed
+    pub expires_at: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Job {
+    pub id: JobId,
+    pub kind: JobKind,
+    /// Instance id of the node that posted the job
+    pub poster: i64,
+    pub position: Vector2,
+    /// Jobs with higher priority are taken first
+    pub priority: f32,
+    /// Coins the treasury pays whoever finishes the job
+    pub wage: i32,
+    /// How many agents can work on the job at once
+    pub openings: usize,
+    pub claims: Vec<JobClaim>,
+}
+
+impl Job {
+    pub fn new(kind: JobKind, poster: i64, position: Vector2) -> Self {
+        Self { id: 0, kind, poster, position, priority: 0.0, wage: 0, openings: 1, claims: Vec::new() }
+    }
+
+    pub fn with_priority(mut self, priority: f32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_wage(mut self, wage: i32) -> Self {
+        self.wage = wage;
+        self
+    }
+
+    pub fn with_openings(mut self, openings: usize) -> Self {
+        self.openings = openings;
+        self
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.claims.len() < self.openings
+    }
+
+    pub fn is_claimed_by(&self, agent: &str) -> bool {
+        self.claims.iter().any(|claim| claim.agent == agent)
+    }
+}
+
+/// Job for an agent with `skills` standing at `position`: the highest priority first, then the
+/// closest, then the oldest. Jobs further away than `reach` or already claimed by the agent are
+/// left out.
+pub fn select_job(jobs: &[&Job], agent: &str, skills: &[Skill], position: Vector2, reach: f32) -> Option<usize> {
+    jobs.iter()
+        .enumerate()
+        .filter(|(_, job)| job.is_open() && !job.is_claimed_by(agent) && skills.contains(&job.kind.skill()))
+        .map(|(index, job)| (index, job, position.distance_to(job.position)))
+        .filter(|(_, _, distance)| *distance <= reach)
+        .min_by(|(_, a, a_distance), (_, b, b_distance)| {
+            b.priority.total_cmp(&a.priority).then(a_distance.total_cmp(b_distance)).then(a.id.cmp(&b.id))
+        })
+        .map(|(index, _, _)| index)
+}
+
+/// Jobs posted by buildings, claimed by idle agents whose skills match. Claims lapse after a
+/// timeout unless the agent keeps renewing them, so jobs of agents that got stuck or vanished
+/// go back up for others.
+#[derive(Default)]
+pub struct JobBoard {
+    jobs: Vec<Job>,
+    next_id: JobId,
+}
+
+impl JobBoard {
+    pub fn new() -> Self {
+        Self { jobs: Vec::new(), next_id: 0 }
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn open_jobs(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter().filter(|job| job.is_open())
+    }
+
+    pub fn find(&self, poster: i64, kind: JobKind) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.poster == poster && job.kind == kind)
+    }
+
+    /// Posts a job unless the poster already has one of that kind up, posting twice is harmless.
+    pub fn post(&mut self, mut job: Job) -> JobId {
+        if let Some(existing) = self.find(job.poster, job.kind) {
+            return existing.id;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        job.id = id;
+        job.claims.clear();
+        self.jobs.push(job);
+        id
+    }
+
+    /// Takes the poster's job of `kind` down, claimed or not, once the work is no longer needed.
+    pub fn withdraw(&mut self, poster: i64, kind: JobKind) {
+        self.jobs.retain(|job| job.poster != poster || job.kind != kind);
+    }
+
+    /// Claims the best job for `agent`, see `select_job`.
+    pub fn claim(
+        &mut self,
+        agent: &str,
+        skills: &[Skill],
+        position: Vector2,
+        reach: f32,
+        now: f64,
+        timeout: f64,
+    ) -> Option<Job> {
+        let candidates: Vec<&Job> = self.jobs.iter().collect();
+        let index = select_job(&candidates, agent, skills, position, reach)?;
+        let job = &mut self.jobs[index];
+        job.claims.push(JobClaim { agent: agent.to_string(), expires_at: now + timeout });
+        Some(job.clone())
+    }
+
+    /// Claims the poster's job of `kind` if it has an opening, or renews the claim `agent`
+    /// already holds. Posters without such a job are free to work on.
+    pub fn claim_posted(&mut self, poster: i64, kind: JobKind, agent: &str, now: f64, timeout: f64) -> bool {
+        let Some(job) = self.jobs.iter_mut().find(|job| job.poster == poster && job.kind == kind) else {
+            return true;
+        };
+        if let Some(claim) = job.claims.iter_mut().find(|claim| claim.agent == agent) {
+            claim.expires_at = now + timeout;
+            return true;
+        }
+        if !job.is_open() {
+            return false;
+        }
+        job.claims.push(JobClaim { agent: agent.to_string(), expires_at: now + timeout });
+        true
+    }
+
+    /// Whether every opening of the poster's job of `kind` is taken by agents other than `agent`.
+    pub fn is_taken_by_others(&self, poster: i64, kind: JobKind, agent: &str) -> bool {
+        self.find(poster, kind).is_some_and(|job| !job.is_open() && !job.is_claimed_by(agent))
+    }
+
+    /// Keeps the claim of an agent still working on the job from lapsing.
+    pub fn renew(&mut self, id: JobId, agent: &str, now: f64, timeout: f64) {
+        for job in self.jobs.iter_mut().filter(|job| job.id == id) {
+            for claim in job.claims.iter_mut().filter(|claim| claim.agent == agent) {
+                claim.expires_at = now + timeout;
+            }
+        }
+    }
+
+    /// Gives the job up without finishing it, it is open for others again.
+    pub fn release(&mut self, id: JobId, agent: &str) {
+        for job in self.jobs.iter_mut().filter(|job| job.id == id) {
+            job.claims.retain(|claim| claim.agent != agent);
+        }
+    }
+
+    /// Removes a finished job together with every claim on it.
+    pub fn complete(&mut self, id: JobId) {
+        self.jobs.retain(|job| job.id != id);
+    }
+
+    /// Drops claims that were not renewed in time and returns how many lapsed.
+    pub fn expire_claims(&mut self, now: f64) -> usize {
+        let mut expired = 0;
+        for job in self.jobs.iter_mut() {
+            let before = job.claims.len();
+            job.claims.retain(|claim| claim.expires_at > now);
+            expired += before - job.claims.len();
+        }
+        expired
+    }
+}
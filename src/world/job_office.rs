@@ -0,0 +1,37 @@
+use godot::classes::Engine;
+use godot::prelude::*;
+
+use super::job_board::JobBoard;
+
+/// Engine-wide owner of the job board, registered as the `JobOffice` singleton.
+#[derive(GodotClass)]
+#[class(base=Object)]
+pub struct JobOffice {
+    #[base]
+    base: Base<Object>,
+    pub board: JobBoard,
+}
+
+#[godot_api]
+impl IObject for JobOffice {
+    fn init(base: Base<Object>) -> Self {
+        Self { base, board: JobBoard::new() }
+    }
+}
+
+#[godot_api]
+impl JobOffice {
+    pub fn singleton() -> Gd<JobOffice> {
+        Engine::singleton()
+            .get_singleton(&StringName::from("JobOffice"))
+            .expect("JobOffice singleton not found")
+            .try_cast::<JobOffice>()
+            .unwrap()
+    }
+
+    /// Jobs that still have an opening.
+    #[func]
+    fn get_open_job_count(&self) -> i64 {
+        self.board.open_jobs().count() as i64
+    }
+}
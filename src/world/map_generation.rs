@@ -0,0 +1,261 @@
+use crate::behaviour::pathfinding::Cell;
+use crate::world::terrain::{TerrainMap, TerrainType};
+
+/// Shapes the generated world. Levels are noise values in 0..1, densities are chances per cell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapGeneratorConfig {
+    /// Cells generated in each direction around the origin
+    pub radius: i32,
+    /// Cells per noise feature, larger gives wider regions
+    pub region_scale: f32,
+    /// Lakes where the elevation is below this
+    pub water_level: f32,
+    /// Rock where the elevation is above this
+    pub rock_level: f32,
+    /// Forest where the moisture is above this
+    pub forest_level: f32,
+    /// Fertile soil where the fertility is above this
+    pub fertile_level: f32,
+    pub river_count: usize,
+    /// Cells between two fords across a river
+    pub ford_spacing: i32,
+    pub tree_density: f32,
+    pub bush_density: f32,
+    pub deposit_density: f32,
+    /// Smallest and largest stone amount of a deposit
+    pub deposit_stone: (i32, i32),
+    /// Smallest and largest ore amount of a deposit
+    pub deposit_ore: (i32, i32),
+    /// Cells around the origin kept as open grass for the first homes
+    pub clearing_radius: i32,
+    pub spawn_point_count: usize,
+}
+
+pub fn map_generator_config() -> MapGeneratorConfig {
+    MapGeneratorConfig {
+        radius: 20,
+        region_scale: 8.0,
+        water_level: 0.28,
+        rock_level: 0.72,
+        forest_level: 0.62,
+        fertile_level: 0.6,
+        river_count: 2,
+        ford_spacing: 6,
+        tree_density: 0.4,
+        bush_density: 0.04,
+        deposit_density: 0.2,
+        deposit_stone: (10, 30),
+        deposit_ore: (2, 10),
+        clearing_radius: 3,
+        spawn_point_count: 8,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepositSite {
+    pub cell: Cell,
+    pub stone: i32,
+    pub ore: i32,
+}
+
+/// Everything a seed decides about a world: the terrain, where resource nodes stand and where
+/// agents start.
+#[derive(Clone)]
+pub struct GeneratedMap {
+    pub seed: u64,
+    pub radius: i32,
+    pub terrain: TerrainMap,
+    pub trees: Vec<Cell>,
+    pub bushes: Vec<Cell>,
+    pub deposits: Vec<DepositSite>,
+    /// Free grass cells in the clearing, the closest to the origin first
+    pub spawn_points: Vec<Cell>,
+}
+
+impl GeneratedMap {
+    /// Cells resource nodes stand on, they are occupied once the nodes are placed.
+    pub fn occupied_cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        self.trees.iter().chain(self.bushes.iter()).copied().chain(self.deposits.iter().map(|deposit| deposit.cell))
+    }
+
+    /// One character per cell, rows from the top, for looking at a seed without Godot.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::new();
+        for cell_y in -self.radius..=self.radius {
+            for cell_x in -self.radius..=self.radius {
+                text.push(self.cell_char((cell_x, cell_y)));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn cell_char(&self, cell: Cell) -> char {
+        if self.spawn_points.contains(&cell) {
+            '@'
+        } else if self.trees.contains(&cell) {
+            'T'
+        } else if self.bushes.contains(&cell) {
+            'b'
+        } else if self.deposits.iter().any(|deposit| deposit.cell == cell) {
+            'D'
+        } else {
+            match self.terrain.terrain_at(cell) {
+                TerrainType::Grass => '.',
+                TerrainType::FertileSoil => ',',
+                TerrainType::Forest => 'f',
+                TerrainType::Water => '~',
+                TerrainType::Rock => '^',
+                TerrainType::Road => '=',
+            }
+        }
+    }
+}
+
+/// Seed for a world nobody asked for in particular.
+pub fn random_seed() -> u64 {
+    rand::random()
+}
+
+// Salts keeping the noise fields of one seed apart
+const ELEVATION: u64 = 1;
+const MOISTURE: u64 = 2;
+const FERTILITY: u64 = 3;
+const RIVER: u64 = 4;
+const PLACEMENT: u64 = 5;
+const AMOUNT: u64 = 6;
+
+fn mix(mut value: u64) -> u64 {
+    // splitmix64 finaliser
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+/// Value in 0..1 that only depends on the seed, the salt and the cell.
+fn cell_random(seed: u64, salt: u64, cell: Cell) -> f32 {
+    let hash =
+        mix(mix(mix(seed ^ salt.wrapping_mul(0x9e37_79b9_7f4a_7c15)) ^ cell.0 as u32 as u64) ^ cell.1 as u32 as u64);
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn value_noise(seed: u64, salt: u64, x: f32, y: f32) -> f32 {
+    let (cell_x, cell_y) = (x.floor(), y.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, ty) = (smooth(x - cell_x), smooth(y - cell_y));
+    let corner = |dx: i32, dy: i32| cell_random(seed, salt, (cell_x as i32 + dx, cell_y as i32 + dy));
+    let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+    let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+    top + (bottom - top) * ty
+}
+
+/// Three octaves of value noise in 0..1, features are about `scale` cells wide.
+fn fractal_noise(seed: u64, salt: u64, cell: Cell, scale: f32) -> f32 {
+    let (mut total, mut amplitude, mut frequency, mut weight) = (0.0, 1.0, 1.0 / scale, 0.0);
+    for octave in 0..3 {
+        total +=
+            amplitude * value_noise(seed, salt + octave * 16, cell.0 as f32 * frequency, cell.1 as f32 * frequency);
+        weight += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    total / weight
+}
+
+fn base_terrain(seed: u64, config: &MapGeneratorConfig, cell: Cell) -> TerrainType {
+    let elevation = fractal_noise(seed, ELEVATION, cell, config.region_scale);
+    if elevation < config.water_level {
+        return TerrainType::Water;
+    }
+    if elevation > config.rock_level {
+        return TerrainType::Rock;
+    }
+    if fractal_noise(seed, MOISTURE, cell, config.region_scale) > config.forest_level {
+        return TerrainType::Forest;
+    }
+    if fractal_noise(seed, FERTILITY, cell, config.region_scale) > config.fertile_level {
+        return TerrainType::FertileSoil;
+    }
+    TerrainType::Grass
+}
+
+/// Rivers meander across the whole map, every other one north to south. Fords every
+/// `ford_spacing` cells keep both banks reachable.
+fn carve_rivers(seed: u64, config: &MapGeneratorConfig, terrain: &mut TerrainMap) {
+    let radius = config.radius;
+    for river in 0..config.river_count {
+        let salt = RIVER + river as u64 * 16;
+        let mut offset = ((cell_random(seed, salt, (0, 0)) * 2.0 - 1.0) * radius as f32 * 0.6) as i32;
+        for (step, along) in (-radius..=radius).enumerate() {
+            let drift = value_noise(seed, salt, along as f32 * 0.3, 0.5);
+            let bend = if drift < 0.35 {
+                -1
+            } else if drift > 0.65 {
+                1
+            } else {
+                0
+            };
+            let next_offset = (offset + bend).clamp(-radius, radius);
+            let river_terrain = if step as i32 % config.ford_spacing == config.ford_spacing / 2 {
+                TerrainType::Road
+            } else {
+                TerrainType::Water
+            };
+            // Both cells of a bend, so the river has no diagonal gaps
+            for across in [offset, next_offset] {
+                let cell = if river % 2 == 0 { (along, across) } else { (across, along) };
+                terrain.set_terrain(cell, river_terrain);
+            }
+            offset = next_offset;
+        }
+    }
+}
+
+fn in_clearing(config: &MapGeneratorConfig, cell: Cell) -> bool {
+    cell.0.pow(2) + cell.1.pow(2) <= config.clearing_radius.pow(2)
+}
+
+fn amount_between(seed: u64, salt: u64, cell: Cell, (low, high): (i32, i32)) -> i32 {
+    low + ((high - low + 1) as f32 * cell_random(seed, salt, cell)) as i32
+}
+
+/// Same seed and config, same world.
+pub fn generate_map(seed: u64, config: &MapGeneratorConfig) -> GeneratedMap {
+    let radius = config.radius;
+    let cells: Vec<Cell> =
+        (-radius..=radius).flat_map(|cell_y| (-radius..=radius).map(move |cell_x| (cell_x, cell_y))).collect();
+
+    let mut terrain = TerrainMap::default();
+    terrain.set_extent(radius);
+    for &cell in &cells {
+        terrain.set_terrain(cell, base_terrain(seed, config, cell));
+    }
+    carve_rivers(seed, config, &mut terrain);
+    for &cell in cells.iter().filter(|cell| in_clearing(config, **cell)) {
+        terrain.set_terrain(cell, TerrainType::Grass);
+    }
+
+    let mut spawn_points: Vec<Cell> = cells.iter().copied().filter(|cell| in_clearing(config, *cell)).collect();
+    spawn_points.sort_by_key(|cell| (cell.0.pow(2) + cell.1.pow(2), *cell));
+    spawn_points.truncate(config.spawn_point_count);
+
+    let (mut trees, mut bushes, mut deposits) = (Vec::new(), Vec::new(), Vec::new());
+    for &cell in &cells {
+        if spawn_points.contains(&cell) {
+            continue;
+        }
+        let chance = cell_random(seed, PLACEMENT, cell);
+        match terrain.terrain_at(cell) {
+            TerrainType::Forest if chance < config.tree_density => trees.push(cell),
+            TerrainType::Grass if chance < config.bush_density => bushes.push(cell),
+            TerrainType::Rock if chance < config.deposit_density => deposits.push(DepositSite {
+                cell,
+                stone: amount_between(seed, AMOUNT, cell, config.deposit_stone),
+                ore: amount_between(seed, AMOUNT + 1, cell, config.deposit_ore),
+            }),
+            _ => {}
+        }
+    }
+
+    GeneratedMap { seed, radius, terrain, trees, bushes, deposits, spawn_points }
+}
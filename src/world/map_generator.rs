@@ -0,0 +1,200 @@
+use godot::classes::{Node2D, Sprite2D, Texture2D};
+use godot::prelude::*;
+
+use crate::agent::AGENT_GROUP;
+use crate::behaviour::free_space_manager::FreeSpaceManager;
+use crate::behaviour::perception::nodes_in_group;
+use crate::behaviour::random::set_world_seed;
+use crate::building::{Bush, Deposit, ForestTree};
+use crate::world::map_generation::{generate_map, map_generator_config, random_seed, GeneratedMap, MapGeneratorConfig};
+
+/// Generates the world when the scene starts: hands the terrain to the `FreeSpaceManager`,
+/// places trees, bushes and deposits next to itself and moves the agents to the spawn points.
+/// Has to come before the `TerrainLayer` in the scene so the layer draws the generated terrain.
+#[derive(GodotClass)]
+#[class(base=Node)]
+pub struct MapGenerator {
+    base: Base<Node>,
+    /// World to generate, 0 picks a new one every session
+    #[export]
+    seed: i64,
+    /// Cells generated in each direction around the origin
+    #[export]
+    radius: i32,
+    /// Cells per noise feature, larger gives wider regions
+    #[export]
+    region_scale: f32,
+    /// Lakes where the elevation is below this
+    #[export]
+    water_level: f32,
+    /// Rock where the elevation is above this
+    #[export]
+    rock_level: f32,
+    /// Forest where the moisture is above this
+    #[export]
+    forest_level: f32,
+    /// Fertile soil where the fertility is above this
+    #[export]
+    fertile_level: f32,
+    #[export]
+    river_count: i32,
+    /// Cells between two fords across a river
+    #[export]
+    ford_spacing: i32,
+    #[export]
+    tree_density: f32,
+    #[export]
+    bush_density: f32,
+    #[export]
+    deposit_density: f32,
+    /// Smallest and largest stone amount of a deposit
+    #[export]
+    deposit_stone: Vector2i,
+    /// Smallest and largest ore amount of a deposit
+    #[export]
+    deposit_ore: Vector2i,
+    /// Cells around the origin kept as open grass for the first homes
+    #[export]
+    clearing_radius: i32,
+    #[export]
+    spawn_point_count: i32,
+    #[export]
+    tree_texture: Option<Gd<Texture2D>>,
+    #[export]
+    tree_scale: Vector2,
+    #[export]
+    tree_color: Color,
+    #[export]
+    bush_texture: Option<Gd<Texture2D>>,
+    #[export]
+    deposit_texture: Option<Gd<Texture2D>>,
+    #[export]
+    deposit_color: Color,
+    map: Option<GeneratedMap>,
+}
+
+#[godot_api]
+impl INode for MapGenerator {
+    fn init(base: Base<Node>) -> Self {
+        let config = map_generator_config();
+        Self {
+            base,
+            seed: 0,
+            radius: config.radius,
+            region_scale: config.region_scale,
+            water_level: config.water_level,
+            rock_level: config.rock_level,
+            forest_level: config.forest_level,
+            fertile_level: config.fertile_level,
+            river_count: config.river_count as i32,
+            ford_spacing: config.ford_spacing,
+            tree_density: config.tree_density,
+            bush_density: config.bush_density,
+            deposit_density: config.deposit_density,
+            deposit_stone: Vector2i::new(config.deposit_stone.0, config.deposit_stone.1),
+            deposit_ore: Vector2i::new(config.deposit_ore.0, config.deposit_ore.1),
+            clearing_radius: config.clearing_radius,
+            spawn_point_count: config.spawn_point_count as i32,
+            tree_texture: None,
+            tree_scale: Vector2::new(1.5, 1.5),
+            tree_color: Color::from_rgb(0.35, 0.6, 0.3),
+            bush_texture: None,
+            deposit_texture: None,
+            deposit_color: Color::from_rgb(0.55, 0.5, 0.5),
+            map: None,
+        }
+    }
+
+    fn ready(&mut self) {
+        let seed = if self.seed == 0 { random_seed() } else { self.seed as u64 };
+        godot_print!("Generating map from seed {}", seed);
+        // Agents draw from the same seed, so a seed replays the whole session and not just the map
+        set_world_seed(seed);
+        let map = generate_map(seed, &self.config());
+        *FreeSpaceManager::singleton().bind_mut().terrain_mut() = map.terrain.clone();
+        self.map = Some(map);
+        // Siblings can't be added while the parent is still setting up its children
+        self.base_mut().call_deferred("populate", &[]);
+    }
+}
+
+impl MapGenerator {
+    /// The exported settings, they start out as `map_generator_config`.
+    fn config(&self) -> MapGeneratorConfig {
+        MapGeneratorConfig {
+            radius: self.radius,
+            region_scale: self.region_scale,
+            water_level: self.water_level,
+            rock_level: self.rock_level,
+            forest_level: self.forest_level,
+            fertile_level: self.fertile_level,
+            river_count: self.river_count.max(0) as usize,
+            ford_spacing: self.ford_spacing.max(1),
+            tree_density: self.tree_density,
+            bush_density: self.bush_density,
+            deposit_density: self.deposit_density,
+            deposit_stone: (self.deposit_stone.x, self.deposit_stone.y),
+            deposit_ore: (self.deposit_ore.x, self.deposit_ore.y),
+            clearing_radius: self.clearing_radius,
+            spawn_point_count: self.spawn_point_count.max(0) as usize,
+        }
+    }
+}
+
+#[godot_api]
+impl MapGenerator {
+    /// Seed of the current world, to get the same one again.
+    #[func]
+    pub fn get_generated_seed(&self) -> i64 {
+        self.map.as_ref().map_or(0, |map| map.seed as i64)
+    }
+
+    #[func]
+    fn populate(&mut self) {
+        let (Some(map), Some(mut parent)) = (self.map.clone(), self.base().get_parent()) else {
+            return;
+        };
+        let free_space_manager = FreeSpaceManager::singleton();
+        let cell_position = |cell| free_space_manager.bind().cell_position(cell);
+
+        for &cell in &map.trees {
+            let mut tree = ForestTree::new_alloc();
+            place(tree.clone().upcast(), cell_position(cell), self.tree_texture.clone());
+            tree.set_scale(self.tree_scale);
+            tree.set_self_modulate(self.tree_color);
+            parent.add_child(&tree);
+        }
+        for &cell in &map.bushes {
+            let bush = Bush::new_alloc();
+            place(bush.clone().upcast(), cell_position(cell), self.bush_texture.clone());
+            parent.add_child(&bush);
+        }
+        for deposit_site in &map.deposits {
+            let mut deposit = Deposit::new_alloc();
+            place(deposit.clone().upcast(), cell_position(deposit_site.cell), self.deposit_texture.clone());
+            deposit.set_modulate(self.deposit_color);
+            deposit.set("stone", &deposit_site.stone.to_variant());
+            deposit.set("ore", &deposit_site.ore.to_variant());
+            parent.add_child(&deposit);
+        }
+
+        let agents = nodes_in_group::<Node2D>(AGENT_GROUP);
+        for (mut agent, cell) in agents.into_iter().zip(map.spawn_points.iter().cycle()) {
+            agent.set_position(cell_position(*cell));
+        }
+        godot_print!(
+            "Map {} placed {} trees, {} bushes and {} deposits",
+            map.seed,
+            map.trees.len(),
+            map.bushes.len(),
+            map.deposits.len()
+        );
+    }
+}
+
+fn place(mut node: Gd<Sprite2D>, position: Vector2, texture: Option<Gd<Texture2D>>) {
+    node.set_position(position);
+    if let Some(texture) = texture {
+        node.set_texture(&texture);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod clock;
+// World nodes register #[func]s and exported properties through closures returning godot's large CallError
+#[allow(clippy::result_large_err)]
+pub mod daylight_tint;
+#[allow(clippy::result_large_err)]
+pub mod world_clock;
+pub mod job_board;
+#[allow(clippy::result_large_err)]
+pub mod job_office;
+#[allow(clippy::result_large_err)]
+pub mod debug_overlay;
+pub mod terrain;
+#[allow(clippy::result_large_err)]
+pub mod terrain_layer;
+pub mod map_generation;
+#[allow(clippy::result_large_err)]
+pub mod map_generator;
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use crate::behaviour::pathfinding::{Cell, CostGrid};
+
+/// What agents put up on a cell, terrain decides which of them fit where.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Structure {
+    Home,
+    Field,
+    Quarry,
+}
+
+/// Ground of one `FreeSpaceManager` cell.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+pub enum TerrainType {
+    Grass,
+    FertileSoil,
+    Forest,
+    Water,
+    Rock,
+    Road,
+}
+
+impl TerrainType {
+    pub const ALL: [TerrainType; 6] = [
+        TerrainType::Grass,
+        TerrainType::FertileSoil,
+        TerrainType::Forest,
+        TerrainType::Water,
+        TerrainType::Rock,
+        TerrainType::Road,
+    ];
+
+    /// Cost of walking into the cell, roads are the cheapest at 1.0. None for water, which
+    /// can't be crossed.
+    pub fn move_cost(&self) -> Option<f32> {
+        match self {
+            TerrainType::Road => Some(1.0),
+            TerrainType::Grass | TerrainType::FertileSoil => Some(1.5),
+            TerrainType::Forest => Some(3.0),
+            TerrainType::Rock => Some(4.0),
+            TerrainType::Water => None,
+        }
+    }
+
+    pub fn allowed_structures(&self) -> &'static [Structure] {
+        match self {
+            TerrainType::Grass => &[Structure::Home, Structure::Field],
+            TerrainType::FertileSoil => &[Structure::Field],
+            TerrainType::Rock => &[Structure::Quarry],
+            TerrainType::Forest | TerrainType::Water | TerrainType::Road => &[],
+        }
+    }
+
+    pub fn allows(&self, structure: Structure) -> bool {
+        self.allowed_structures().contains(&structure)
+    }
+}
+
+/// Terrain of every cell. Cells that were never set have the default terrain, so the map has
+/// no edges.
+#[derive(Clone)]
+pub struct TerrainMap {
+    cells: HashMap<Cell, TerrainType>,
+    default: TerrainType,
+    /// Cells in each direction around the origin the map was made for, 0 for a map made by hand
+    extent: i32,
+}
+
+impl Default for TerrainMap {
+    fn default() -> Self {
+        Self::new(TerrainType::Grass)
+    }
+}
+
+impl TerrainMap {
+    pub fn new(default: TerrainType) -> Self {
+        Self { cells: HashMap::new(), default, extent: 0 }
+    }
+
+    pub fn extent(&self) -> i32 {
+        self.extent
+    }
+
+    pub fn set_extent(&mut self, extent: i32) {
+        self.extent = extent;
+    }
+
+    pub fn default_terrain(&self) -> TerrainType {
+        self.default
+    }
+
+    pub fn terrain_at(&self, cell: Cell) -> TerrainType {
+        self.cells.get(&cell).copied().unwrap_or(self.default)
+    }
+
+    pub fn set_terrain(&mut self, cell: Cell, terrain: TerrainType) {
+        if terrain == self.default {
+            self.cells.remove(&cell);
+        } else {
+            self.cells.insert(cell, terrain);
+        }
+    }
+
+    /// Cells whose terrain differs from the default.
+    pub fn cells(&self) -> impl Iterator<Item = (Cell, TerrainType)> + '_ {
+        self.cells.iter().map(|(cell, terrain)| (*cell, *terrain))
+    }
+
+    pub fn allows(&self, cell: Cell, structure: Structure) -> bool {
+        self.terrain_at(cell).allows(structure)
+    }
+}
+
+impl CostGrid for TerrainMap {
+    fn move_cost(&self, cell: Cell) -> Option<f32> {
+        self.terrain_at(cell).move_cost()
+    }
+}
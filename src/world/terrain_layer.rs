@@ -0,0 +1,98 @@
+use godot::classes::{ITileMapLayer, Texture2D, TileMapLayer, TileSet, TileSetAtlasSource};
+use godot::prelude::*;
+
+use crate::behaviour::free_space_manager::FreeSpaceManager;
+use crate::world::terrain::TerrainType;
+
+/// Size of one tile in the atlas texture
+const ATLAS_TILE_SIZE: i32 = 128;
+
+/// Tile of the atlas drawn for each terrain type
+fn atlas_coords(terrain: TerrainType) -> Vector2i {
+    match terrain {
+        TerrainType::Grass => Vector2i::new(1, 1),
+        TerrainType::FertileSoil => Vector2i::new(6, 1),
+        TerrainType::Forest => Vector2i::new(0, 0),
+        TerrainType::Water => Vector2i::new(2, 0),
+        TerrainType::Rock => Vector2i::new(2, 5),
+        TerrainType::Road => Vector2i::new(5, 1),
+    }
+}
+
+/// Draws the terrain the `FreeSpaceManager` holds, one atlas tile stretched over each of its
+/// cells. The terrain data is the source of truth, the layer only shows it.
+#[derive(GodotClass)]
+#[class(base=TileMapLayer)]
+pub struct TerrainLayer {
+    base: Base<TileMapLayer>,
+    #[export]
+    atlas: Option<Gd<Texture2D>>,
+    source_id: i32,
+}
+
+#[godot_api]
+impl ITileMapLayer for TerrainLayer {
+    fn init(base: Base<TileMapLayer>) -> Self {
+        Self { base, atlas: None, source_id: -1 }
+    }
+
+    fn ready(&mut self) {
+        let Some(atlas) = self.atlas.clone() else {
+            godot_warn!("TerrainLayer has no atlas texture, terrain is not drawn");
+            return;
+        };
+        let mut source = TileSetAtlasSource::new_gd();
+        source.set_texture(&atlas);
+        source.set_texture_region_size(Vector2i::new(ATLAS_TILE_SIZE, ATLAS_TILE_SIZE));
+        for terrain in TerrainType::ALL {
+            source.create_tile(atlas_coords(terrain));
+        }
+        let mut tile_set = TileSet::new_gd();
+        tile_set.set_tile_size(Vector2i::new(ATLAS_TILE_SIZE, ATLAS_TILE_SIZE));
+        self.source_id = tile_set.add_source(&source);
+        self.base_mut().set_tile_set(&tile_set);
+
+        // Cell (0, 0) of the free space grid starts half a cell away from the origin
+        let cell_size = FreeSpaceManager::singleton().bind().cell_size();
+        self.base_mut().set_scale(cell_size / ATLAS_TILE_SIZE as f32);
+        self.base_mut().set_position(cell_size / 2.0);
+        self.base_mut().set_z_index(-10);
+        self.redraw();
+    }
+}
+
+#[godot_api]
+impl TerrainLayer {
+    /// Fills the tiles from the terrain data again, after the terrain was changed.
+    #[func]
+    pub fn redraw(&mut self) {
+        if self.source_id < 0 {
+            return;
+        }
+        let free_space_manager = FreeSpaceManager::singleton();
+        let free_space_manager = free_space_manager.bind();
+        let terrain = free_space_manager.terrain();
+        let source_id = self.source_id;
+        // The whole generated map, cells further out only when their terrain was set
+        let extent = terrain.extent();
+
+        self.base_mut().clear();
+        for cell_x in -extent..=extent {
+            for cell_y in -extent..=extent {
+                let coords = atlas_coords(terrain.terrain_at((cell_x, cell_y)));
+                self.base_mut()
+                    .set_cell_ex(Vector2i::new(cell_x, cell_y))
+                    .source_id(source_id)
+                    .atlas_coords(coords)
+                    .done();
+            }
+        }
+        for ((cell_x, cell_y), cell_terrain) in terrain.cells() {
+            self.base_mut()
+                .set_cell_ex(Vector2i::new(cell_x, cell_y))
+                .source_id(source_id)
+                .atlas_coords(atlas_coords(cell_terrain))
+                .done();
+        }
+    }
+}
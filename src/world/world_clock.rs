@@ -0,0 +1,70 @@
+use godot::classes::Engine;
+use godot::prelude::*;
+
+use super::clock::{daylight, ClockConfig, DailySchedule, DayTime};
+
+/// Game calendar derived from the physics time, registered as the `WorldClock` singleton.
+#[derive(GodotClass)]
+#[class(base=Object)]
+pub struct WorldClock {
+    #[base]
+    base: Base<Object>,
+    config: ClockConfig,
+    schedule: DailySchedule,
+}
+
+#[godot_api]
+impl IObject for WorldClock {
+    fn init(base: Base<Object>) -> Self {
+        Self {
+            base,
+            config: ClockConfig { seconds_per_day: 240.0, start_hour: 7.0 },
+            schedule: DailySchedule { dawn_hour: 6.0, dusk_hour: 20.0 },
+        }
+    }
+}
+
+#[godot_api]
+impl WorldClock {
+    pub fn singleton() -> Gd<WorldClock> {
+        Engine::singleton()
+            .get_singleton(&StringName::from("WorldClock"))
+            .expect("WorldClock singleton not found")
+            .try_cast::<WorldClock>()
+            .unwrap()
+    }
+
+    /// Physics ticks since the game started, the time every simulated system goes by.
+    pub fn tick() -> u64 {
+        Engine::singleton().get_physics_frames()
+    }
+
+    /// Seconds of simulated time since the game started.
+    pub fn seconds() -> f64 {
+        Self::tick() as f64 / Engine::singleton().get_physics_ticks_per_second() as f64
+    }
+
+    pub fn now(&self) -> DayTime {
+        self.config.time_at(Self::seconds())
+    }
+
+    pub fn schedule(&self) -> DailySchedule {
+        self.schedule
+    }
+
+    #[func]
+    fn get_day(&self) -> i64 {
+        self.now().day as i64
+    }
+
+    #[func]
+    fn get_hour(&self) -> f32 {
+        self.now().hour
+    }
+
+    /// 0 at night, 1 during the day, for lighting.
+    #[func]
+    pub fn get_daylight(&self) -> f32 {
+        daylight(self.now().hour, &self.schedule)
+    }
+}
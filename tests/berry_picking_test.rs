@@ -0,0 +1,32 @@
+use godot::prelude::*;
+use market_and_mastery::behaviour::berry_picker_behaviour::nearest_bush_with_berries;
+use market_and_mastery::building::BerryRegrowth;
+
+#[test]
+fn test_regrowth_stops_when_the_bush_is_full() {
+    let mut regrowth = BerryRegrowth::default();
+
+    assert_eq!(regrowth.grow(3.0, 4.0, 2), 0);
+    assert_eq!(regrowth.grow(6.0, 4.0, 2), 2);
+    assert_eq!(regrowth.grow(100.0, 4.0, 2), 0);
+    assert_eq!(regrowth.grown(), 2);
+}
+
+#[test]
+fn test_regrown_berries_are_handed_over_once() {
+    let mut regrowth = BerryRegrowth::default();
+    regrowth.grow(12.0, 4.0, 5);
+
+    assert_eq!(regrowth.take(), 3);
+    assert_eq!(regrowth.take(), 0);
+    assert_eq!(regrowth.grow(4.0, 4.0, 5), 1);
+}
+
+#[test]
+fn test_picker_goes_to_the_nearest_bush_with_berries() {
+    let bushes = [(Vector2::new(10.0, 0.0), 0), (Vector2::new(50.0, 0.0), 3), (Vector2::new(-30.0, 0.0), 1)];
+
+    assert_eq!(nearest_bush_with_berries(Vector2::ZERO, &bushes), Some(2));
+    assert_eq!(nearest_bush_with_berries(Vector2::new(40.0, 0.0), &bushes), Some(1));
+    assert_eq!(nearest_bush_with_berries(Vector2::ZERO, &[(Vector2::ZERO, 0)]), None);
+}
@@ -0,0 +1,29 @@
+use approx::assert_relative_eq;
+use market_and_mastery::world::clock::{daylight, ClockConfig, DailySchedule, ScheduledActivity};
+
+fn make_schedule() -> DailySchedule {
+    DailySchedule { dawn_hour: 6.0, dusk_hour: 20.0 }
+}
+
+#[test]
+fn test_time_at_rolls_over_to_next_day() {
+    let config = ClockConfig { seconds_per_day: 240.0, start_hour: 18.0 };
+
+    let time = config.time_at(90.0);
+
+    assert_eq!(time.day, 1);
+    assert_relative_eq!(time.hour, 3.0, epsilon = 1e-4);
+}
+
+#[test]
+fn test_schedule_and_daylight_follow_dawn_and_dusk() {
+    let schedule = make_schedule();
+
+    assert_eq!(schedule.activity(5.9), ScheduledActivity::Rest);
+    assert_eq!(schedule.activity(12.0), ScheduledActivity::Work);
+    assert_eq!(schedule.activity(20.0), ScheduledActivity::Rest);
+    assert_eq!(daylight(2.0, &schedule), 0.0);
+    assert_eq!(daylight(6.0, &schedule), 0.5);
+    assert_eq!(daylight(12.0, &schedule), 1.0);
+    assert_eq!(daylight(23.0, &schedule), 0.0);
+}
@@ -0,0 +1,56 @@
+use approx::assert_relative_eq;
+use market_and_mastery::building::ConstructionSite;
+use market_and_mastery::resources::inventory::InventoryResource;
+
+#[test]
+fn test_builders_add_work_to_the_same_site() {
+    let mut site = ConstructionSite::new(4.0, 3);
+    assert!(site.join("Agent1"));
+    assert!(site.join("Agent2"));
+
+    site.add_work(2.0 * 0.5);
+    site.add_work(1.0 * 0.5);
+    assert_relative_eq!(site.progress(), 0.375, epsilon = 1e-6);
+    assert!(!site.is_complete());
+
+    site.add_work(10.0);
+    assert!(site.is_complete());
+    assert_relative_eq!(site.progress(), 1.0, epsilon = 1e-6);
+
+    site.remove_work(4.0);
+    assert!(site.is_cleared());
+}
+
+#[test]
+fn test_full_site_turns_new_builders_away() {
+    let mut site = ConstructionSite::new(1.0, 2);
+    assert!(site.join("Agent1"));
+    assert!(site.join("Agent2"));
+    assert!(site.join("Agent1"));
+    assert!(!site.has_free_slot());
+    assert!(!site.join("Agent3"));
+
+    site.leave("Agent1");
+    assert!(site.join("Agent3"));
+    assert_eq!(site.builders(), ["Agent2".to_string(), "Agent3".to_string()]);
+}
+
+#[test]
+fn test_work_only_starts_once_the_materials_are_on_site() {
+    let mut site = ConstructionSite::new(2.0, 2).with_materials(&[(InventoryResource::Wood, 3)]);
+    site.join("Agent1");
+
+    site.add_work(1.0);
+    assert!(site.is_cleared());
+
+    assert_eq!(site.deliver(InventoryResource::Berries, 2), 0);
+    assert_eq!(site.deliver(InventoryResource::Wood, 2), 2);
+    assert_eq!(site.missing_materials(), [(InventoryResource::Wood, 1)]);
+    site.add_work(1.0);
+    assert!(site.is_cleared());
+
+    assert_eq!(site.deliver(InventoryResource::Wood, 5), 1);
+    assert!(site.has_materials());
+    site.add_work(1.0);
+    assert_relative_eq!(site.progress(), 0.5, epsilon = 1e-6);
+}
@@ -0,0 +1,19 @@
+use market_and_mastery::behaviour::farmer_behaviour::{forget_stopped_field, FieldTask};
+
+#[test]
+fn test_cancel_during_field_building_drops_that_field_wherever_it_is() {
+    let mut fields = vec![1, 2, 3];
+
+    forget_stopped_field(&mut fields, FieldTask::Building(2), |field| *field);
+
+    assert_eq!(fields, vec![1, 3]);
+}
+
+#[test]
+fn test_cancel_during_field_removing_keeps_the_field() {
+    let mut fields = vec![1, 2, 3];
+
+    forget_stopped_field(&mut fields, FieldTask::Removing(2), |field| *field);
+
+    assert_eq!(fields, vec![1, 2, 3]);
+}
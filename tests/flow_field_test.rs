@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use market_and_mastery::behaviour::flow_field::{FlowField, FlowFieldCache};
+use market_and_mastery::behaviour::pathfinding::{find_path, Cell, CostGrid};
+
+/// Open ground costing 1.0 with some blocked cells
+struct TestGrid {
+    blocked: HashSet<Cell>,
+}
+
+impl CostGrid for TestGrid {
+    fn move_cost(&self, cell: Cell) -> Option<f32> {
+        if self.blocked.contains(&cell) {
+            None
+        } else {
+            Some(1.0)
+        }
+    }
+}
+
+fn follow(field: &FlowField, mut cell: Cell) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    while cell != field.goal() {
+        cell = field.next_cell(cell).unwrap();
+        cells.push(cell);
+        assert!(cells.len() < 100, "flow field goes in circles");
+    }
+    cells
+}
+
+#[test]
+fn test_flow_field_leads_as_far_as_a_path() {
+    let grid = TestGrid { blocked: [(2, -1), (2, 0), (2, 1), (2, 2)].into_iter().collect() };
+    let field = FlowField::compute(&grid, (5, 0), 10);
+
+    for start in [(0, 0), (-3, 4), (5, -6)] {
+        let walked = follow(&field, start);
+        let path = find_path(&grid, start, (5, 0)).unwrap();
+        assert_eq!(walked.last(), Some(&(5, 0)));
+        assert!(walked.iter().all(|cell| !grid.blocked.contains(cell)));
+        assert!((field.cost_to_goal(start).unwrap() - path_cost(start, &path)).abs() < 0.001);
+    }
+}
+
+fn path_cost(start: Cell, path: &[Cell]) -> f32 {
+    let mut previous = start;
+    let mut cost = 0.0;
+    for cell in path {
+        cost += if previous.0 != cell.0 && previous.1 != cell.1 { std::f32::consts::SQRT_2 } else { 1.0 };
+        previous = *cell;
+    }
+    cost
+}
+
+#[test]
+fn test_agents_on_blocked_cells_step_off_and_far_ones_are_not_reached() {
+    let grid = TestGrid { blocked: [(0, 0)].into_iter().collect() };
+    let field = FlowField::compute(&grid, (3, 0), 5);
+
+    assert!(field.reaches((0, 0)));
+    assert_eq!(follow(&field, (0, 0)).last(), Some(&(3, 0)));
+    assert!(!field.reaches((20, 0)));
+}
+
+#[test]
+fn test_cache_shares_popular_fields_and_drops_only_the_ones_a_change_touches() {
+    let grid = TestGrid { blocked: HashSet::new() };
+    let mut cache = FlowFieldCache::new(2, 2, 10.0, 5);
+
+    assert!(!cache.note_request((0, 0), 0.0));
+    assert!(cache.note_request((0, 0), 1.0));
+    cache.field((0, 0), &grid);
+    cache.field((20, 0), &grid);
+    assert!(cache.is_cached((0, 0)) && cache.is_cached((20, 0)));
+
+    assert_eq!(cache.invalidate((3, 3)), 1);
+    assert!(!cache.is_cached((0, 0)));
+    assert!(cache.is_cached((20, 0)));
+
+    // Over capacity the least recently used field goes
+    cache.field((0, 0), &grid);
+    cache.field((0, 0), &grid);
+    cache.field((40, 0), &grid);
+    assert!(cache.is_cached((0, 0)) && cache.is_cached((40, 0)));
+    assert!(!cache.is_cached((20, 0)));
+}
+
+#[test]
+fn test_requests_only_count_within_their_window() {
+    let mut cache = FlowFieldCache::new(2, 3, 10.0, 5);
+
+    assert!(!cache.note_request((0, 0), 0.0));
+    assert!(!cache.note_request((0, 0), 5.0));
+    // The first two requests are too old to count along
+    assert!(!cache.note_request((0, 0), 12.0));
+    assert!(!cache.note_request((0, 0), 13.0));
+    assert!(cache.note_request((0, 0), 14.0));
+}
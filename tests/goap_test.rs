@@ -0,0 +1,80 @@
+use market_and_mastery::behaviour::behaviour_regestry::{make_farmer_goals, make_farmer_planner_actions};
+use market_and_mastery::behaviour::goap::{plan, Condition, Effect, PlannerAction, WorldFact, WorldState};
+
+fn action(name: &'static str, cost: u32, preconditions: Vec<Condition>, effects: Vec<Effect>) -> PlannerAction<()> {
+    PlannerAction { name, steps: vec![()], cost, preconditions, effects }
+}
+
+#[test]
+fn test_plan_picks_cheapest_route() {
+    let actions = vec![
+        action("Expensive food", 10, vec![], vec![Effect::Add(WorldFact::HouseholdFood, 2)]),
+        action("Build field", 1, vec![], vec![Effect::Set(WorldFact::GrownFieldCount, 1)]),
+        action(
+            "Harvest",
+            1,
+            vec![Condition::AtLeast(WorldFact::GrownFieldCount, 1)],
+            vec![Effect::Set(WorldFact::GrownFieldCount, 0), Effect::Add(WorldFact::HouseholdFood, 1)],
+        ),
+    ];
+    let goal = [Condition::AtLeast(WorldFact::HouseholdFood, 2)];
+
+    assert_eq!(plan(&WorldState::new(), &goal, &actions, 1000), Some(vec![1, 2, 1, 2]));
+}
+
+#[test]
+fn test_plan_is_empty_for_met_goal_and_none_for_unreachable_goal() {
+    let actions = vec![action("Build home", 1, vec![], vec![Effect::Set(WorldFact::HasHome, 1)])];
+    let start = WorldState::new().with(WorldFact::HasHome, 1);
+
+    assert_eq!(plan(&start, &[Condition::AtLeast(WorldFact::HasHome, 1)], &actions, 1000), Some(vec![]));
+    assert_eq!(plan(&start, &[Condition::AtLeast(WorldFact::FieldCount, 1)], &actions, 1000), None);
+}
+
+#[test]
+fn test_farmer_plans_food_from_scratch() {
+    let actions = make_farmer_planner_actions(3, 3, 5.0);
+    let goals = make_farmer_goals(3);
+
+    let steps = plan(&WorldState::new(), &goals[0].conditions, &actions, 20_000).unwrap();
+
+    let names: Vec<&str> = steps.iter().map(|step| actions[*step].name).collect();
+    assert_eq!(names[0], "Build home");
+    assert_eq!(names.last(), Some(&"Deliver wheat"));
+    assert_eq!(names.iter().filter(|name| **name == "Harvest field").count(), 10);
+}
+
+#[test]
+fn test_farmer_harvests_for_buyers_once_its_own_food_is_stocked() {
+    let actions = make_farmer_planner_actions(3, 3, 0.0);
+    let goals = make_farmer_goals(3);
+    let start = WorldState::new()
+        .with(WorldFact::HasHome, 1)
+        .with(WorldFact::FieldCount, 3)
+        .with(WorldFact::PlantedFieldCount, 1)
+        .with(WorldFact::GrownFieldCount, 1)
+        .with(WorldFact::HouseholdFood, 10)
+        .with(WorldFact::MarketHasBuyers, 1);
+
+    let steps = plan(&start, &goals[2].conditions, &actions, 20_000).unwrap();
+
+    let names: Vec<&str> = steps.iter().map(|step| actions[*step].name).collect();
+    assert_eq!(names, ["Harvest field", "Supply buyer"]);
+}
+
+#[test]
+fn test_farmer_replants_harvested_fields_instead_of_building_new_ones() {
+    let actions = make_farmer_planner_actions(3, 3, 0.0);
+    let goals = make_farmer_goals(3);
+    let start = WorldState::new()
+        .with(WorldFact::HasHome, 1)
+        .with(WorldFact::FieldCount, 3)
+        .with(WorldFact::PlantedFieldCount, 1)
+        .with(WorldFact::EmptyFieldCount, 2)
+        .with(WorldFact::HouseholdFood, 10);
+
+    let steps = plan(&start, &goals[1].conditions, &actions, 20_000).unwrap();
+
+    let names: Vec<&str> = steps.iter().map(|step| actions[*step].name).collect();
+    assert_eq!(names, ["Replant field", "Replant field"]);
+}
@@ -0,0 +1,12 @@
+use market_and_mastery::behaviour::guard_behaviour::split_wage;
+
+#[test]
+fn test_split_wage_shares_evenly() {
+    assert_eq!(split_wage(5, &[10, 10, 10]), vec![2, 2, 1]);
+}
+
+#[test]
+fn test_split_wage_skips_payers_that_run_out() {
+    assert_eq!(split_wage(6, &[1, 0, 10]), vec![1, 0, 5]);
+    assert_eq!(split_wage(6, &[1, 2]), vec![1, 2]);
+}
@@ -0,0 +1,31 @@
+use godot::prelude::*;
+use market_and_mastery::behaviour::introspection::StateStack;
+
+#[derive(Debug)]
+enum FarmerState {
+    FieldRemoving,
+}
+
+#[test]
+fn test_state_stack_reads_from_outermost_to_innermost() {
+    let stack = StateStack::of("AgentBehaviour", format_args!("Working"))
+        .then(StateStack::of("FarmerBehaviour", FarmerState::FieldRemoving))
+        .then(StateStack::of("MoveAndBuild", format_args!("Moving({}, {:.2})", Vector2::new(10.0, 20.0), 0.5)));
+
+    assert_eq!(
+        stack.to_string(),
+        "AgentBehaviour::Working > FarmerBehaviour::FieldRemoving > MoveAndBuild::Moving((10, 20), 0.50)"
+    );
+    assert_eq!(stack.frames.len(), 3);
+}
+
+#[test]
+fn test_inner_move_target_and_reserved_cell_win() {
+    let outer = StateStack::of("Schedule", format_args!("GoingHome")).with_move_target(Some(Vector2::new(1.0, 1.0)));
+    let inner = StateStack::of("Move", format_args!("Moving")).with_move_target(Some(Vector2::new(5.0, 5.0)));
+
+    let stack = outer.then(inner).then(StateStack::of("Idle", format_args!("Idle")));
+
+    assert_eq!(stack.move_target, Some(Vector2::new(5.0, 5.0)));
+    assert_eq!(stack.reserved_cell, None);
+}
@@ -0,0 +1,60 @@
+use godot::prelude::*;
+use market_and_mastery::world::job_board::{Job, JobBoard, JobKind, Skill};
+
+#[test]
+fn test_claim_prefers_priority_then_distance_and_matches_skills() {
+    let mut board = JobBoard::new();
+    let far_urgent = board.post(Job::new(JobKind::Construct, 1, Vector2::new(500.0, 0.0)).with_priority(1.0));
+    let near = board.post(Job::new(JobKind::Construct, 2, Vector2::new(50.0, 0.0)).with_priority(0.5));
+    board.post(Job::new(JobKind::Harvest, 3, Vector2::new(10.0, 0.0)).with_priority(2.0));
+
+    let first = board.claim("Builder", &[Skill::Building], Vector2::ZERO, 1000.0, 0.0, 30.0).unwrap();
+    let second = board.claim("Builder", &[Skill::Building], Vector2::ZERO, 1000.0, 0.0, 30.0).unwrap();
+
+    assert_eq!(first.id, far_urgent);
+    assert_eq!(second.id, near);
+    assert!(board.claim("Builder", &[Skill::Building], Vector2::ZERO, 1000.0, 0.0, 30.0).is_none());
+}
+
+#[test]
+fn test_claim_ignores_jobs_out_of_reach() {
+    let mut board = JobBoard::new();
+    board.post(Job::new(JobKind::Harvest, 1, Vector2::new(500.0, 0.0)));
+
+    assert!(board.claim("Farmer", &[Skill::Farming], Vector2::ZERO, 100.0, 0.0, 30.0).is_none());
+    assert!(board.claim("Farmer", &[Skill::Farming], Vector2::ZERO, 600.0, 0.0, 30.0).is_some());
+}
+
+#[test]
+fn test_claims_fill_openings_and_lapse_unless_renewed() {
+    let mut board = JobBoard::new();
+    let id = board.post(Job::new(JobKind::Construct, 1, Vector2::ZERO).with_openings(2));
+
+    assert!(board.claim("A", &[Skill::Building], Vector2::ZERO, 100.0, 0.0, 10.0).is_some());
+    assert!(board.claim("B", &[Skill::Building], Vector2::ZERO, 100.0, 0.0, 10.0).is_some());
+    assert!(board.claim("C", &[Skill::Building], Vector2::ZERO, 100.0, 0.0, 10.0).is_none());
+
+    board.renew(id, "A", 8.0, 10.0);
+    assert_eq!(board.expire_claims(12.0), 1);
+    assert!(board.claim("C", &[Skill::Building], Vector2::ZERO, 100.0, 12.0, 10.0).is_some());
+    assert!(board.is_taken_by_others(1, JobKind::Construct, "B"));
+    assert!(!board.is_taken_by_others(1, JobKind::Construct, "A"));
+
+    board.complete(id);
+    assert!(board.jobs().is_empty());
+}
+
+#[test]
+fn test_posting_twice_keeps_one_job_until_withdrawn() {
+    let mut board = JobBoard::new();
+    let first = board.post(Job::new(JobKind::Harvest, 7, Vector2::ZERO));
+    let second = board.post(Job::new(JobKind::Harvest, 7, Vector2::ZERO));
+
+    assert_eq!(first, second);
+    assert!(board.claim_posted(7, JobKind::Harvest, "Owner", 0.0, 30.0));
+    assert!(!board.claim_posted(7, JobKind::Harvest, "Other", 0.0, 30.0));
+
+    board.withdraw(7, JobKind::Harvest);
+    assert!(board.jobs().is_empty());
+    assert!(board.claim_posted(7, JobKind::Harvest, "Other", 0.0, 30.0));
+}
@@ -0,0 +1,35 @@
+use market_and_mastery::resources::labour_market::{pick_better_profession, LabourMarket, WageSample};
+
+fn sample(job: &'static str, agent: &str, income: f32, duration: f32, time: f64) -> WageSample {
+    WageSample { job, agent: agent.to_string(), income, duration, time }
+}
+
+#[test]
+fn test_wages_only_count_samples_in_window() {
+    let mut market = LabourMarket::new();
+    market.record(sample("Farmer", "A", 100.0, 10.0, 0.0));
+    market.record(sample("Farmer", "A", 4.0, 2.0, 50.0));
+    market.record(sample("Farmer", "B", 8.0, 2.0, 55.0));
+
+    assert_eq!(market.market_wage("Farmer", 60.0, 30.0), Some(3.0));
+    assert_eq!(market.agent_wage("A", "Farmer", 60.0, 30.0), Some(2.0));
+    assert_eq!(market.market_wage("Guard", 60.0, 30.0), None);
+}
+
+#[test]
+fn test_pick_better_profession_needs_clear_raise() {
+    let wages = [("Berry picker", Some(1.2)), ("Guard", None)];
+
+    assert_eq!(pick_better_profession(Some(1.0), &wages, 0.3, 0.0), None);
+    assert_eq!(pick_better_profession(Some(0.9), &wages, 0.3, 0.0), Some("Berry picker"));
+    assert_eq!(pick_better_profession(Some(0.0), &[("Guard", Some(0.0))], 0.3, 0.0), None);
+    assert_eq!(pick_better_profession(None, &wages, 0.3, 0.0), None);
+}
+
+#[test]
+fn test_pick_better_profession_takes_retraining_cost_off_the_raise() {
+    let wages = [("Berry picker", Some(1.5))];
+
+    assert_eq!(pick_better_profession(Some(1.0), &wages, 0.3, 0.1), Some("Berry picker"));
+    assert_eq!(pick_better_profession(Some(1.0), &wages, 0.3, 0.3), None);
+}
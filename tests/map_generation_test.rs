@@ -0,0 +1,45 @@
+use std::collections::HashSet;
+
+use market_and_mastery::world::map_generation::{generate_map, map_generator_config};
+use market_and_mastery::world::terrain::{Structure, TerrainType};
+
+#[test]
+fn test_same_seed_gives_the_same_world() {
+    let config = map_generator_config();
+
+    let first = generate_map(7, &config);
+    let second = generate_map(7, &config);
+    let other = generate_map(8, &config);
+
+    assert_eq!(first.to_ascii(), second.to_ascii());
+    assert_eq!(first.deposits, second.deposits);
+    assert_ne!(first.to_ascii(), other.to_ascii());
+}
+
+#[test]
+fn test_resources_stand_on_matching_terrain_and_never_share_a_cell() {
+    for seed in 0..5 {
+        let map = generate_map(seed, &map_generator_config());
+
+        assert!(map.trees.iter().all(|cell| map.terrain.terrain_at(*cell) == TerrainType::Forest));
+        assert!(map.bushes.iter().all(|cell| map.terrain.terrain_at(*cell) == TerrainType::Grass));
+        assert!(map.deposits.iter().all(|deposit| map.terrain.allows(deposit.cell, Structure::Quarry)));
+        let occupied: Vec<_> = map.occupied_cells().collect();
+        let unique: HashSet<_> = occupied.iter().collect();
+        assert_eq!(occupied.len(), unique.len());
+    }
+}
+
+#[test]
+fn test_spawn_points_are_free_grass_around_the_origin() {
+    let config = map_generator_config();
+    let map = generate_map(3, &config);
+
+    assert_eq!(map.spawn_points.len(), config.spawn_point_count);
+    assert_eq!(map.spawn_points[0], (0, 0));
+    let occupied: HashSet<_> = map.occupied_cells().collect();
+    for cell in &map.spawn_points {
+        assert!(map.terrain.allows(*cell, Structure::Home));
+        assert!(!occupied.contains(cell));
+    }
+}
@@ -0,0 +1,46 @@
+use approx::assert_relative_eq;
+use market_and_mastery::building::{extraction_rate, next_extracted, MIN_EXTRACTION_SHARE};
+use market_and_mastery::resources::inventory::InventoryResource;
+
+#[test]
+fn test_extraction_slows_down_as_deposit_depletes() {
+    let full = extraction_rate(2.0, 20, 20);
+    let half = extraction_rate(2.0, 10, 20);
+    let nearly_empty = extraction_rate(2.0, 1, 20);
+
+    assert_relative_eq!(full, 2.0, epsilon = 1e-6);
+    assert!(half < full && nearly_empty < half);
+    assert!(nearly_empty >= 2.0 * MIN_EXTRACTION_SHARE);
+}
+
+#[test]
+fn test_exhausted_deposit_yields_nothing() {
+    assert_relative_eq!(extraction_rate(2.0, 0, 20), 0.0);
+    assert_relative_eq!(extraction_rate(2.0, 5, 0), 0.0);
+}
+
+#[test]
+fn test_extraction_spreads_ore_evenly_through_the_deposit() {
+    let (mut stone, mut ore) = (20, 5);
+    let mut extracted = Vec::new();
+    while let Some(resource) = next_extracted(stone, ore, 20, 5) {
+        match resource {
+            InventoryResource::Stone => stone -= 1,
+            _ => ore -= 1,
+        }
+        extracted.push(resource);
+    }
+
+    assert_eq!(extracted.len(), 25);
+    assert_eq!(extracted.iter().filter(|resource| **resource == InventoryResource::Ore).count(), 5);
+    assert_eq!(extracted[0], InventoryResource::Stone);
+    assert_eq!(extracted[1], InventoryResource::Ore);
+    assert!(extracted.windows(2).all(|pair| pair != [InventoryResource::Ore, InventoryResource::Ore]));
+}
+
+#[test]
+fn test_extraction_takes_what_is_left() {
+    assert_eq!(next_extracted(0, 3, 20, 5), Some(InventoryResource::Ore));
+    assert_eq!(next_extracted(2, 0, 20, 5), Some(InventoryResource::Stone));
+    assert_eq!(next_extracted(0, 0, 20, 5), None);
+}
@@ -1,4 +1,4 @@
-use market_and_mastery::behaviour::move_behaviour::{MoveBehaviour, Result, MoveBehaviourConfig};
+use market_and_mastery::behaviour::move_behaviour::{FailureReason, MoveBehaviour, Result, MoveBehaviourConfig};
 use godot::prelude::*;
 use approx::assert_relative_eq;
 
@@ -9,6 +9,9 @@ fn test_move_agent_success() {
         speed: 100.0,
         max_step_height: 20.0,
         step_period: 0.1,
+        pathfinding: false,
+        steering: None,
+        stuck_timeout: 0.0,
     };
     let mut behaviour = MoveBehaviour::new(config);
     let current_pos = Vector2::new(0.0, 0.0);
@@ -27,6 +30,9 @@ fn test_move_agent_running() {
         speed: 100.0,
         max_step_height: 20.0,
         step_period: 0.1,
+        pathfinding: false,
+        steering: None,
+        stuck_timeout: 0.0,
     };
     let mut behaviour = MoveBehaviour::new(config);
     let current_pos = Vector2::new(0.0, 0.0);
@@ -40,3 +46,74 @@ fn test_move_agent_running() {
     // Y position will vary due to step height
 }
 
+
+#[test]
+fn test_move_agent_without_target_fails() {
+    let config = MoveBehaviourConfig {
+        speed: 100.0,
+        max_step_height: 20.0,
+        step_period: 0.1,
+        pathfinding: false,
+        steering: None,
+        stuck_timeout: 0.0,
+    };
+    let mut behaviour = MoveBehaviour::new(config);
+
+    let (result, _) = behaviour.move_agent(0.1);
+
+    assert_eq!(result, Result::Failure(FailureReason::NoTarget));
+}
+
+#[test]
+fn test_move_agent_fails_when_it_stops_getting_closer() {
+    let config = MoveBehaviourConfig {
+        speed: 100.0,
+        max_step_height: 0.0,
+        step_period: 0.1,
+        pathfinding: false,
+        steering: None,
+        stuck_timeout: 1.0,
+    };
+    let mut behaviour = MoveBehaviour::new(config);
+    let mut target_pos = Vector2::new(100.0, 0.0);
+    behaviour.start_moving(Vector2::new(0.0, 0.0), target_pos);
+
+    // The target runs away as fast as the agent walks
+    let mut result = Result::Running;
+    let mut ticks = 0;
+    while result == Result::Running && ticks < 100 {
+        target_pos.x += 10.0;
+        behaviour.retarget(target_pos);
+        result = behaviour.move_agent(0.1).0;
+        ticks += 1;
+    }
+
+    assert_eq!(result, Result::Failure(FailureReason::Stuck));
+    assert!(ticks >= 10);
+}
+
+#[test]
+fn test_move_agent_follows_waypoints() {
+    let config = MoveBehaviourConfig {
+        speed: 100.0,
+        max_step_height: 0.0,
+        step_period: 0.1,
+        pathfinding: false,
+        steering: None,
+        stuck_timeout: 0.0,
+    };
+    let mut behaviour = MoveBehaviour::new(config);
+    let target_pos = Vector2::new(10.0, 10.0);
+
+    behaviour.start_moving_along(Vector2::new(0.0, 0.0), vec![Vector2::new(10.0, 0.0), target_pos]);
+    let (result, position) = behaviour.move_agent(0.15); // Past the corner and 5 units on
+
+    assert_eq!(result, Result::Running);
+    assert_relative_eq!(position.x, 10.0, epsilon = 0.001);
+    assert_relative_eq!(position.y, 5.0, epsilon = 0.001);
+    assert_eq!(behaviour.target(), Some(target_pos));
+
+    let (result, position) = behaviour.move_agent(0.1);
+    assert_eq!(result, Result::Success);
+    assert_eq!(position, target_pos);
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use market_and_mastery::behaviour::pathfinding::{find_path, Cell, CostGrid};
+
+/// Open ground costing 1.0, except for the listed cells
+struct TestGrid {
+    cells: HashMap<Cell, Option<f32>>,
+}
+
+impl TestGrid {
+    fn new(cells: &[(Cell, Option<f32>)]) -> Self {
+        Self { cells: cells.iter().copied().collect() }
+    }
+}
+
+impl CostGrid for TestGrid {
+    fn move_cost(&self, cell: Cell) -> Option<f32> {
+        self.cells.get(&cell).copied().unwrap_or(Some(1.0))
+    }
+}
+
+#[test]
+fn test_path_walks_around_a_wall() {
+    let grid = TestGrid::new(&[((1, -1), None), ((1, 0), None), ((1, 1), None)]);
+
+    let path = find_path(&grid, (0, 0), (2, 0)).unwrap();
+
+    assert_eq!(path.last(), Some(&(2, 0)));
+    assert!(!path.contains(&(0, 0)));
+    assert!(path.iter().all(|cell| grid.move_cost(*cell).is_some()));
+    // Around either end of the wall without cutting its corners
+    assert_eq!(path.len(), 6);
+}
+
+#[test]
+fn test_path_prefers_cheaper_cells() {
+    let grid = TestGrid::new(&[((1, 0), Some(5.0))]);
+
+    let path = find_path(&grid, (0, 0), (2, 0)).unwrap();
+
+    assert!(!path.contains(&(1, 0)));
+    assert_eq!(path.len(), 2);
+}
+
+#[test]
+fn test_occupied_goal_is_reachable_but_walled_in_goal_is_not() {
+    let occupied_goal = TestGrid::new(&[((3, 0), None)]);
+    assert_eq!(find_path(&occupied_goal, (0, 0), (3, 0)), Some(vec![(1, 0), (2, 0), (3, 0)]));
+
+    let ring: Vec<(Cell, Option<f32>)> = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)]
+        .iter()
+        .map(|&(x, y)| ((x + 5, y), None))
+        .collect();
+    let walled_in = TestGrid::new(&ring);
+    assert_eq!(find_path(&walled_in, (0, 0), (5, 0)), None);
+}
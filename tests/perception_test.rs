@@ -0,0 +1,11 @@
+use godot::prelude::*;
+use market_and_mastery::behaviour::perception::nearest_within;
+
+#[test]
+fn test_nearest_within_ignores_positions_out_of_sight() {
+    let positions = [Vector2::new(300.0, 0.0), Vector2::new(0.0, 120.0), Vector2::new(-80.0, 0.0)];
+
+    assert_eq!(nearest_within(Vector2::ZERO, 100.0, &positions), Some(2));
+    assert_eq!(nearest_within(Vector2::new(0.0, 200.0), 100.0, &positions), Some(1));
+    assert_eq!(nearest_within(Vector2::new(1000.0, 0.0), 100.0, &positions), None);
+}
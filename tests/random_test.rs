@@ -0,0 +1,13 @@
+use market_and_mastery::behaviour::random::seeded_rng;
+use rand::Rng;
+
+fn draw(name: &str) -> Vec<u32> {
+    let mut rng = seeded_rng(name);
+    (0..5).map(|_| rng.gen()).collect()
+}
+
+#[test]
+fn test_seeded_rng_repeats_per_name() {
+    assert_eq!(draw("Wolf"), draw("Wolf"));
+    assert_ne!(draw("Wolf"), draw("Wolf2"));
+}
@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use godot::prelude::*;
+use market_and_mastery::behaviour::pathfinding::{Cell, CostGrid};
+use market_and_mastery::behaviour::steering::{
+    avoidance_velocity, queue_factor, walkable_offset, Crowd, Mover, SteeringConfig,
+};
+use market_and_mastery::world::terrain::{TerrainMap, TerrainType};
+
+/// Terrain with buildings standing on some of its cells
+struct TestGrid {
+    terrain: TerrainMap,
+    buildings: HashSet<Cell>,
+}
+
+impl CostGrid for TestGrid {
+    fn move_cost(&self, cell: Cell) -> Option<f32> {
+        if self.buildings.contains(&cell) {
+            None
+        } else {
+            self.terrain.move_cost(cell)
+        }
+    }
+}
+
+fn cell_of(position: Vector2) -> Cell {
+    ((position.x / 100.0).floor() as i32, (position.y / 100.0).floor() as i32)
+}
+
+fn config() -> SteeringConfig {
+    SteeringConfig {
+        separation_radius: 60.0,
+        avoidance_horizon: 1.0,
+        max_steering_share: 0.6,
+        queue_radius: 250.0,
+        queue_spacing: 80.0,
+    }
+}
+
+fn mover(id: u64, position: Vector2, velocity: Vector2, target: Vector2) -> Mover {
+    Mover { id, position, velocity, target }
+}
+
+#[test]
+fn test_agents_on_the_same_spot_are_pushed_apart_the_same_way_every_time() {
+    let first = mover(1, Vector2::ZERO, Vector2::ZERO, Vector2::new(500.0, 0.0));
+    let second = mover(2, Vector2::ZERO, Vector2::ZERO, Vector2::new(500.0, 0.0));
+
+    let first_push = avoidance_velocity(&first, &[second], 100.0, &config());
+    let second_push = avoidance_velocity(&second, &[first], 100.0, &config());
+
+    assert!(first_push.length() > 0.0);
+    assert!((first_push + second_push).length() < 0.001);
+    assert_eq!(first_push, avoidance_velocity(&first, &[second], 100.0, &config()));
+    assert!(first_push.length() <= 60.0 + 0.001);
+}
+
+#[test]
+fn test_agents_meeting_head_on_both_give_way_to_their_right() {
+    let east = mover(1, Vector2::new(-75.0, 0.0), Vector2::new(100.0, 0.0), Vector2::new(500.0, 0.0));
+    let west = mover(2, Vector2::new(75.0, 0.0), Vector2::new(-100.0, 0.0), Vector2::new(-500.0, 0.0));
+
+    let east_steering = avoidance_velocity(&east, &[west], 100.0, &config());
+    let west_steering = avoidance_velocity(&west, &[east], 100.0, &config());
+
+    // Right of east is down the screen, right of west is up
+    assert!(east_steering.y > 0.0);
+    assert!(west_steering.y < 0.0);
+}
+
+#[test]
+fn test_agents_queue_behind_the_one_closest_to_a_shared_target() {
+    let door = Vector2::new(0.0, 0.0);
+    let front = mover(5, Vector2::new(50.0, 0.0), Vector2::ZERO, door);
+    let behind = mover(1, Vector2::new(80.0, 0.0), Vector2::ZERO, door);
+    let elsewhere = mover(2, Vector2::new(60.0, 0.0), Vector2::ZERO, Vector2::new(900.0, 0.0));
+
+    assert_eq!(queue_factor(&front, &[behind, elsewhere], &config()), 1.0);
+    assert_eq!(queue_factor(&behind, &[front, elsewhere], &config()), 0.0);
+
+    let far_away = mover(3, Vector2::new(400.0, 0.0), Vector2::ZERO, door);
+    assert_eq!(queue_factor(&far_away, &[front], &config()), 1.0);
+}
+
+#[test]
+fn test_crowd_shows_the_last_frame_in_id_order() {
+    let mut crowd = Crowd::new(200.0);
+    let target = Vector2::new(1000.0, 0.0);
+    crowd.report(1, mover(7, Vector2::new(10.0, 0.0), Vector2::ZERO, target));
+    crowd.report(1, mover(3, Vector2::new(250.0, 0.0), Vector2::ZERO, target));
+
+    assert!(crowd.neighbours(1, 9, Vector2::ZERO, 300.0).is_empty());
+    let ids: Vec<u64> = crowd.neighbours(2, 9, Vector2::ZERO, 300.0).iter().map(|mover| mover.id).collect();
+    assert_eq!(ids, vec![3, 7]);
+    assert_eq!(crowd.neighbours(2, 7, Vector2::ZERO, 100.0).len(), 0);
+
+    // Reports from long ago are forgotten
+    assert!(crowd.neighbours(5, 9, Vector2::ZERO, 300.0).is_empty());
+}
+
+#[test]
+fn test_steering_into_water_slides_along_the_shore() {
+    let mut terrain = TerrainMap::default();
+    terrain.set_terrain((1, 0), TerrainType::Water);
+    terrain.set_terrain((1, 1), TerrainType::Water);
+    let grid = TestGrid { terrain, buildings: HashSet::new() };
+    let position = Vector2::new(90.0, 90.0);
+
+    // Diagonally into the water only the step down the shore is left
+    let steered = walkable_offset(&grid, cell_of, position, Vector2::new(20.0, 20.0));
+    assert_eq!(steered, Vector2::new(90.0, 110.0));
+
+    // Straight into the water there is nothing left to walk
+    assert_eq!(walkable_offset(&grid, cell_of, position, Vector2::new(20.0, 0.0)), position);
+
+    // Within the own cell and onto open ground the whole offset is walked
+    assert_eq!(walkable_offset(&grid, cell_of, position, Vector2::new(5.0, -5.0)), Vector2::new(95.0, 85.0));
+    assert_eq!(walkable_offset(&grid, cell_of, position, Vector2::new(-20.0, 20.0)), Vector2::new(70.0, 110.0));
+}
+
+#[test]
+fn test_steering_into_a_building_slides_along_its_wall() {
+    let grid = TestGrid { terrain: TerrainMap::default(), buildings: HashSet::from([(0, 1), (1, 1)]) };
+    let position = Vector2::new(90.0, 90.0);
+
+    let steered = walkable_offset(&grid, cell_of, position, Vector2::new(20.0, 20.0));
+    assert_eq!(steered, Vector2::new(110.0, 90.0));
+
+    // An agent that starts out on a building may still move about on it
+    let home = Vector2::new(50.0, 150.0);
+    assert_eq!(walkable_offset(&grid, cell_of, home, Vector2::new(10.0, 10.0)), Vector2::new(60.0, 160.0));
+    assert_eq!(walkable_offset(&grid, cell_of, home, Vector2::new(-60.0, 0.0)), Vector2::new(-10.0, 150.0));
+}
@@ -0,0 +1,40 @@
+use market_and_mastery::behaviour::pathfinding::find_path;
+use market_and_mastery::world::terrain::{Structure, TerrainMap, TerrainType};
+
+#[test]
+fn test_terrain_decides_what_can_be_built() {
+    let mut terrain = TerrainMap::default();
+    terrain.set_terrain((1, 0), TerrainType::FertileSoil);
+    terrain.set_terrain((2, 0), TerrainType::Water);
+    terrain.set_terrain((3, 0), TerrainType::Rock);
+
+    assert!(terrain.allows((0, 0), Structure::Home));
+    assert!(terrain.allows((0, 0), Structure::Field));
+    assert!(terrain.allows((1, 0), Structure::Field));
+    assert!(!terrain.allows((1, 0), Structure::Home));
+    assert!(!terrain.allows((2, 0), Structure::Field));
+    assert!(terrain.allows((3, 0), Structure::Quarry));
+
+    terrain.set_terrain((1, 0), TerrainType::Grass);
+    assert_eq!(terrain.cells().count(), 2);
+}
+
+#[test]
+fn test_paths_cross_no_water_and_follow_roads() {
+    let mut terrain = TerrainMap::default();
+    for cell_y in -5..=5 {
+        terrain.set_terrain((2, cell_y), TerrainType::Water);
+    }
+    // A bridge south of the straight line
+    terrain.set_terrain((2, 3), TerrainType::Road);
+
+    let path = find_path(&terrain, (0, 0), (4, 0)).unwrap();
+
+    assert!(path.contains(&(2, 3)));
+    assert!(path.iter().all(|cell| terrain.terrain_at(*cell) != TerrainType::Water));
+
+    // Without the bridge the way leads around the end of the river
+    terrain.set_terrain((2, 3), TerrainType::Water);
+    let path = find_path(&terrain, (0, 0), (4, 0)).unwrap();
+    assert!(path.iter().any(|(_, cell_y)| cell_y.abs() > 5));
+}
@@ -0,0 +1,55 @@
+use godot::prelude::*;
+use market_and_mastery::behaviour::threat_awareness::{ThreatAwareness, ThreatAwarenessConfig};
+
+fn make_awareness() -> ThreatAwareness {
+    ThreatAwareness::new(ThreatAwarenessConfig { flee_distance: 100.0, safe_distance: 200.0 })
+}
+
+#[test]
+fn test_threat_awareness_keeps_fleeing_until_safe_distance() {
+    let mut awareness = make_awareness();
+    let agent = Vector2::ZERO;
+
+    assert!(!awareness.update(agent, &[Vector2::new(150.0, 0.0)]));
+    assert!(awareness.update(agent, &[Vector2::new(90.0, 0.0)]));
+    assert!(awareness.update(agent, &[Vector2::new(150.0, 0.0)]));
+    assert!(!awareness.update(agent, &[Vector2::new(250.0, 0.0)]));
+    assert!(!awareness.update(agent, &[]));
+}
+
+#[test]
+fn test_flee_target_prefers_home_and_otherwise_runs_away() {
+    let awareness = make_awareness();
+    let agent = Vector2::ZERO;
+    let wolf = Vector2::new(50.0, 0.0);
+    let home = Vector2::new(0.0, 300.0);
+
+    assert_eq!(awareness.flee_target(agent, Some(home), &[wolf]), home);
+    let away = awareness.flee_target(agent, None, &[wolf]);
+    assert!(away.x < 0.0);
+    assert!(away.distance_to(wolf) > 200.0);
+}
+
+#[test]
+fn test_flee_target_avoids_home_behind_the_threat() {
+    let awareness = make_awareness();
+    let agent = Vector2::ZERO;
+    let wolf = Vector2::new(80.0, 0.0);
+    let home = Vector2::new(400.0, 20.0);
+
+    let target = awareness.flee_target(agent, Some(home), &[wolf]);
+    assert_ne!(target, home);
+    assert!(target.x < 0.0);
+}
+
+#[test]
+fn test_flee_target_changes_when_the_threat_moves_onto_the_way_home() {
+    let awareness = make_awareness();
+    let agent = Vector2::ZERO;
+    let home = Vector2::new(0.0, 300.0);
+
+    assert_eq!(awareness.flee_target(agent, Some(home), &[Vector2::new(-80.0, 0.0)]), home);
+    let target = awareness.flee_target(agent, Some(home), &[Vector2::new(0.0, 80.0)]);
+    assert_ne!(target, home);
+    assert!(target.y < 0.0);
+}
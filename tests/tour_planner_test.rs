@@ -0,0 +1,46 @@
+use approx::assert_relative_eq;
+use godot::prelude::*;
+use market_and_mastery::behaviour::tour_planner::{plan_shortest_tour, tour_length};
+
+#[test]
+fn test_plan_shortest_tour_visits_stops_in_order_along_the_way() {
+    let start = Vector2::new(0.0, 0.0);
+    let home = Vector2::new(400.0, 0.0);
+    let stops = [Vector2::new(300.0, 0.0), Vector2::new(100.0, 0.0), Vector2::new(200.0, 0.0)];
+
+    let tour = plan_shortest_tour(start, &stops, Some(home), 3);
+
+    assert_eq!(tour, vec![1, 2, 0]);
+    assert_relative_eq!(tour_length(start, &stops, Some(home), &tour), 400.0, epsilon = 0.001);
+}
+
+#[test]
+fn test_plan_shortest_tour_limits_stops_to_capacity() {
+    let start = Vector2::new(0.0, 0.0);
+    let home = Vector2::new(0.0, 100.0);
+    let stops = [Vector2::new(1000.0, 0.0), Vector2::new(0.0, 50.0), Vector2::new(-1000.0, 0.0)];
+
+    let tour = plan_shortest_tour(start, &stops, Some(home), 1);
+
+    assert_eq!(tour, vec![1]);
+}
+
+#[test]
+fn test_plan_shortest_tour_without_stops_is_empty() {
+    let tour = plan_shortest_tour(Vector2::ZERO, &[], None, 3);
+
+    assert!(tour.is_empty());
+}
+
+#[test]
+fn test_plan_shortest_tour_over_many_stops_counts_the_way_home() {
+    let start = Vector2::new(0.0, 0.0);
+    let home = Vector2::new(0.0, 1000.0);
+    let mut stops: Vec<Vector2> = (0..10).map(|index| Vector2::new(5000.0, index as f32 * 10.0)).collect();
+    stops.push(Vector2::new(-100.0, 0.0));
+    stops.push(Vector2::new(0.0, 500.0));
+
+    let tour = plan_shortest_tour(start, &stops, Some(home), 1);
+
+    assert_eq!(tour, vec![11]);
+}
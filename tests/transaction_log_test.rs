@@ -0,0 +1,45 @@
+use market_and_mastery::resources::inventory::{Inventory, InventoryResource};
+use market_and_mastery::resources::transaction_log::{
+    InventoryOwner, TransactionContext, TransactionLog, TransactionReason,
+};
+
+fn context(reason: TransactionReason, tick: u64) -> TransactionContext {
+    TransactionContext { agent: Some("Farmer".to_string()), tick, reason }
+}
+
+#[test]
+fn test_goods_are_conserved_between_inventories() {
+    let mut log = TransactionLog::new();
+    let mut carry = Inventory::with_capacity(InventoryOwner::Agent("Farmer".to_string()), 3);
+    let mut home = Inventory::new(InventoryOwner::Building(7));
+
+    let harvested = log.produce(&mut carry, InventoryResource::Wheat, 5, &context(TransactionReason::Harvest, 1));
+    log.transfer_all(&mut carry, &mut home, &context(TransactionReason::Deposit, 2));
+    let spoiled = log.consume(&mut home, InventoryResource::Wheat, 1, &context(TransactionReason::Spoilage, 3));
+
+    assert_eq!(harvested, 3);
+    assert_eq!(spoiled, 1);
+    for inventory in [&carry, &home] {
+        let balance = log.balance(inventory.owner(), InventoryResource::Wheat);
+        assert_eq!(balance, inventory.amount(InventoryResource::Wheat));
+    }
+    let produced = log.balance(&InventoryOwner::World, InventoryResource::Wheat);
+    assert_eq!(-produced, carry.total() + home.total());
+}
+
+#[test]
+fn test_log_is_queryable_per_building_and_agent() {
+    let mut log = TransactionLog::new();
+    let mut carry = Inventory::new(InventoryOwner::Agent("Farmer".to_string()));
+    let mut home = Inventory::new(InventoryOwner::Building(7));
+
+    log.produce(&mut carry, InventoryResource::Wheat, 2, &context(TransactionReason::Harvest, 1));
+    log.transfer_all(&mut carry, &mut home, &context(TransactionReason::Deposit, 2));
+
+    assert_eq!(log.entries_for_agent("Farmer").len(), 2);
+    let building_entries = log.entries_for_building(7);
+    assert_eq!(building_entries.len(), 1);
+    assert_eq!(building_entries[0].reason, TransactionReason::Deposit);
+    assert_eq!(building_entries[0].tick, 2);
+    assert!(log.entries_for_agent("Nobody").is_empty());
+}
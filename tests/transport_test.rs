@@ -0,0 +1,104 @@
+use godot::prelude::*;
+use market_and_mastery::resources::inventory::InventoryResource;
+use market_and_mastery::resources::transaction_log::InventoryOwner;
+use market_and_mastery::resources::transport::{
+    plan_transport_route, TransportBoard, TransportRequest, TransportStopKind,
+};
+
+fn request(amount: i32, source: i64, destination: i64, urgency: f32) -> TransportRequest {
+    TransportRequest::new(
+        InventoryResource::Wheat,
+        amount,
+        InventoryOwner::Building(source),
+        Vector2::new(source as f32 * 100.0, 0.0),
+        InventoryOwner::Building(destination),
+        Vector2::new(destination as f32 * 100.0, 100.0),
+    )
+    .with_urgency(urgency)
+}
+
+#[test]
+fn test_claim_batch_takes_most_urgent_requests_that_fit() {
+    let mut board = TransportBoard::new();
+    let low = board.post(request(2, 1, 2, 0.1));
+    let high = board.post(request(3, 3, 4, 0.9));
+    let too_big = board.post(request(4, 5, 6, 0.5));
+
+    let batch = board.claim_batch("Hauler", 5);
+    let ids: Vec<u64> = batch.iter().map(|request| request.id).collect();
+
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&high) && ids.contains(&low) && !ids.contains(&too_big));
+    assert_eq!(board.open_requests().map(|request| request.id).collect::<Vec<_>>(), vec![too_big]);
+
+    board.release_all("Hauler");
+    assert_eq!(board.open_requests().count(), 3);
+}
+
+#[test]
+fn test_claim_batch_splits_a_request_too_big_to_carry() {
+    let mut board = TransportBoard::new();
+    let id = board.post(request(10, 1, 2, 0.5));
+
+    let batch = board.claim_batch("Hauler", 4);
+
+    assert_eq!(batch.len(), 1);
+    assert_eq!((batch[0].id, batch[0].amount), (id, 4));
+    let rest: Vec<&TransportRequest> = board.open_requests().collect();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].amount, 6);
+    assert!(board.has_open_request_from(&InventoryOwner::Building(1)));
+    board.complete(id);
+    assert_eq!(board.requests().len(), 1);
+}
+
+#[test]
+fn test_release_only_hands_back_requests_of_that_hauler() {
+    let mut board = TransportBoard::new();
+    let first = board.post(request(2, 1, 2, 0.5));
+    let second = board.post(request(2, 3, 4, 0.5));
+    board.claim_batch("Hauler", 4);
+
+    board.release(first, "Other hauler");
+    assert_eq!(board.open_requests().count(), 0);
+
+    board.release(first, "Hauler");
+    assert_eq!(board.open_requests().map(|request| request.id).collect::<Vec<_>>(), vec![first]);
+    assert!(board.requests().iter().any(|request| request.id == second && request.hauler.is_some()));
+}
+
+#[test]
+fn test_split_off_leaves_the_rest_open() {
+    let mut board = TransportBoard::new();
+    let id = board.post(request(5, 1, 2, 0.5));
+    board.claim_batch("Hauler", 5);
+
+    let rest = board.split_off(id, 3).unwrap();
+
+    assert_eq!(board.open_requests().map(|request| (request.id, request.amount)).collect::<Vec<_>>(), vec![(rest, 3)]);
+    assert_eq!(board.requests().iter().find(|request| request.id == id).unwrap().amount, 2);
+    assert_eq!(board.split_off(id, 2), None);
+}
+
+#[test]
+fn test_route_picks_everything_up_before_dropping_off() {
+    let mut board = TransportBoard::new();
+    board.post(request(1, 1, 4, 0.0));
+    board.post(request(1, 2, 3, 0.0));
+    let batch = board.claim_batch("Hauler", 2);
+
+    let route = plan_transport_route(Vector2::ZERO, &batch);
+
+    let kinds: Vec<TransportStopKind> = route.iter().map(|stop| stop.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TransportStopKind::Pickup,
+            TransportStopKind::Pickup,
+            TransportStopKind::Dropoff,
+            TransportStopKind::Dropoff
+        ]
+    );
+    assert_eq!(route[0].position, Vector2::new(100.0, 0.0));
+    assert_eq!(route[1].position, Vector2::new(200.0, 0.0));
+}
@@ -0,0 +1,48 @@
+use bonsai_bt::{Action, Select, Wait};
+use market_and_mastery::behaviour::tree_behaviour::{AgentAction, MoveTarget};
+use market_and_mastery::behaviour::tree_loader::{load_behaviour_tree, TreeNodeRegistry};
+
+#[test]
+fn test_load_behaviour_tree_builds_registered_nodes() {
+    let source = r#"{ "Select": ["IsCarrying", { "MoveTo": { "target": "Home" } }, { "Wait": { "seconds": 0.5 } }] }"#;
+
+    let tree = load_behaviour_tree(source, &TreeNodeRegistry::agent_nodes()).unwrap();
+
+    let expected =
+        Select(vec![Action(AgentAction::IsCarrying), Action(AgentAction::MoveTo(MoveTarget::Home)), Wait(0.5)]);
+    assert_eq!(tree, expected);
+}
+
+#[test]
+fn test_load_behaviour_tree_reports_every_problem_with_location() {
+    let source = r#"{
+        "Sequence": [
+            "Dance",
+            { "MoveTo": { "target": "Moon" } },
+            { "Deposit": { "amount": 3 } }
+        ]
+    }"#;
+
+    let errors = load_behaviour_tree(source, &TreeNodeRegistry::agent_nodes()).unwrap_err();
+
+    let locations: Vec<&str> = errors.iter().map(|error| error.location.as_str()).collect();
+    assert_eq!(locations, vec!["root.Sequence[0]", "root.Sequence[1].MoveTo", "root.Sequence[2].Deposit"]);
+    assert!(errors[0].message.contains("unknown node `Dance`"));
+    assert!(errors[1].message.contains("Moon"));
+    assert!(errors[2].message.contains("unknown parameter `amount`"));
+}
+
+#[test]
+fn test_load_behaviour_tree_reports_syntax_error_position() {
+    let errors = load_behaviour_tree("{ \"Sequence\": [", &TreeNodeRegistry::agent_nodes()).unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].location.starts_with("line 1"));
+}
+
+#[test]
+fn test_shipped_farmer_tree_is_valid() {
+    let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/godot/trees/farmer.json")).unwrap();
+
+    assert!(load_behaviour_tree(&source, &TreeNodeRegistry::agent_nodes()).is_ok());
+}
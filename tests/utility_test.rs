@@ -0,0 +1,36 @@
+use market_and_mastery::behaviour::utility::{
+    pick_best_job, score_job, unavailable_job, UtilityInputs, UtilityWeights,
+};
+
+fn make_weights() -> UtilityWeights {
+    UtilityWeights { household_need: 2.0, price: 1.0, distance: 1.0, skill: 1.0, distance_scale: 100.0 }
+}
+
+#[test]
+fn test_score_job_sums_weighted_terms() {
+    let inputs = UtilityInputs { household_need: 0.5, price: 3.0, distance: 100.0 };
+
+    let score = score_job("Farmer", inputs, 0.25, &make_weights());
+
+    assert_eq!(score.household_need, 1.0);
+    assert_eq!(score.price, 3.0);
+    assert_eq!(score.distance, 0.5);
+    assert_eq!(score.skill, 0.25);
+    assert_eq!(score.total, 4.75);
+}
+
+#[test]
+fn test_pick_best_job_skips_unavailable_jobs() {
+    let weights = make_weights();
+    let needed = UtilityInputs { household_need: 1.0, price: 1.0, distance: 0.0 };
+    let paid = UtilityInputs { household_need: 0.0, price: 2.0, distance: 0.0 };
+
+    let scores = vec![
+        unavailable_job("Guard"),
+        score_job("Farmer", paid, 0.0, &weights),
+        score_job("Picker", needed, 0.0, &weights),
+    ];
+    assert_eq!(pick_best_job(&scores), Some(2));
+
+    assert_eq!(pick_best_job(&[unavailable_job("Guard")]), None);
+}
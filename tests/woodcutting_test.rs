@@ -0,0 +1,24 @@
+use market_and_mastery::building::Reservation;
+
+#[test]
+fn test_reserved_tree_is_only_free_for_its_woodcutter() {
+    let mut reservation = Reservation::default();
+
+    assert!(reservation.reserve("Alice"));
+    assert!(reservation.is_free_for("Alice"));
+    assert!(!reservation.is_free_for("Bob"));
+    assert!(!reservation.reserve("Bob"));
+    assert!(reservation.reserve("Alice"));
+}
+
+#[test]
+fn test_only_the_holder_releases_a_tree() {
+    let mut reservation = Reservation::default();
+    reservation.reserve("Alice");
+
+    reservation.release("Bob");
+    assert!(!reservation.is_free_for("Bob"));
+
+    reservation.release("Alice");
+    assert!(reservation.reserve("Bob"));
+}